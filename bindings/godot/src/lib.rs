@@ -1,4 +1,4 @@
-use bobbin_runtime::{HostState, Runtime, Value, VariableStorage};
+use bobbin_runtime::{DeclKind, HostState, NextKind, Runtime, Value, VariableStorage};
 use godot::classes::{
     Engine, FileAccess, IResourceFormatLoader, IResourceFormatSaver, IScriptExtension,
     IScriptLanguageExtension, Os, Resource, ResourceFormatLoader, ResourceFormatSaver,
@@ -56,6 +56,14 @@ impl VariableStorage for MemoryStorage {
     fn contains(&self, name: &str) -> bool {
         self.values.read().unwrap().contains_key(name)
     }
+
+    fn iter(&self) -> Vec<(String, Value)> {
+        let values = self.values.read().unwrap();
+        values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
 }
 
 /// Host state implementation backed by a HashMap.
@@ -761,6 +769,9 @@ pub struct BobbinRuntime {
     source_path: Option<GString>,  // None if created via from_string()
     last_modified: u64,            // File modification timestamp
     poll_timer: Option<Gd<Timer>>, // Self-managed polling timer
+
+    // Whether dialogue_finished has fired for the current runtime
+    finished_signaled: bool,
 }
 
 #[godot_api]
@@ -790,6 +801,7 @@ impl BobbinRuntime {
                 source_path: None,
                 last_modified: 0,
                 poll_timer: None,
+                finished_signaled: false,
             })),
             Err(e) => {
                 godot_error!(
@@ -850,6 +862,7 @@ impl BobbinRuntime {
                     source_path,
                     last_modified,
                     poll_timer: None,
+                    finished_signaled: false,
                 });
 
                 // Start hot reload polling (debug only, requires scene tree)
@@ -893,6 +906,7 @@ impl BobbinRuntime {
         match Runtime::new(&source_str, storage_dyn, host_dyn) {
             Ok(new_runtime) => {
                 self.inner = new_runtime;
+                self.finished_signaled = false;
                 self.base_mut()
                     .emit_signal(&StringName::from("reloaded"), &[]);
                 true
@@ -1008,11 +1022,46 @@ impl BobbinRuntime {
         }
     }
 
+    // =========================================================================
+    // Dialogue Flow
+    // =========================================================================
+
+    /// Emitted when `advance()` or `select_choice()` produces a new line,
+    /// including an empty one.
+    ///
+    /// Fires after the runtime has stepped, so `current_line()` already
+    /// returns `text` and `has_more()`/`is_waiting_for_choice()` are up to date.
+    #[signal]
+    fn line_changed(text: GString);
+
+    /// Emitted once when `has_more()` becomes false.
+    ///
+    /// Fires after `line_changed` for the final line, if there is one. A
+    /// runtime whose dialogue is over from the start emits it on the first
+    /// `advance()`.
+    #[signal]
+    fn dialogue_finished();
+
+    /// Emitted when the dialogue reaches a `wait`.
+    ///
+    /// There is no current line during a wait; call `advance()` once
+    /// `seconds` have passed to continue.
+    #[signal]
+    fn wait_started(seconds: f64);
+
     #[func]
     fn advance(&mut self) {
+        if !self.inner.has_more() {
+            // A dialogue that was over from the start has had no step to
+            // report it, so the first advance does
+            self.emit_finished_once();
+            return;
+        }
         if let Err(e) = self.inner.advance() {
             godot_error!("advance failed: {}", e);
+            return;
         }
+        self.emit_step_signals();
     }
 
     #[func]
@@ -1030,6 +1079,24 @@ impl BobbinRuntime {
         self.inner.is_waiting_for_choice()
     }
 
+    /// What the dialogue stops on next: "line", "choice", "wait", or "done".
+    #[func]
+    fn peek_next(&self) -> GString {
+        let kind = match self.inner.peek_next() {
+            NextKind::Line => "line",
+            NextKind::Choice => "choice",
+            NextKind::Wait => "wait",
+            NextKind::Done => "done",
+        };
+        GString::from(kind)
+    }
+
+    /// Seconds to hold before the next `advance()`, or 0 when not paused on a `wait`.
+    #[func]
+    fn current_wait(&self) -> f64 {
+        self.inner.current_wait().unwrap_or(0.0)
+    }
+
     #[func]
     fn current_choices(&self) -> PackedStringArray {
         let choices = self.inner.current_choices();
@@ -1040,14 +1107,88 @@ impl BobbinRuntime {
         arr
     }
 
+    /// Tags of the offered choice at `index`, without the `#`.
+    #[func]
+    fn choice_tags(&self, index: i64) -> PackedStringArray {
+        let mut arr = PackedStringArray::new();
+        let Ok(index) = usize::try_from(index) else {
+            return arr;
+        };
+        for tag in self.inner.choice_tags(index) {
+            arr.push(&GString::from(tag.as_str()));
+        }
+        arr
+    }
+
+    /// Select a choice by index. Out-of-range indices are logged, not fatal.
     #[func]
-    fn select_choice(&mut self, index: i32) {
-        if let Err(e) = self.inner.select_choice(index as usize) {
+    fn select_choice(&mut self, index: i64) {
+        let Ok(index) = usize::try_from(index) else {
+            godot_error!("select_choice failed: choice index {} is negative", index);
+            return;
+        };
+        if !self.inner.is_waiting_for_choice() {
+            godot_error!("select_choice failed: runtime is not waiting for a choice");
+            return;
+        }
+        if let Err(e) = self.inner.select_choice(index) {
             godot_error!("select_choice failed: {}", e);
+            return;
         }
+        self.emit_step_signals();
+    }
+
+    /// Index of the `#default` choice on offer, or -1 if there is none.
+    #[func]
+    fn default_choice(&self) -> i64 {
+        self.inner
+            .default_choice()
+            .map_or(-1, |index| index as i64)
     }
 
-    /// Get a save variable value.
+    /// Select the `#default` choice, e.g. when a choice timer runs out.
+    #[func]
+    fn select_default(&mut self) {
+        if !self.inner.is_waiting_for_choice() {
+            godot_error!("select_default failed: runtime is not waiting for a choice");
+            return;
+        }
+        if let Err(e) = self.inner.select_default() {
+            godot_error!("select_default failed: {}", e);
+            return;
+        }
+        self.emit_step_signals();
+    }
+
+    /// Emit flow signals after a successful runtime step.
+    fn emit_step_signals(&mut self) {
+        // Empty lines are reported too; a step that ends the dialogue without
+        // a line (e.g. an empty final choice branch) has no line on show.
+        if self.inner.has_line() {
+            let text = GString::from(self.inner.current_line());
+            self.base_mut()
+                .emit_signal(&StringName::from("line_changed"), &[text.to_variant()]);
+        }
+        if let Some(seconds) = self.inner.current_wait() {
+            self.base_mut()
+                .emit_signal(&StringName::from("wait_started"), &[seconds.to_variant()]);
+        }
+        if !self.inner.has_more() {
+            self.emit_finished_once();
+        }
+    }
+
+    /// Emit `dialogue_finished` unless it has already fired for this runtime.
+    fn emit_finished_once(&mut self) {
+        if self.finished_signaled {
+            return;
+        }
+        self.finished_signaled = true;
+        self.base_mut()
+            .emit_signal(&StringName::from("dialogue_finished"), &[]);
+    }
+
+    /// Get a save variable value. Returns null if the variable is not in storage.
     #[func]
     fn get_variable(&self, name: GString) -> Variant {
         match self.storage.get(&name.to_string()) {
@@ -1057,10 +1198,18 @@ impl BobbinRuntime {
     }
 
     /// Set a save variable value.
+    ///
+    /// Accepts `int`, `float`, `bool`, and `String`. Other variant types are
+    /// rejected with an error and leave storage unchanged.
     #[func]
     fn set_variable(&self, name: GString, value: Variant) {
-        if let Some(val) = variant_to_value(&value) {
-            self.storage.set(&name.to_string(), val);
+        match variant_to_value(&value) {
+            Some(val) => self.storage.set(&name.to_string(), val),
+            None => godot_error!(
+                "set_variable failed: '{}' cannot be stored as a {:?} (expected int, float, bool, or String)",
+                name,
+                value.get_type()
+            ),
         }
     }
 
@@ -1074,6 +1223,20 @@ impl BobbinRuntime {
         dict
     }
 
+    /// How the script declares a variable: "save", "temp", "const", or
+    /// "extern". Returns an empty string for names the script does not declare.
+    #[func]
+    fn get_variable_kind(&self, name: GString) -> GString {
+        let kind = match self.inner.var_kind(&name.to_string()) {
+            Some(DeclKind::Save) => "save",
+            Some(DeclKind::Temp) => "temp",
+            Some(DeclKind::Const) => "const",
+            Some(DeclKind::Extern) => "extern",
+            None => "",
+        };
+        GString::from(kind)
+    }
+
     /// Update a host variable (game state changed).
     #[func]
     fn update_host_variable(&self, name: GString, value: Variant) {
@@ -1,8 +1,17 @@
+//! Abstract syntax tree produced by the parser and consumed by the resolver and compiler.
+
+use crate::chunk::{LogicOp, NumberFormat};
 use crate::token::Span;
 
-/// Unique identifier for AST nodes that need semantic binding.
-/// Used to track which variable reference resolves to which slot.
+/// Unique identifier for AST nodes that need semantic binding or runtime tracking.
+/// Used to track which variable reference resolves to which slot, and which
+/// lines and choices have been visited.
+///
+/// The parser numbers nodes in source order as it meets them, so ids increase
+/// through the file and parsing identical source always gives identical ids.
+/// Editing a script can shift the ids of everything after the edit.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct NodeId(pub usize);
 
 #[derive(Debug, Clone)]
@@ -10,38 +19,238 @@ pub struct Script {
     pub statements: Vec<Stmt>,
 }
 
+impl Script {
+    /// Every statement in the script, with how deeply it is nested in choice
+    /// branches (0 at the top level).
+    ///
+    /// Statements come in source order: a choice set is followed by the
+    /// statements of its first branch, then its second, and so on, before
+    /// the statement after the set.
+    pub fn walk(&self) -> Walk<'_> {
+        Walk {
+            blocks: vec![(self.statements.iter(), 0)],
+        }
+    }
+}
+
+/// Iterator over a script's statements, from [`Script::walk`].
+#[derive(Debug, Clone)]
+pub struct Walk<'a> {
+    /// Blocks still being walked, with their depth; the last is the current one
+    blocks: Vec<(std::slice::Iter<'a, Stmt>, usize)>,
+}
+
+impl<'a> Iterator for Walk<'a> {
+    type Item = (&'a Stmt, usize);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (block, depth) = self.blocks.last_mut()?;
+            let depth = *depth;
+            let Some(stmt) = block.next() else {
+                self.blocks.pop();
+                continue;
+            };
+            if let Stmt::ChoiceSet { choices, .. } = stmt {
+                // Pushed last to first, so the first branch is walked first
+                for choice in choices.iter().rev() {
+                    self.blocks.push((choice.nested.iter(), depth + 1));
+                }
+            }
+            return Some((stmt, depth));
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum Stmt {
-    Line { parts: Vec<TextPart>, span: Span },
+    Line {
+        id: NodeId,
+        parts: Vec<TextPart>,
+        span: Span,
+    },
     TempDecl(VarBindingData),
     SaveDecl(VarBindingData),
+    /// A read-only binding whose literal value is inlined at each use.
+    ConstDecl(VarBindingData),
     ExternDecl(ExternDeclData),
     Assignment(VarBindingData),
-    ChoiceSet { choices: Vec<Choice> },
+    /// Read-modify-write of a variable: `set gold += reward`
+    CompoundAssignment(CompoundAssignData),
+    ChoiceSet {
+        choices: Vec<Choice>,
+        /// From the first choice marker to the end of the last choice's text
+        span: Span,
+    },
+    /// A named jump target: `== name ==`
+    Label {
+        name: String,
+        span: Span,
+    },
+    /// An unconditional jump to a label: `goto name`
+    Goto {
+        label: String,
+        /// Span of the label name, for suggestions
+        label_span: Span,
+        span: Span,
+    },
+    /// Pauses the dialogue for a number of seconds: `wait 1.5`
+    Wait {
+        seconds: f64,
+        span: Span,
+    },
+    /// Ends the dialogue: `end`, or `done`
+    End {
+        span: Span,
+    },
+    /// Pulls in another file's statements: `include "common.bobbin"`
+    ///
+    /// Expanded by `compile_with_includes`; never reaches the compiler.
+    Include {
+        path: String,
+        /// Span of the quoted path, for missing-file errors
+        path_span: Span,
+        span: Span,
+    },
+}
+
+impl Stmt {
+    /// Source span of this statement. For choice sets, only the choice lines
+    /// are covered, not their nested branches.
+    pub fn span(&self) -> Span {
+        match self {
+            Stmt::Line { span, .. }
+            | Stmt::ChoiceSet { span, .. }
+            | Stmt::Label { span, .. }
+            | Stmt::Goto { span, .. }
+            | Stmt::Wait { span, .. }
+            | Stmt::End { span }
+            | Stmt::Include { span, .. } => *span,
+            Stmt::TempDecl(data)
+            | Stmt::SaveDecl(data)
+            | Stmt::ConstDecl(data)
+            | Stmt::Assignment(data) => data.span,
+            Stmt::ExternDecl(data) => data.span,
+            Stmt::CompoundAssignment(data) => data.span,
+        }
+    }
+
+    /// The id of the node this statement introduces: a line, or the variable
+    /// a declaration or assignment binds. `None` for statements without one;
+    /// a choice set's ids are on its choices.
+    pub fn node_id(&self) -> Option<NodeId> {
+        match self {
+            Stmt::Line { id, .. } => Some(*id),
+            Stmt::TempDecl(data)
+            | Stmt::SaveDecl(data)
+            | Stmt::ConstDecl(data)
+            | Stmt::Assignment(data) => Some(data.id),
+            Stmt::ExternDecl(data) => Some(data.id),
+            Stmt::CompoundAssignment(data) => Some(data.id),
+            Stmt::ChoiceSet { .. }
+            | Stmt::Label { .. }
+            | Stmt::Goto { .. }
+            | Stmt::Wait { .. }
+            | Stmt::End { .. }
+            | Stmt::Include { .. } => None,
+        }
+    }
+
+    /// Whether control never continues to the next statement in the same block.
+    ///
+    /// Statements after a diverging one are unreachable. Every current statement
+    /// falls through except `goto` and `end`. A label after a diverging
+    /// statement is a jump target, so it is reachable again.
+    pub fn diverges(&self) -> bool {
+        match self {
+            Stmt::Goto { .. } | Stmt::End { .. } => true,
+            Stmt::Line { .. }
+            | Stmt::TempDecl(_)
+            | Stmt::SaveDecl(_)
+            | Stmt::ConstDecl(_)
+            | Stmt::ExternDecl(_)
+            | Stmt::Assignment(_)
+            | Stmt::CompoundAssignment(_)
+            | Stmt::ChoiceSet { .. }
+            | Stmt::Label { .. }
+            | Stmt::Wait { .. }
+            | Stmt::Include { .. } => false,
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct Choice {
+    pub id: NodeId,
     pub parts: Vec<TextPart>,
     pub span: Span,
+    /// Trailing `#tags`, without the `#`, in source order
+    pub tags: Vec<String>,
+    /// Marked with `#default`: the branch the host takes when the player does
+    /// not choose in time. The marker is not kept in `tags`.
+    pub default: bool,
     /// Nested statements to execute when this choice is selected
     pub nested: Vec<Stmt>,
+    /// From the start of the first nested statement to the end of the last,
+    /// including any choices nested deeper. Empty, at the end of the choice
+    /// line, when nothing is nested.
+    pub branch_span: Span,
 }
 
-/// A part of text content - either literal text or a variable reference
+/// A part of text content - literal text, a variable reference, or a computed value
 #[derive(Debug, Clone)]
 pub enum TextPart {
     Literal {
         text: String,
         span: Span,
     },
+    /// An interpolation of a lone variable: `{gold}`
     VarRef {
         id: NodeId,
         name: String,
+        /// Just the variable name, where diagnostics about it point
+        name_span: Span,
+        /// Number format from `{name:format}`, if any
+        format: Option<NumberFormat>,
+        /// From the opening to the closing brace
+        span: Span,
+    },
+    /// An interpolation that is more than a variable name: `{random(1, 6)}`
+    Expr {
+        expr: Expr,
+        /// Number format from `{expr:format}`, if any
+        format: Option<NumberFormat>,
+        /// From the opening to the closing brace
         span: Span,
     },
 }
 
+impl TextPart {
+    pub fn span(&self) -> Span {
+        match self {
+            TextPart::Literal { span, .. }
+            | TextPart::VarRef { span, .. }
+            | TextPart::Expr { span, .. } => *span,
+        }
+    }
+
+    /// The id of a variable reference, which the resolver binds.
+    pub fn node_id(&self) -> Option<NodeId> {
+        match self {
+            TextPart::VarRef { id, .. } => Some(*id),
+            TextPart::Literal { .. } | TextPart::Expr { .. } => None,
+        }
+    }
+
+    /// The name of a variable reference.
+    pub fn var_name(&self) -> Option<&str> {
+        match self {
+            TextPart::VarRef { name, .. } => Some(name),
+            TextPart::Literal { .. } | TextPart::Expr { .. } => None,
+        }
+    }
+}
+
 /// A literal value in declarations
 #[derive(Debug, Clone)]
 pub enum Literal {
@@ -55,7 +264,158 @@ pub enum Literal {
 pub struct VarBindingData {
     pub id: NodeId,
     pub name: String,
-    pub value: Literal,
+    /// The assigned value. Always a literal for a valid `const`.
+    pub value: Expr,
+    pub span: Span,
+    /// A declaration's trailing `// comment`, trimmed. Always `None` for an
+    /// assignment.
+    pub doc: Option<String>,
+}
+
+/// An arithmetic operator applied by a compound assignment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignOp {
+    /// `+=`
+    Add,
+    /// `-=`
+    Subtract,
+    /// `*=`
+    Multiply,
+    /// `/=`
+    Divide,
+}
+
+/// A value computed at runtime.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    Literal {
+        value: Literal,
+        span: Span,
+    },
+    /// A variable read, resolved like an interpolation
+    Var {
+        id: NodeId,
+        name: String,
+        span: Span,
+    },
+    /// A prefix operator: `not ready`
+    Unary {
+        op: UnaryOp,
+        operand: Box<Expr>,
+        span: Span,
+    },
+    /// Arithmetic or a comparison: `gold * 2`, `visits > 3`
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    /// `and` or `or`. The right side only runs if the left does not settle
+    /// the result.
+    Logical {
+        op: LogicOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+        span: Span,
+    },
+    /// A built-in function call: `random(1, 6)`. The name is checked by the
+    /// resolver.
+    Call {
+        name: String,
+        /// Span of the function name, for errors
+        name_span: Span,
+        args: Vec<Expr>,
+        span: Span,
+    },
+}
+
+impl Expr {
+    pub fn span(&self) -> Span {
+        match self {
+            Expr::Literal { span, .. }
+            | Expr::Var { span, .. }
+            | Expr::Unary { span, .. }
+            | Expr::Binary { span, .. }
+            | Expr::Logical { span, .. }
+            | Expr::Call { span, .. } => *span,
+        }
+    }
+}
+
+/// An operator between two operands that evaluates both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `+`
+    Add,
+    /// `-`
+    Subtract,
+    /// `*`
+    Multiply,
+    /// `/`
+    Divide,
+    /// `==`
+    Equal,
+    /// `!=`
+    NotEqual,
+    /// `<`
+    Less,
+    /// `<=`
+    LessEqual,
+    /// `>`
+    Greater,
+    /// `>=`
+    GreaterEqual,
+}
+
+/// A function built into the language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Builtin {
+    /// `random(min, max)`: a whole number from `min` to `max`, inclusive
+    Random,
+}
+
+impl Builtin {
+    pub const ALL: &[Builtin] = &[Builtin::Random];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|builtin| builtin.name() == name)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Builtin::Random => "random",
+        }
+    }
+
+    /// Number of arguments the function takes.
+    pub fn arity(self) -> usize {
+        match self {
+            Builtin::Random => 2,
+        }
+    }
+}
+
+/// An operator written before its operand.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    /// `not`, on booleans
+    Not,
+    /// `-`, on numbers
+    Negate,
+}
+
+/// Data for a compound assignment: `set name op= value`
+#[derive(Debug, Clone)]
+pub struct CompoundAssignData {
+    /// Binding for the target, used for both the read and the write
+    pub id: NodeId,
+    pub name: String,
+    pub op: AssignOp,
+    pub value: Expr,
     pub span: Span,
 }
 
@@ -65,4 +425,6 @@ pub struct ExternDeclData {
     pub id: NodeId,
     pub name: String,
     pub span: Span,
+    /// The trailing `// comment`, trimmed.
+    pub doc: Option<String>,
 }
@@ -1,26 +1,101 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::ast::NodeId;
+use crate::resolver::DeclKind;
+use crate::token::Span;
+
+/// Version of the serialized bytecode format.
+///
+/// Bump this whenever `Instruction`, `Value`, or `Chunk` change shape so that
+/// precompiled chunks from an older runtime are rejected instead of misread.
+pub const BYTECODE_VERSION: u32 = 17;
+
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Instruction {
     Constant {
         index: usize,
     },
-    /// Copy value from stack[slot] to top of stack.
+    /// Copy value from `stack[slot]` to top of stack.
     GetLocal {
         slot: usize,
     },
-    /// Pop top of stack and write to stack[slot].
+    /// Pop top of stack and write to `stack[slot]`.
     SetLocal {
         slot: usize,
     },
+    /// Pop top of stack, drop everything from `slot` up, and push the value so
+    /// it lands at `stack[slot]`. Discards locals left over from finished
+    /// choice branches and from earlier passes through a `goto` loop.
+    DeclareLocal {
+        slot: usize,
+    },
+    /// Pop `b`, pop `a`, push `a + b`. Both must be numbers.
+    Add,
+    /// Pop `b`, pop `a`, push `a - b`. Both must be numbers.
+    Subtract,
+    /// Pop `b`, pop `a`, push `a * b`. Both must be numbers.
+    Multiply,
+    /// Pop `b`, pop `a`, push `a / b`. Both must be numbers and `b` nonzero.
+    Divide,
+    /// Pop `b`, pop `a`, push whether they are the same type and value.
+    Equal,
+    /// Pop `b`, pop `a`, push whether they differ in type or value.
+    NotEqual,
+    /// Pop `b`, pop `a`, push `a < b`. Both must be numbers.
+    Less,
+    /// Pop `b`, pop `a`, push `a <= b`. Both must be numbers.
+    LessEqual,
+    /// Pop `b`, pop `a`, push `a > b`. Both must be numbers.
+    Greater,
+    /// Pop `b`, pop `a`, push `a >= b`. Both must be numbers.
+    GreaterEqual,
+    /// Pop a bool and push its negation.
+    Not,
+    /// Pop a number and push its negation.
+    Negate,
+    /// Pop `max`, pop `min`, and push a whole number between them, inclusive,
+    /// drawn from the VM's seeded generator.
+    Random,
+    /// Check that the top of the stack is a bool. If it settles `op` (false
+    /// for `and`, true for `or`), leave it as the result and jump to `target`;
+    /// otherwise pop it so the right operand can run.
+    ShortCircuit {
+        op: LogicOp,
+        target: usize,
+    },
+    /// Check that the top of the stack, the right operand of `op`, is a bool.
+    CheckBool {
+        op: LogicOp,
+    },
+    /// Copy the top of the stack, an interpolated expression's value, into
+    /// capture `slot` for translations of the text to read.
+    Capture {
+        slot: usize,
+    },
+    /// Pop a number, format it, and push the resulting string.
+    Format {
+        format: NumberFormat,
+    },
     /// Pop `count` values, concatenate as strings, push result.
     Concat {
         count: usize,
     },
     Line,
+    /// Pause for `seconds`. The VM returns control to the host, which resumes
+    /// with the next advance.
+    Wait {
+        seconds: f64,
+    },
     /// Present choices to the user. VM pauses for selection.
-    /// On resume, jumps to targets[selected_index].
+    /// On resume, jumps to `targets[selected_index]`.
     ChoiceSet {
         count: usize,
         targets: Vec<usize>,
+        /// Index of the choice to take when the host selects the default
+        default: Option<usize>,
     },
     /// Unconditional jump to target instruction index.
     Jump {
@@ -46,14 +121,106 @@ pub enum Instruction {
     Return,
 }
 
+/// The opcode and its operands, as in one line of [`Chunk::disassemble`].
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Instruction::Constant { index } => write!(f, "{:<14} {}", "Constant", index),
+            Instruction::GetLocal { slot } => write!(f, "{:<14} slot {}", "GetLocal", slot),
+            Instruction::SetLocal { slot } => write!(f, "{:<14} slot {}", "SetLocal", slot),
+            Instruction::DeclareLocal { slot } => {
+                write!(f, "{:<14} slot {}", "DeclareLocal", slot)
+            }
+            Instruction::Add => write!(f, "Add"),
+            Instruction::Subtract => write!(f, "Subtract"),
+            Instruction::Multiply => write!(f, "Multiply"),
+            Instruction::Divide => write!(f, "Divide"),
+            Instruction::Equal => write!(f, "Equal"),
+            Instruction::NotEqual => write!(f, "NotEqual"),
+            Instruction::Less => write!(f, "Less"),
+            Instruction::LessEqual => write!(f, "LessEqual"),
+            Instruction::Greater => write!(f, "Greater"),
+            Instruction::GreaterEqual => write!(f, "GreaterEqual"),
+            Instruction::Not => write!(f, "Not"),
+            Instruction::Negate => write!(f, "Negate"),
+            Instruction::Random => write!(f, "Random"),
+            Instruction::ShortCircuit { op, target } => {
+                write!(f, "{:<14} {} -> {}", "ShortCircuit", op.as_str(), target)
+            }
+            Instruction::CheckBool { op } => write!(f, "{:<14} {}", "CheckBool", op.as_str()),
+            Instruction::Capture { slot } => write!(f, "{:<14} {}", "Capture", slot),
+            Instruction::Format { format } => write!(f, "{:<14} {}", "Format", format),
+            Instruction::Concat { count } => write!(f, "{:<14} {}", "Concat", count),
+            Instruction::Line => write!(f, "Line"),
+            Instruction::Wait { seconds } => write!(f, "{:<14} {}s", "Wait", seconds),
+            Instruction::ChoiceSet {
+                count,
+                targets,
+                default,
+            } => match default {
+                Some(default) => write!(
+                    f,
+                    "{:<14} {} -> {:?} default {}",
+                    "ChoiceSet", count, targets, default
+                ),
+                None => write!(f, "{:<14} {} -> {:?}", "ChoiceSet", count, targets),
+            },
+            Instruction::Jump { target } => write!(f, "{:<14} -> {}", "Jump", target),
+            Instruction::InitStorage { name } => write!(f, "{:<14} {}", "InitStorage", name),
+            Instruction::GetStorage { name } => write!(f, "{:<14} {}", "GetStorage", name),
+            Instruction::SetStorage { name } => write!(f, "{:<14} {}", "SetStorage", name),
+            Instruction::GetHost { name } => write!(f, "{:<14} {}", "GetHost", name),
+            Instruction::Return => write!(f, "Return"),
+        }
+    }
+}
+
+/// A value held by a variable or produced by an expression.
+///
+/// Values compare equal only to values of the same type, so `Number(1.0)` is
+/// not equal to `String("1")`. Ordering puts every bool before every number
+/// and every number before every string: `Bool < Number < String`. Within a
+/// type, `false < true`, numbers compare numerically, and strings compare by
+/// bytes. A NaN number is unordered, as with `f64`.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
     String(String),
     Number(f64),
     Bool(bool),
 }
 
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+            _ => self.type_rank().partial_cmp(&other.type_rank()),
+        }
+    }
+}
+
 impl Value {
+    /// The name of the value's type, as written in error messages:
+    /// `"string"`, `"number"`, or `"bool"`.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Number(_) => "number",
+            Value::Bool(_) => "bool",
+        }
+    }
+
+    /// Position of the value's type in the cross-type ordering.
+    fn type_rank(&self) -> u8 {
+        match self {
+            Value::Bool(_) => 0,
+            Value::Number(_) => 1,
+            Value::String(_) => 2,
+        }
+    }
+
     /// Convert value to string representation for interpolation.
     pub fn to_string_value(&self) -> String {
         match self {
@@ -69,23 +236,250 @@ impl Value {
             Value::Bool(b) => if *b { "true" } else { "false" }.to_string(),
         }
     }
+
+    /// Like [`Value::to_string_value`], but hands a string back without
+    /// copying it.
+    pub fn into_string_value(self) -> String {
+        match self {
+            Value::String(s) => s,
+            value => value.to_string_value(),
+        }
+    }
+
+    /// Describe the value for debugging output, quoting strings.
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            Value::String(s) => format!("{:?}", s),
+            _ => self.to_string_value(),
+        }
+    }
 }
 
-#[derive(Debug)]
+/// A short-circuiting boolean operator.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LogicOp {
+    And,
+    Or,
+}
+
+impl LogicOp {
+    /// The operator as written in source.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogicOp::And => "and",
+            LogicOp::Or => "or",
+        }
+    }
+
+    /// The left operand value that decides the result on its own.
+    pub fn settled_by(self) -> bool {
+        matches!(self, LogicOp::Or)
+    }
+}
+
+/// How to render a number in an interpolation, written `{name:format}`.
+///
+/// The format is `0` (round to an integer), `0.N` (exactly `N` decimals),
+/// either of those followed by `%` (multiply by 100 and add a percent sign),
+/// or `%` alone for a whole percentage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NumberFormat {
+    /// Digits after the decimal point.
+    pub decimals: u8,
+    /// Render as a percentage.
+    pub percent: bool,
+}
+
+impl NumberFormat {
+    /// Parse a format specifier, or `None` if it is not a valid format.
+    pub fn parse(spec: &str) -> Option<Self> {
+        let (spec, percent) = match spec.strip_suffix('%') {
+            Some(rest) => (rest, true),
+            None => (spec, false),
+        };
+        let decimals = match spec {
+            "" if percent => 0,
+            "0" => 0,
+            _ => {
+                let digits = spec.strip_prefix("0.")?;
+                if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+                    return None;
+                }
+                digits.parse().ok()?
+            }
+        };
+        Some(Self { decimals, percent })
+    }
+
+    /// Render `n` with this format.
+    pub fn apply(&self, n: f64) -> String {
+        let decimals = self.decimals as usize;
+        if self.percent {
+            format!("{:.*}%", decimals, n * 100.0)
+        } else {
+            format!("{:.*}", decimals, n)
+        }
+    }
+}
+
+impl std::fmt::Display for NumberFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.decimals, self.percent) {
+            (0, true) => write!(f, "%"),
+            (0, false) => write!(f, "0"),
+            (n, percent) => write!(f, "0.{}{}", n, if percent { "%" } else { "" }),
+        }
+    }
+}
+
+/// Compiled bytecode for a script.
+///
+/// With the `serde` feature enabled, chunks can be serialized at build time and
+/// loaded with `Runtime::from_chunk`. The serialized form carries
+/// [`BYTECODE_VERSION`]; deserializing a chunk with a different version fails.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(into = "SerializedChunk", try_from = "SerializedChunk")
+)]
 pub struct Chunk {
     pub code: Vec<Instruction>,
     pub constants: Vec<Value>,
     pub lines: Vec<usize>,
+    /// Source line behind each `Line` instruction, keyed by instruction offset.
+    pub line_nodes: HashMap<usize, NodeId>,
+    /// Source choices behind each `ChoiceSet` instruction, keyed by instruction offset.
+    pub choice_nodes: HashMap<usize, Vec<NodeId>>,
+    /// Tags of each choice, keyed by `ChoiceSet` offset. Sets without any tags
+    /// are left out.
+    pub choice_tags: HashMap<usize, Vec<Vec<String>>>,
+    /// String table key of each `Line` instruction, keyed by instruction offset.
+    pub line_keys: HashMap<usize, TextKey>,
+    /// String table keys of each choice, keyed by `ChoiceSet` offset.
+    pub choice_keys: HashMap<usize, Vec<TextKey>>,
+    /// How each variable was declared, by name, for debug tooling.
+    pub var_kinds: HashMap<String, DeclKind>,
+    /// Instructions of each choice's branch, keyed by choice id: its nested
+    /// code and the jump back out, so coverage tools can map what ran back to
+    /// `Choice::branch_span`.
+    pub branches: HashMap<NodeId, Range<usize>>,
+    /// Source span of each line and choice, by id, for coverage reports.
+    pub node_spans: HashMap<NodeId, Span>,
 }
 
-impl Chunk {
-    pub fn new() -> Self {
+/// How a line or choice is looked up in a string table, and the variables a
+/// translation of it may interpolate.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextKey {
+    /// The id `extract_strings` reports for the text.
+    pub id: String,
+    /// Variables interpolated in the source text, by name, and interpolated
+    /// expressions, by their numbered placeholder (`0`, `1`, ...).
+    pub vars: Vec<(String, VarSource)>,
+}
+
+/// Where a translation reads an interpolated variable from. An interpolated
+/// expression is read from the capture slot `Instruction::Capture` saved it in.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum VarSource {
+    Local { slot: usize },
+    Storage { name: String },
+    Host { name: String },
+    Const { value: Value },
+    Capture { slot: usize },
+}
+
+/// On-disk representation of a `Chunk`, tagged with the bytecode version.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct SerializedChunk {
+    version: u32,
+    code: Vec<Instruction>,
+    constants: Vec<Value>,
+    lines: Vec<usize>,
+    line_nodes: HashMap<usize, NodeId>,
+    choice_nodes: HashMap<usize, Vec<NodeId>>,
+    choice_tags: HashMap<usize, Vec<Vec<String>>>,
+    line_keys: HashMap<usize, TextKey>,
+    choice_keys: HashMap<usize, Vec<TextKey>>,
+    var_kinds: HashMap<String, DeclKind>,
+    branches: HashMap<NodeId, Range<usize>>,
+    node_spans: HashMap<NodeId, Span>,
+}
+
+#[cfg(feature = "serde")]
+impl From<Chunk> for SerializedChunk {
+    fn from(chunk: Chunk) -> Self {
         Self {
-            code: Vec::new(),
-            constants: Vec::new(),
-            lines: Vec::new(),
+            version: BYTECODE_VERSION,
+            code: chunk.code,
+            constants: chunk.constants,
+            lines: chunk.lines,
+            line_nodes: chunk.line_nodes,
+            choice_nodes: chunk.choice_nodes,
+            choice_tags: chunk.choice_tags,
+            line_keys: chunk.line_keys,
+            choice_keys: chunk.choice_keys,
+            var_kinds: chunk.var_kinds,
+            branches: chunk.branches,
+            node_spans: chunk.node_spans,
         }
     }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<SerializedChunk> for Chunk {
+    type Error = BytecodeVersionError;
+
+    fn try_from(serialized: SerializedChunk) -> Result<Self, Self::Error> {
+        if serialized.version != BYTECODE_VERSION {
+            return Err(BytecodeVersionError {
+                found: serialized.version,
+            });
+        }
+        Ok(Self {
+            code: serialized.code,
+            constants: serialized.constants,
+            lines: serialized.lines,
+            line_nodes: serialized.line_nodes,
+            choice_nodes: serialized.choice_nodes,
+            choice_tags: serialized.choice_tags,
+            line_keys: serialized.line_keys,
+            choice_keys: serialized.choice_keys,
+            var_kinds: serialized.var_kinds,
+            branches: serialized.branches,
+            node_spans: serialized.node_spans,
+        })
+    }
+}
+
+/// A serialized chunk was produced for a different bytecode version.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BytecodeVersionError {
+    pub found: u32,
+}
+
+impl std::fmt::Display for BytecodeVersionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "unsupported bytecode version {} (this runtime reads version {}); recompile the script",
+            self.found, BYTECODE_VERSION
+        )
+    }
+}
+
+impl std::error::Error for BytecodeVersionError {}
+
+impl Chunk {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
     pub fn emit(&mut self, instruction: Instruction, line: usize) {
         self.code.push(instruction);
@@ -102,15 +496,49 @@ impl Chunk {
         self.code.len()
     }
 
-    /// Patch a Jump instruction at `offset` to jump to `target`.
+    /// Patch a Jump or ShortCircuit instruction at `offset` to jump to `target`.
     pub fn patch_jump(&mut self, offset: usize, target: usize) {
-        if let Instruction::Jump { target: ref mut t } = self.code[offset] {
-            *t = target;
-        } else {
-            panic!("patch_jump called on non-Jump instruction");
+        match &mut self.code[offset] {
+            Instruction::Jump { target: t } | Instruction::ShortCircuit { target: t, .. } => {
+                *t = target;
+            }
+            _ => panic!("patch_jump called on non-jump instruction"),
         }
     }
 
+    /// Names of the host variables the code reads, in order of first use.
+    pub fn extern_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for instruction in &self.code {
+            if let Instruction::GetHost { name } = instruction
+                && !names.contains(&name.as_str())
+            {
+                names.push(name);
+            }
+        }
+        names
+    }
+
+    /// Render the chunk as human-readable bytecode, one instruction per line.
+    ///
+    /// Each line starts with the instruction's index. Jump and choice targets are
+    /// absolute instruction indices, and constant loads show the resolved value.
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        for (offset, instruction) in self.code.iter().enumerate() {
+            // Writing to a String cannot fail
+            let _ = write!(out, "{:04} {}", offset, instruction);
+            if let Instruction::Constant { index } = instruction {
+                let _ = match self.constants.get(*index) {
+                    Some(value) => write!(out, " ({})", value.describe()),
+                    None => write!(out, " (<missing>)"),
+                };
+            }
+            out.push('\n');
+        }
+        out
+    }
+
     /// Patch a ChoiceSet instruction's targets at `offset`.
     pub fn patch_choice_targets(&mut self, offset: usize, new_targets: Vec<usize>) {
         if let Instruction::ChoiceSet { targets, .. } = &mut self.code[offset] {
@@ -120,3 +548,15 @@ impl Chunk {
         }
     }
 }
+
+/// The disassembly followed by the constant pool, for debug printing.
+impl std::fmt::Display for Chunk {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.disassemble())?;
+        writeln!(f, "constants:")?;
+        for (index, value) in self.constants.iter().enumerate() {
+            writeln!(f, "{:04} {}", index, value.describe())?;
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,11 @@
-use crate::ast::{Literal, NodeId, Script, Stmt, TextPart, VarBindingData};
-use crate::chunk::{Chunk, Instruction, Value};
+use std::collections::HashMap;
+
+use crate::ast::{
+    AssignOp, BinaryOp, Builtin, CompoundAssignData, Expr, Literal, NodeId, Script, Stmt, TextPart,
+    UnaryOp, VarBindingData,
+};
+use crate::chunk::{Chunk, Instruction, TextKey, Value, VarSource};
+use crate::loc::string_ids;
 use crate::resolver::SymbolTable;
 
 #[derive(Debug, Clone)]
@@ -10,6 +16,16 @@ pub struct Compiler<'a> {
     ast: &'a Script,
     chunk: Chunk,
     symbols: &'a SymbolTable,
+    /// Label name -> offset of the first instruction after it
+    labels: HashMap<String, usize>,
+    /// Goto jumps awaiting their label offset: (jump offset, label name)
+    goto_patches: Vec<(usize, &'a str)>,
+    /// String table id of each line and choice
+    string_ids: HashMap<NodeId, String>,
+    /// Capture slots handed out to interpolated expressions so far
+    captures: usize,
+    /// The script's source, when blank lines after a line become beats
+    beats: Option<&'a str>,
 }
 
 impl<'a> Compiler<'a> {
@@ -18,15 +34,43 @@ impl<'a> Compiler<'a> {
             ast,
             chunk: Chunk::new(),
             symbols,
+            labels: HashMap::new(),
+            goto_patches: Vec::new(),
+            string_ids: string_ids(ast),
+            captures: 0,
+            beats: None,
         }
     }
 
+    /// Show a blank line that follows a line as an empty line of its own, a
+    /// beat for the host to pause on. `source` is the text the AST was parsed
+    /// from. Blank lines at the end of a block are still skipped.
+    pub fn with_beats(mut self, source: &'a str) -> Self {
+        self.beats = Some(source);
+        self
+    }
+
     pub fn compile(mut self) -> Result<Chunk, CompileError> {
-        for stmt in &self.ast.statements {
-            self.compile_stmt(stmt);
-        }
+        self.compile_block(&self.ast.statements);
 
         self.chunk.emit(Instruction::Return, 0);
+
+        self.chunk.var_kinds = self
+            .symbols
+            .declarations
+            .iter()
+            .map(|(name, (kind, _))| (name.clone(), *kind))
+            .collect();
+
+        // Labels may follow the gotos that target them, so patch at the end
+        for (jump_offset, label) in std::mem::take(&mut self.goto_patches) {
+            let target = *self
+                .labels
+                .get(label)
+                .expect("label not found: resolver bug");
+            self.chunk.patch_jump(jump_offset, target);
+        }
+
         Ok(self.chunk)
     }
 
@@ -49,9 +93,12 @@ impl<'a> Compiler<'a> {
         self.symbols.extern_bindings.get(&id).map(|s| s.as_str())
     }
 
-    /// Emit instruction to read a variable (temp, save, or extern) and push onto stack.
+    /// Emit instruction to read a variable (temp, save, const, or extern) and push onto stack.
     fn emit_var_read(&mut self, id: NodeId, line: usize) {
-        if let Some(name) = self.get_save_name(id) {
+        if let Some(value) = self.symbols.const_bindings.get(&id) {
+            // Consts are inlined; they never occupy a slot
+            self.compile_literal(value, line);
+        } else if let Some(name) = self.get_save_name(id) {
             self.chunk.emit(
                 Instruction::GetStorage {
                     name: name.to_string(),
@@ -71,6 +118,48 @@ impl<'a> Compiler<'a> {
         }
     }
 
+    /// Where a translation of some text would read the variable behind `id`.
+    fn var_source(&self, id: NodeId) -> VarSource {
+        if let Some(literal) = self.symbols.const_bindings.get(&id) {
+            VarSource::Const {
+                value: literal_value(literal),
+            }
+        } else if let Some(name) = self.get_save_name(id) {
+            VarSource::Storage {
+                name: name.to_string(),
+            }
+        } else if let Some(name) = self.get_extern_name(id) {
+            VarSource::Host {
+                name: name.to_string(),
+            }
+        } else {
+            VarSource::Local {
+                slot: self.get_slot(id),
+            }
+        }
+    }
+
+    /// The string table key of a line or choice, with the variables its text
+    /// interpolates and the capture slots `compile_text_parts` gave its
+    /// expressions, numbered in order.
+    fn text_key(&self, node: NodeId, parts: &[TextPart], captures: &[usize]) -> TextKey {
+        let mut vars: Vec<(String, VarSource)> = Vec::new();
+        for part in parts {
+            if let TextPart::VarRef { id, name, .. } = part
+                && !vars.iter().any(|(known, _)| known == name)
+            {
+                vars.push((name.clone(), self.var_source(*id)));
+            }
+        }
+        for (index, slot) in captures.iter().enumerate() {
+            vars.push((index.to_string(), VarSource::Capture { slot: *slot }));
+        }
+        TextKey {
+            id: self.string_ids[&node].clone(),
+            vars,
+        }
+    }
+
     /// Emit instruction to write a value (already on stack) to a variable (temp or save).
     fn emit_var_write(&mut self, id: NodeId, line: usize) {
         if let Some(name) = self.get_save_name(id) {
@@ -86,22 +175,45 @@ impl<'a> Compiler<'a> {
         }
     }
 
-    fn compile_stmt(&mut self, stmt: &Stmt) {
+    fn compile_block(&mut self, block: &'a [Stmt]) {
+        for (index, stmt) in block.iter().enumerate() {
+            self.compile_stmt(stmt);
+            if let (Some(source), Stmt::Line { span, .. }, Some(next)) =
+                (self.beats, stmt, block.get(index + 1))
+                && has_blank_line(&source[span.end..next.span().start])
+            {
+                let empty = self.chunk.add_constant(Value::String(String::new()));
+                self.chunk
+                    .emit(Instruction::Constant { index: empty }, span.end);
+                self.chunk.emit(Instruction::Line, span.end);
+            }
+        }
+    }
+
+    fn compile_stmt(&mut self, stmt: &'a Stmt) {
         match stmt {
-            Stmt::TempDecl(VarBindingData { value, span, .. }) => {
-                // Push initial value onto stack.
-                // The value lives at its assigned slot position (implicit from declaration order).
-                self.compile_literal(value, span.start);
+            Stmt::TempDecl(VarBindingData {
+                id, value, span, ..
+            }) => {
+                // Push initial value, then settle it at its assigned slot.
+                // Slots follow declaration order, so everything above is stale.
+                self.compile_expr(value);
+                let slot = self.get_slot(*id);
+                self.chunk
+                    .emit(Instruction::DeclareLocal { slot }, span.start);
             }
             Stmt::SaveDecl(VarBindingData {
                 name, value, span, ..
             }) => {
                 // Push initial value onto stack, then emit InitStorage.
                 // InitStorage uses "initialize if absent" semantics for save variables.
-                self.compile_literal(value, span.start);
+                self.compile_expr(value);
                 self.chunk
                     .emit(Instruction::InitStorage { name: name.clone() }, span.start);
             }
+            Stmt::ConstDecl(_) => {
+                // No-op: each reference loads the const's value directly.
+            }
             Stmt::ExternDecl(_) => {
                 // No-op: extern declarations don't generate code.
                 // The host provides values on-demand when GetHost executes.
@@ -111,21 +223,69 @@ impl<'a> Compiler<'a> {
             }) => {
                 // Assignment modifies an existing variable (temp or save).
                 // Push value, then emit appropriate write instruction.
-                self.compile_literal(value, span.start);
+                self.compile_expr(value);
+                self.emit_var_write(*id, span.start);
+            }
+            Stmt::CompoundAssignment(CompoundAssignData {
+                id,
+                op,
+                value,
+                span,
+                ..
+            }) => {
+                // Read the target, apply the operator, and write the result back.
+                self.emit_var_read(*id, span.start);
+                self.compile_expr(value);
+                let instruction = match op {
+                    AssignOp::Add => Instruction::Add,
+                    AssignOp::Subtract => Instruction::Subtract,
+                    AssignOp::Multiply => Instruction::Multiply,
+                    AssignOp::Divide => Instruction::Divide,
+                };
+                self.chunk.emit(instruction, span.start);
                 self.emit_var_write(*id, span.start);
             }
-            Stmt::Line { parts, span } => {
-                self.compile_text_parts(parts, span.start);
+            Stmt::Line { id, parts, span } => {
+                let captures = self.compile_text_parts(parts, span.start);
+                self.chunk
+                    .line_nodes
+                    .insert(self.chunk.current_offset(), *id);
+                self.chunk.node_spans.insert(*id, *span);
+                let key = self.text_key(*id, parts, &captures);
+                self.chunk
+                    .line_keys
+                    .insert(self.chunk.current_offset(), key);
                 self.chunk.emit(Instruction::Line, span.start);
             }
-            Stmt::ChoiceSet { choices } => {
+            Stmt::Label { name, .. } => {
+                // No code; the label marks the next instruction
+                self.labels
+                    .insert(name.clone(), self.chunk.current_offset());
+            }
+            Stmt::Goto { label, span, .. } => {
+                let jump_offset = self.chunk.current_offset();
+                self.chunk.emit(Instruction::Jump { target: 0 }, span.start);
+                self.goto_patches.push((jump_offset, label));
+            }
+            Stmt::Wait { seconds, span } => {
+                self.chunk
+                    .emit(Instruction::Wait { seconds: *seconds }, span.start);
+            }
+            Stmt::End { span } => {
+                self.chunk.emit(Instruction::Return, span.start);
+            }
+            Stmt::Include { .. } => {
+                unreachable!("include not expanded: resolver bug")
+            }
+            Stmt::ChoiceSet { choices, span } => {
                 let count = choices.len();
-                let line = choices[0].span.start;
+                let line = span.start;
 
                 // 1. Emit code for all choice texts (may involve interpolation)
-                for choice in choices {
-                    self.compile_text_parts(&choice.parts, choice.span.start);
-                }
+                let captures: Vec<Vec<usize>> = choices
+                    .iter()
+                    .map(|choice| self.compile_text_parts(&choice.parts, choice.span.start))
+                    .collect();
 
                 // 2. Emit ChoiceSet with placeholder targets (VM pauses here)
                 let choice_set_offset = self.chunk.current_offset();
@@ -133,28 +293,51 @@ impl<'a> Compiler<'a> {
                     Instruction::ChoiceSet {
                         count,
                         targets: vec![0; count],
+                        default: choices.iter().position(|choice| choice.default),
                     },
                     line,
                 );
 
+                self.chunk.choice_nodes.insert(
+                    choice_set_offset,
+                    choices.iter().map(|choice| choice.id).collect(),
+                );
+                self.chunk
+                    .node_spans
+                    .extend(choices.iter().map(|choice| (choice.id, choice.span)));
+                let keys = choices
+                    .iter()
+                    .zip(&captures)
+                    .map(|(choice, captures)| self.text_key(choice.id, &choice.parts, captures))
+                    .collect();
+                self.chunk.choice_keys.insert(choice_set_offset, keys);
+                if choices.iter().any(|choice| !choice.tags.is_empty()) {
+                    self.chunk.choice_tags.insert(
+                        choice_set_offset,
+                        choices.iter().map(|choice| choice.tags.clone()).collect(),
+                    );
+                }
+
                 // 3. Emit nested code for each choice, collecting their start offsets
                 let mut choice_targets = Vec::with_capacity(count);
                 let mut jump_patches = Vec::new();
 
                 for choice in choices {
                     // Record the start offset for this choice's nested code
-                    choice_targets.push(self.chunk.current_offset());
+                    let start = self.chunk.current_offset();
+                    choice_targets.push(start);
 
                     // Emit nested statements
-                    for nested_stmt in &choice.nested {
-                        self.compile_stmt(nested_stmt);
-                    }
+                    self.compile_block(&choice.nested);
 
                     // Emit Jump to gather point (placeholder target)
                     let jump_offset = self.chunk.current_offset();
                     self.chunk
                         .emit(Instruction::Jump { target: 0 }, choice.span.start);
                     jump_patches.push(jump_offset);
+                    self.chunk
+                        .branches
+                        .insert(choice.id, start..jump_offset + 1);
                 }
 
                 // 4. Gather point is here
@@ -173,34 +356,58 @@ impl<'a> Compiler<'a> {
     }
 
     /// Compile text parts (literals and variable references) onto the stack.
-    /// If there's only one literal part, just push it.
-    /// If there are multiple parts, push all and emit Concat.
-    fn compile_text_parts(&mut self, parts: &[TextPart], line: usize) {
-        if parts.is_empty() {
-            // Empty text - push empty string
-            let index = self.chunk.add_constant(Value::String(String::new()));
+    /// Text without interpolation is pushed as a single constant.
+    /// Otherwise every part is pushed and joined with Concat. Returns the
+    /// capture slots given to the interpolated expressions, in order.
+    fn compile_text_parts(&mut self, parts: &[TextPart], line: usize) -> Vec<usize> {
+        // Optimization: plain text needs no concat, so the VM can hand the
+        // constant's string straight to the host
+        let literal: Option<String> = parts
+            .iter()
+            .map(|part| match part {
+                TextPart::Literal { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if let Some(text) = literal {
+            let index = self.chunk.add_constant(Value::String(text));
             self.chunk.emit(Instruction::Constant { index }, line);
-            return;
-        }
-
-        // Optimization: single literal part, no concat needed
-        if parts.len() == 1 {
-            if let TextPart::Literal { text, .. } = &parts[0] {
-                let index = self.chunk.add_constant(Value::String(text.clone()));
-                self.chunk.emit(Instruction::Constant { index }, line);
-                return;
-            }
+            return Vec::new();
         }
 
         // Multiple parts or single var ref - push all and concat
+        let mut captures = Vec::new();
         for part in parts {
             match part {
                 TextPart::Literal { text, span } => {
                     let index = self.chunk.add_constant(Value::String(text.clone()));
                     self.chunk.emit(Instruction::Constant { index }, span.start);
                 }
-                TextPart::VarRef { id, span, .. } => {
+                TextPart::VarRef {
+                    id, format, span, ..
+                } => {
                     self.emit_var_read(*id, span.start);
+                    if let Some(format) = format {
+                        self.chunk
+                            .emit(Instruction::Format { format: *format }, span.start);
+                    }
+                }
+                TextPart::Expr { expr, format, span } => {
+                    self.compile_expr(expr);
+                    // Saved before formatting, so a translation can format
+                    // the value its own way
+                    self.chunk.emit(
+                        Instruction::Capture {
+                            slot: self.captures,
+                        },
+                        span.start,
+                    );
+                    captures.push(self.captures);
+                    self.captures += 1;
+                    if let Some(format) = format {
+                        self.chunk
+                            .emit(Instruction::Format { format: *format }, span.start);
+                    }
                 }
             }
         }
@@ -210,16 +417,99 @@ impl<'a> Compiler<'a> {
             self.chunk
                 .emit(Instruction::Concat { count: parts.len() }, line);
         }
+        captures
+    }
+
+    /// Compile an expression and push its value onto the stack.
+    fn compile_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal { value, span } => self.compile_literal(value, span.start),
+            Expr::Var { id, span, .. } => self.emit_var_read(*id, span.start),
+            Expr::Unary { op, operand, span } => {
+                self.compile_expr(operand);
+                let instruction = match op {
+                    UnaryOp::Not => Instruction::Not,
+                    UnaryOp::Negate => Instruction::Negate,
+                };
+                self.chunk.emit(instruction, span.start);
+            }
+            Expr::Binary {
+                op,
+                left,
+                right,
+                span,
+            } => {
+                self.compile_expr(left);
+                self.compile_expr(right);
+                let instruction = match op {
+                    BinaryOp::Add => Instruction::Add,
+                    BinaryOp::Subtract => Instruction::Subtract,
+                    BinaryOp::Multiply => Instruction::Multiply,
+                    BinaryOp::Divide => Instruction::Divide,
+                    BinaryOp::Equal => Instruction::Equal,
+                    BinaryOp::NotEqual => Instruction::NotEqual,
+                    BinaryOp::Less => Instruction::Less,
+                    BinaryOp::LessEqual => Instruction::LessEqual,
+                    BinaryOp::Greater => Instruction::Greater,
+                    BinaryOp::GreaterEqual => Instruction::GreaterEqual,
+                };
+                self.chunk.emit(instruction, span.start);
+            }
+            Expr::Logical {
+                op,
+                left,
+                right,
+                span,
+            } => {
+                // The left value is the result if it settles the operator;
+                // otherwise it is dropped and the right value is the result
+                self.compile_expr(left);
+                let jump_offset = self.chunk.current_offset();
+                self.chunk
+                    .emit(Instruction::ShortCircuit { op: *op, target: 0 }, span.start);
+                self.compile_expr(right);
+                self.chunk
+                    .emit(Instruction::CheckBool { op: *op }, span.start);
+                self.chunk
+                    .patch_jump(jump_offset, self.chunk.current_offset());
+            }
+            Expr::Call {
+                name, args, span, ..
+            } => {
+                for arg in args {
+                    self.compile_expr(arg);
+                }
+                let builtin = Builtin::from_name(name).expect("unknown function: resolver bug");
+                let instruction = match builtin {
+                    Builtin::Random => Instruction::Random,
+                };
+                self.chunk.emit(instruction, span.start);
+            }
+        }
     }
 
     /// Compile a literal value and push onto stack.
     fn compile_literal(&mut self, literal: &Literal, line: usize) {
-        let value = match literal {
-            Literal::String(s) => Value::String(s.clone()),
-            Literal::Number(n) => Value::Number(*n),
-            Literal::Bool(b) => Value::Bool(*b),
-        };
-        let index = self.chunk.add_constant(value);
+        let index = self.chunk.add_constant(literal_value(literal));
         self.chunk.emit(Instruction::Constant { index }, line);
     }
 }
+
+fn literal_value(literal: &Literal) -> Value {
+    match literal {
+        Literal::String(s) => Value::String(s.clone()),
+        Literal::Number(n) => Value::Number(*n),
+        Literal::Bool(b) => Value::Bool(*b),
+    }
+}
+
+/// Whether the text between two statements has a line with nothing on it.
+/// The first piece ends the earlier statement's line and the last starts the
+/// later one's, so only the lines in between count.
+fn has_blank_line(gap: &str) -> bool {
+    let lines: Vec<&str> = gap.split('\n').collect();
+    lines.len() > 2
+        && lines[1..lines.len() - 1]
+            .iter()
+            .any(|line| line.trim().is_empty())
+}
@@ -3,7 +3,7 @@
 //! The `IntoDiagnostic` trait provides a uniform way to convert different
 //! error types into `Diagnostic` values for rendering.
 
-use super::{Diagnostic, Matcher};
+use super::{Diagnostic, Matcher, RecencyBoostMatcher};
 
 /// Context provided during diagnostic conversion.
 ///
@@ -14,23 +14,57 @@ pub struct DiagnosticContext<'a> {
     pub known_variables: &'a [String],
     /// The fuzzy matcher to use for suggestions.
     pub matcher: &'a dyn Matcher,
+    /// Maximum number of "did you mean?" suggestions attached to one diagnostic.
+    pub max_suggestions: usize,
 }
 
 impl<'a> DiagnosticContext<'a> {
+    /// Default cap on suggestions per diagnostic.
+    pub const DEFAULT_MAX_SUGGESTIONS: usize = 3;
+
     /// Create a new context with the given variables and matcher.
     pub fn new(known_variables: &'a [String], matcher: &'a dyn Matcher) -> Self {
         Self {
             known_variables,
             matcher,
+            max_suggestions: Self::DEFAULT_MAX_SUGGESTIONS,
         }
     }
 
+    /// Set the maximum number of suggestions attached to one diagnostic.
+    pub fn with_max_suggestions(mut self, max: usize) -> Self {
+        self.max_suggestions = max;
+        self
+    }
+
     /// Find a similar variable name for "did you mean?" suggestions.
     pub fn find_similar_variable(&self, name: &str) -> Option<&str> {
         self.matcher
             .best_match(name, self.known_variables)
             .map(|(s, _)| s)
     }
+
+    /// Find up to `max_suggestions` similar variable names, best match first.
+    pub fn find_similar_variables(&self, name: &str) -> Vec<&str> {
+        self.matcher
+            .find_similar(name, self.known_variables)
+            .into_iter()
+            .take(self.max_suggestions)
+            .map(|(s, _)| s)
+            .collect()
+    }
+
+    /// Like `find_similar_variables`, but favoring the `recent` names when
+    /// candidates are about as close, since a typo is usually of a name used
+    /// nearby.
+    pub fn find_similar_variables_near(&self, name: &str, recent: &[String]) -> Vec<&str> {
+        RecencyBoostMatcher::new(self.matcher, recent.to_vec())
+            .find_similar(name, self.known_variables)
+            .into_iter()
+            .take(self.max_suggestions)
+            .map(|(s, _)| s)
+            .collect()
+    }
 }
 
 /// Trait for converting an error into a diagnostic.
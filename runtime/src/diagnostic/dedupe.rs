@@ -0,0 +1,54 @@
+//! Collapsing of repeated diagnostics.
+
+use std::collections::HashSet;
+
+use super::Diagnostic;
+
+/// Remove diagnostics that repeat an earlier one.
+///
+/// Two diagnostics are duplicates when they share severity, message, and primary
+/// label span. The first occurrence is kept along with its notes and suggestions,
+/// and the relative order of the remaining diagnostics is preserved.
+pub fn dedupe(diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+    let mut seen = HashSet::new();
+    diagnostics
+        .into_iter()
+        .filter(|diag| seen.insert((diag.severity, diag.message.clone(), diag.primary_span())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Span;
+
+    fn undefined(span: Span) -> Diagnostic {
+        Diagnostic::error("undefined variable 'gld'", span, "not found in this scope")
+    }
+
+    #[test]
+    fn collapses_repeated_undefined_variable() {
+        let span = Span { start: 4, end: 7 };
+        let first = undefined(span).with_suggestion("did you mean 'gold'?", span, "gold");
+        let repeat = undefined(span).with_note("a later duplicate");
+
+        let deduped = dedupe(vec![first, repeat]);
+
+        assert_eq!(deduped.len(), 1);
+        assert_eq!(deduped[0].suggestions.len(), 1);
+        assert!(deduped[0].notes.is_empty());
+    }
+
+    #[test]
+    fn keeps_distinct_spans_and_severities() {
+        let a = Span { start: 0, end: 3 };
+        let b = Span { start: 10, end: 13 };
+        let warning = Diagnostic::warning("undefined variable 'gld'", a, "here");
+
+        let deduped = dedupe(vec![undefined(a), undefined(b), warning, undefined(a)]);
+
+        assert_eq!(deduped.len(), 3);
+        assert_eq!(deduped[0].primary_span(), Some(a));
+        assert_eq!(deduped[1].primary_span(), Some(b));
+    }
+}
@@ -0,0 +1,63 @@
+//! Filtering of diagnostics by severity.
+
+use super::{Diagnostic, Severity};
+
+/// The diagnostics at `min` severity or above, in their original order.
+///
+/// Pass `Severity::Error` to drop warnings from production output, or
+/// `Severity::Note` to keep everything.
+pub fn filter_by_severity(diagnostics: &[Diagnostic], min: Severity) -> Vec<&Diagnostic> {
+    diagnostics
+        .iter()
+        .filter(|diag| diag.severity.is_at_least(min))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Span;
+
+    fn mixed() -> Vec<Diagnostic> {
+        let span = Span { start: 0, end: 1 };
+        let mut note = Diagnostic::warning("a note", span, "here");
+        note.severity = Severity::Note;
+        vec![
+            Diagnostic::warning("a warning", span, "here"),
+            Diagnostic::error("an error", span, "here"),
+            note,
+        ]
+    }
+
+    fn messages(diagnostics: Vec<&Diagnostic>) -> Vec<&str> {
+        diagnostics
+            .iter()
+            .map(|diag| diag.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn keeps_only_errors() {
+        let diagnostics = mixed();
+        assert_eq!(
+            messages(filter_by_severity(&diagnostics, Severity::Error)),
+            ["an error"]
+        );
+    }
+
+    #[test]
+    fn warning_threshold_keeps_errors_and_warnings_in_order() {
+        let diagnostics = mixed();
+        assert_eq!(
+            messages(filter_by_severity(&diagnostics, Severity::Warning)),
+            ["a warning", "an error"]
+        );
+    }
+
+    #[test]
+    fn note_and_help_thresholds_keep_everything() {
+        let diagnostics = mixed();
+        assert_eq!(filter_by_severity(&diagnostics, Severity::Note).len(), 3);
+        assert_eq!(filter_by_severity(&diagnostics, Severity::Help).len(), 3);
+    }
+}
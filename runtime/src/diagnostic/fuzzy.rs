@@ -21,6 +21,16 @@ pub trait Matcher {
     fn find_similar<'a>(&self, query: &str, candidates: &'a [String]) -> Vec<(&'a str, f64)>;
 }
 
+impl<M: Matcher + ?Sized> Matcher for &M {
+    fn best_match<'a>(&self, query: &str, candidates: &'a [String]) -> Option<(&'a str, f64)> {
+        (**self).best_match(query, candidates)
+    }
+
+    fn find_similar<'a>(&self, query: &str, candidates: &'a [String]) -> Vec<(&'a str, f64)> {
+        (**self).find_similar(query, candidates)
+    }
+}
+
 /// Jaro-Winkler based matcher using the strsim crate.
 ///
 /// Jaro-Winkler is well-suited for matching variable names because it:
@@ -73,6 +83,52 @@ impl Matcher for JaroWinklerMatcher {
     }
 }
 
+/// Wraps another matcher and nudges up the scores of recently used names.
+///
+/// A typo is usually of a name used nearby, so when two candidates are about
+/// as close to the query, the recent one should come first. Only candidates
+/// the inner matcher already accepts are boosted; the boost never lifts a
+/// name over the inner matcher's threshold.
+#[derive(Debug, Clone)]
+pub struct RecencyBoostMatcher<M> {
+    pub inner: M,
+    /// Names used recently, in any order.
+    pub recent: Vec<String>,
+    /// Added to a recent candidate's score, which is then capped at 1.0.
+    pub boost: f64,
+}
+
+impl<M: Matcher> RecencyBoostMatcher<M> {
+    /// The default boost: enough to break a tie or a near-tie, too small to
+    /// beat a clearly closer name.
+    pub const DEFAULT_BOOST: f64 = 0.02;
+
+    pub fn new(inner: M, recent: Vec<String>) -> Self {
+        Self {
+            inner,
+            recent,
+            boost: Self::DEFAULT_BOOST,
+        }
+    }
+}
+
+impl<M: Matcher> Matcher for RecencyBoostMatcher<M> {
+    fn best_match<'a>(&self, query: &str, candidates: &'a [String]) -> Option<(&'a str, f64)> {
+        self.find_similar(query, candidates).into_iter().next()
+    }
+
+    fn find_similar<'a>(&self, query: &str, candidates: &'a [String]) -> Vec<(&'a str, f64)> {
+        let mut matches = self.inner.find_similar(query, candidates);
+        for (candidate, score) in &mut matches {
+            if self.recent.iter().any(|recent| recent == candidate) {
+                *score = (*score + self.boost).min(1.0);
+            }
+        }
+        matches.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+        matches
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -131,4 +187,44 @@ mod tests {
         let result = loose_matcher.best_match("naem", &candidates);
         assert!(result.is_some());
     }
+
+    #[test]
+    fn recency_breaks_a_tie() {
+        let candidates = vec!["cats".to_string(), "cate".to_string()];
+        let plain = JaroWinklerMatcher::default();
+        let similar = plain.find_similar("cat", &candidates);
+        assert_eq!(similar[0].1, similar[1].1);
+        assert_eq!(similar[0].0, "cats");
+
+        let boosted = RecencyBoostMatcher::new(&plain, vec!["cate".to_string()]);
+        assert_eq!(boosted.best_match("cat", &candidates).unwrap().0, "cate");
+        let names: Vec<_> = boosted
+            .find_similar("cat", &candidates)
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(names, ["cate", "cats"]);
+    }
+
+    #[test]
+    fn recency_does_not_beat_a_closer_name() {
+        let candidates = vec!["player_name".to_string(), "player_game".to_string()];
+        let boosted = RecencyBoostMatcher::new(
+            JaroWinklerMatcher::default(),
+            vec!["player_game".to_string()],
+        );
+        assert_eq!(
+            boosted.best_match("player_naem", &candidates).unwrap().0,
+            "player_name"
+        );
+    }
+
+    #[test]
+    fn recency_does_not_pass_the_threshold() {
+        let candidates = vec!["gold".to_string()];
+        let mut boosted =
+            RecencyBoostMatcher::new(JaroWinklerMatcher::new(0.99), vec!["gold".to_string()]);
+        boosted.boost = 0.5;
+        assert!(boosted.best_match("gld", &candidates).is_none());
+    }
 }
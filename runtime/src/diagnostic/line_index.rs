@@ -0,0 +1,269 @@
+//! Conversion between byte offsets and line/column positions.
+//!
+//! Spans throughout the pipeline are byte offsets into the source. Renderers and
+//! editor integrations need human-facing positions instead, so `LineIndex`
+//! precomputes line starts once and answers position queries in O(log n).
+
+/// A zero-based line/column position in a source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SourcePosition {
+    /// Zero-based line number.
+    pub line: u32,
+    /// Zero-based column, in the unit requested when the position was computed.
+    pub column: u32,
+}
+
+/// The unit a column is counted in.
+///
+/// Non-exhaustive because the `grapheme` feature adds a variant, and turning
+/// a feature on must not break a downstream `match`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ColumnEncoding {
+    /// Bytes of UTF-8.
+    Utf8,
+    /// UTF-16 code units, the LSP default.
+    Utf16,
+    /// Extended grapheme clusters, which is how most text fields move the caret.
+    #[cfg(feature = "grapheme")]
+    Grapheme,
+}
+
+/// Precomputed line boundaries for a source file.
+///
+/// Lines are separated by `\n`; a `\r` before the `\n` is treated as part of the
+/// line terminator and excluded from line text.
+#[derive(Debug, Clone)]
+pub struct LineIndex {
+    source: String,
+    /// Byte offset of the start of each line. Always contains at least one entry.
+    line_starts: Vec<usize>,
+}
+
+impl LineIndex {
+    /// Build an index for the given source.
+    pub fn new(source: &str) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self {
+            source: source.to_string(),
+            line_starts,
+        }
+    }
+
+    /// Number of lines in the source. An empty source has one (empty) line.
+    pub fn line_count(&self) -> usize {
+        self.line_starts.len()
+    }
+
+    /// Byte offset where `line` starts, or `None` if the line does not exist.
+    pub fn line_start(&self, line: u32) -> Option<usize> {
+        self.line_starts.get(line as usize).copied()
+    }
+
+    /// Text of `line` without its line terminator.
+    pub fn line_text(&self, line: u32) -> Option<&str> {
+        let start = self.line_start(line)?;
+        let end = self.line_start(line + 1).unwrap_or(self.source.len());
+        let text = &self.source[start..end];
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        Some(text.strip_suffix('\r').unwrap_or(text))
+    }
+
+    /// Zero-based line and byte column of `offset`.
+    ///
+    /// Offsets past the end of the source are clamped to the end, and offsets
+    /// inside a multi-byte character are moved back to the character's start.
+    pub fn line_col(&self, offset: usize) -> SourcePosition {
+        let offset = self.clamp(offset);
+        let line = self.line_of(offset);
+        SourcePosition {
+            line: line as u32,
+            column: (offset - self.line_starts[line]) as u32,
+        }
+    }
+
+    /// Column of `offset` in UTF-16 code units from the start of its line.
+    pub fn utf16_col(&self, offset: usize) -> u32 {
+        let offset = self.clamp(offset);
+        let start = self.line_starts[self.line_of(offset)];
+        self.source[start..offset]
+            .chars()
+            .map(|c| c.len_utf16() as u32)
+            .sum()
+    }
+
+    /// Byte offset of the UTF-16 column `utf16_col` on `line`, or `None` if the
+    /// line does not exist. The inverse of [`utf16_col`](Self::utf16_col).
+    ///
+    /// Columns past the end of the line are clamped to the end, and a column
+    /// that falls between the two halves of a surrogate pair is moved back to
+    /// the character's start.
+    pub fn utf16_offset(&self, line: u32, utf16_col: u32) -> Option<usize> {
+        let start = self.line_start(line)?;
+        let text = self.line_text(line)?;
+        let mut units = 0;
+        for (i, c) in text.char_indices() {
+            units += c.len_utf16() as u32;
+            if units > utf16_col {
+                return Some(start + i);
+            }
+        }
+        Some(start + text.len())
+    }
+
+    /// Column of `offset` in characters from the start of its line.
+    ///
+    /// This is what plain-text renderers use to align carets under the source.
+    pub fn char_col(&self, offset: usize) -> u32 {
+        let offset = self.clamp(offset);
+        let start = self.line_starts[self.line_of(offset)];
+        self.source[start..offset].chars().count() as u32
+    }
+
+    /// Column of `offset` in extended grapheme clusters from the start of its line.
+    ///
+    /// An emoji sequence such as a flag or a family counts as one column.
+    #[cfg(feature = "grapheme")]
+    pub fn grapheme_col(&self, offset: usize) -> u32 {
+        use unicode_segmentation::UnicodeSegmentation;
+
+        let offset = self.clamp(offset);
+        let start = self.line_starts[self.line_of(offset)];
+        self.source[start..offset].graphemes(true).count() as u32
+    }
+
+    /// Position of `offset` with its column counted in `encoding`.
+    pub fn position(&self, offset: usize, encoding: ColumnEncoding) -> SourcePosition {
+        let position = self.line_col(offset);
+        let column = match encoding {
+            ColumnEncoding::Utf8 => position.column,
+            ColumnEncoding::Utf16 => self.utf16_col(offset),
+            #[cfg(feature = "grapheme")]
+            ColumnEncoding::Grapheme => self.grapheme_col(offset),
+        };
+        SourcePosition {
+            line: position.line,
+            column,
+        }
+    }
+
+    /// Position of `offset` as an LSP client expects it.
+    ///
+    /// LSP columns default to UTF-16 code units; pass `utf16 = false` for clients
+    /// that negotiated UTF-8 positions.
+    pub fn to_lsp_position(&self, offset: usize, utf16: bool) -> SourcePosition {
+        let encoding = if utf16 {
+            ColumnEncoding::Utf16
+        } else {
+            ColumnEncoding::Utf8
+        };
+        self.position(offset, encoding)
+    }
+
+    fn line_of(&self, offset: usize) -> usize {
+        // line_starts[0] == 0, so the partition point is always at least 1
+        self.line_starts.partition_point(|&start| start <= offset) - 1
+    }
+
+    fn clamp(&self, offset: usize) -> usize {
+        let mut offset = offset.min(self.source.len());
+        while !self.source.is_char_boundary(offset) {
+            offset -= 1;
+        }
+        offset
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(line: u32, column: u32) -> SourcePosition {
+        SourcePosition { line, column }
+    }
+
+    #[test]
+    fn line_col_across_lines() {
+        let index = LineIndex::new("Hello\nWorld\n");
+
+        assert_eq!(index.line_count(), 3);
+        assert_eq!(index.line_col(0), pos(0, 0));
+        assert_eq!(index.line_col(4), pos(0, 4));
+        assert_eq!(index.line_col(5), pos(0, 5));
+        assert_eq!(index.line_col(6), pos(1, 0));
+        assert_eq!(index.line_col(12), pos(2, 0));
+        assert_eq!(index.line_col(100), pos(2, 0));
+    }
+
+    #[test]
+    fn line_text_strips_terminators() {
+        let index = LineIndex::new("one\r\ntwo\nthree");
+
+        assert_eq!(index.line_text(0), Some("one"));
+        assert_eq!(index.line_text(1), Some("two"));
+        assert_eq!(index.line_text(2), Some("three"));
+        assert_eq!(index.line_text(3), None);
+    }
+
+    #[test]
+    fn utf16_col_counts_code_units() {
+        // 'é' is 2 bytes / 1 UTF-16 unit; '𐐀' is 4 bytes / 2 UTF-16 units
+        let index = LineIndex::new("x\né𐐀b");
+        let b = "x\né𐐀".len();
+
+        assert_eq!(index.line_col(b), pos(1, 6));
+        assert_eq!(index.utf16_col(b), 3);
+        assert_eq!(index.char_col(b), 2);
+        assert_eq!(index.to_lsp_position(b, true), pos(1, 3));
+        assert_eq!(index.to_lsp_position(b, false), pos(1, 6));
+    }
+
+    #[test]
+    fn utf16_offset_inverts_utf16_col() {
+        // '𐐀' is 4 bytes / 2 UTF-16 units
+        let index = LineIndex::new("x\na𐐀b\r\nz");
+        let line = index.line_start(1).unwrap();
+
+        assert_eq!(index.utf16_offset(1, 3), Some(line + 5));
+        assert_eq!(index.utf16_col(line + 5), 3);
+        assert_eq!(index.utf16_offset(1, 1), Some(line + 1));
+        assert_eq!(index.utf16_offset(1, 0), Some(line));
+        // Between the halves of the surrogate pair
+        assert_eq!(index.utf16_offset(1, 2), Some(line + 1));
+        // Past the end, before the "\r\n"
+        assert_eq!(index.utf16_offset(1, 4), Some(line + 6));
+        assert_eq!(index.utf16_offset(1, 99), Some(line + 6));
+        assert_eq!(index.utf16_offset(3, 0), None);
+    }
+
+    #[cfg(feature = "grapheme")]
+    #[test]
+    fn grapheme_col_counts_clusters() {
+        // The family emoji is 7 code points joined by ZWJ; the flag is 2 regional indicators
+        let line = "👨‍👩‍👧 🇨🇦 x";
+        let index = LineIndex::new(line);
+        let x = line.find('x').unwrap();
+
+        assert_eq!(index.grapheme_col(x), 4);
+        assert!(index.grapheme_col(x) < index.utf16_col(x));
+        assert!(index.grapheme_col(x) < index.line_col(x).column);
+        assert_eq!(index.position(x, ColumnEncoding::Grapheme), pos(0, 4));
+    }
+
+    #[test]
+    fn position_matches_column_helpers() {
+        let index = LineIndex::new("é𐐀b");
+        let b = "é𐐀".len();
+
+        assert_eq!(index.position(b, ColumnEncoding::Utf8), index.line_col(b));
+        assert_eq!(index.position(b, ColumnEncoding::Utf16), pos(0, 3));
+    }
+
+    #[test]
+    fn offset_inside_char_snaps_to_char_start() {
+        let index = LineIndex::new("é");
+        assert_eq!(index.line_col(1), pos(0, 0));
+    }
+}
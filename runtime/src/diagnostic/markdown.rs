@@ -0,0 +1,209 @@
+//! Markdown renderer for posting diagnostics to chat tools and docs.
+
+use std::fmt::Write;
+
+use super::{Diagnostic, Label, LabelStyle, LineIndex, Renderer};
+
+/// Renders diagnostics as Markdown with no color codes.
+///
+/// The message becomes a heading, the labelled source lines go in a fenced
+/// code block with caret underlines, and notes and suggestions become a
+/// bulleted list.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct MarkdownRenderer;
+
+impl MarkdownRenderer {
+    /// Create a new Markdown renderer.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Renderer for MarkdownRenderer {
+    fn render(&self, diagnostic: &Diagnostic, source_id: &str, source: &str) -> String {
+        // Writing to a String cannot fail
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "### {}: {}",
+            diagnostic.severity.as_str(),
+            diagnostic.message
+        );
+
+        let index = LineIndex::new(source);
+        let mut labels: Vec<&Label> = diagnostic.labels.iter().collect();
+        labels.sort_by_key(|label| label.span.start);
+
+        if let Some(first) = diagnostic.primary_span().or(labels.first().map(|l| l.span)) {
+            let position = index.line_col(first.start);
+            let column = index.char_col(first.start);
+            let _ = writeln!(
+                out,
+                "\n`{}:{}:{}`",
+                source_id,
+                position.line + 1,
+                column + 1
+            );
+        }
+
+        if !labels.is_empty() {
+            out.push('\n');
+            write_snippet(&mut out, &index, &labels);
+        }
+
+        if !diagnostic.suggestions.is_empty() || !diagnostic.notes.is_empty() {
+            out.push('\n');
+        }
+        for suggestion in &diagnostic.suggestions {
+            let _ = writeln!(out, "- **help:** {}", suggestion.message);
+        }
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "- **note:** {}", note);
+        }
+
+        out
+    }
+}
+
+/// Write the labelled source lines in a fenced code block.
+///
+/// Each line is shown once, followed by one underline row per label that starts
+/// on it. Labels spanning several lines are underlined to the end of their first
+/// line.
+fn write_snippet(out: &mut String, index: &LineIndex, labels: &[&Label]) {
+    let mut lines: Vec<u32> = labels
+        .iter()
+        .map(|label| index.line_col(label.span.start).line)
+        .collect();
+    lines.dedup();
+
+    let gutter = lines
+        .iter()
+        .map(|line| (line + 1).to_string().len())
+        .max()
+        .unwrap_or(1);
+    let texts: Vec<&str> = lines
+        .iter()
+        .map(|&line| index.line_text(line).unwrap_or(""))
+        .collect();
+    let fence = code_fence(&texts);
+
+    let _ = writeln!(out, "{}text", fence);
+    for (&line, text) in lines.iter().zip(&texts) {
+        let _ = writeln!(out, "{:>gutter$} | {}", line + 1, text);
+
+        for label in labels
+            .iter()
+            .filter(|l| index.line_col(l.span.start).line == line)
+        {
+            let start = index.char_col(label.span.start) as usize;
+            let end_position = index.line_col(label.span.end);
+            let continues = end_position.line > line;
+            let end = if continues {
+                text.chars().count()
+            } else {
+                index.char_col(label.span.end) as usize
+            };
+            let marker = match label.style {
+                LabelStyle::Primary => "^",
+                LabelStyle::Secondary => "-",
+            };
+            let width = end.saturating_sub(start).max(1);
+            let _ = write!(
+                out,
+                "{:gutter$} | {}{}",
+                "",
+                " ".repeat(start),
+                marker.repeat(width)
+            );
+            if !label.message.is_empty() {
+                let _ = write!(out, " {}", label.message);
+            }
+            if continues {
+                out.push_str(" (continues)");
+            }
+            out.push('\n');
+        }
+    }
+    let _ = writeln!(out, "{}", fence);
+}
+
+/// A backtick fence longer than any backtick run in the snippet.
+fn code_fence(texts: &[&str]) -> String {
+    let longest_run = texts
+        .iter()
+        .flat_map(|text| text.split(|c| c != '`'))
+        .map(str::len)
+        .max()
+        .unwrap_or(0);
+    "`".repeat((longest_run + 1).max(3))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Span;
+
+    #[test]
+    fn render_undefined_variable() {
+        let source = "save gold = 5\nYou have {gld} gold.\n";
+        let start = source.find("{gld}").unwrap();
+        let span = Span {
+            start,
+            end: start + 5,
+        };
+        let diagnostic = Diagnostic::error(
+            "undefined variable 'gld'",
+            span,
+            "not defined in this scope",
+        )
+        .with_suggestion("did you mean 'gold'?", span, "gold");
+
+        let output = MarkdownRenderer::new().render(&diagnostic, "shop.bobbin", source);
+
+        assert_eq!(
+            output,
+            "### error: undefined variable 'gld'\n\
+             \n\
+             `shop.bobbin:2:10`\n\
+             \n\
+             ```text\n\
+             2 | You have {gld} gold.\n  \
+             |          ^^^^^ not defined in this scope\n\
+             ```\n\
+             \n\
+             - **help:** did you mean 'gold'?\n"
+        );
+    }
+
+    #[test]
+    fn render_secondary_label_and_continuation() {
+        let source = "temp x = 1\ntemp x = 2\nline\n";
+        let diagnostic = Diagnostic::error(
+            "variable 'x' shadows previous declaration",
+            Span { start: 16, end: 26 },
+            "shadows",
+        )
+        .with_secondary(Span { start: 5, end: 6 }, "declared here");
+
+        let output = MarkdownRenderer::new().render(&diagnostic, "a.bobbin", source);
+
+        assert!(output.contains("`a.bobbin:2:6`"), "{}", output);
+        assert!(
+            output.contains("1 | temp x = 1\n  |      - declared here\n"),
+            "{}",
+            output
+        );
+        assert!(
+            output.contains("2 | temp x = 2\n  |      ^^^^^ shadows (continues)\n"),
+            "{}",
+            output
+        );
+    }
+
+    #[test]
+    fn fence_outgrows_backticks_in_source() {
+        assert_eq!(code_fence(&["say ```hi```"]), "````");
+        assert_eq!(code_fence(&["plain"]), "```");
+    }
+}
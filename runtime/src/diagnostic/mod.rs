@@ -15,11 +15,21 @@
 //! allowing them to be swapped out if needed.
 
 mod convert;
+mod dedupe;
+mod filter;
 mod fuzzy;
+mod line_index;
+mod markdown;
+mod plain;
 mod render;
 mod types;
 
 pub use convert::{DiagnosticContext, IntoDiagnostic};
-pub use fuzzy::{JaroWinklerMatcher, Matcher};
+pub use dedupe::dedupe;
+pub use filter::filter_by_severity;
+pub use fuzzy::{JaroWinklerMatcher, Matcher, RecencyBoostMatcher};
+pub use line_index::{ColumnEncoding, LineIndex, SourcePosition};
+pub use markdown::MarkdownRenderer;
+pub use plain::PlainRenderer;
 pub use render::{AriadneRenderer, Renderer};
 pub use types::{Diagnostic, Label, LabelStyle, Severity, Suggestion};
@@ -0,0 +1,223 @@
+//! Plain-text renderer with caret underlines.
+
+use std::fmt::Write;
+
+use super::{Diagnostic, Label, LabelStyle, LineIndex, Renderer};
+
+/// Renders diagnostics as uncolored, grep-friendly text.
+///
+/// Each diagnostic starts with a `file:line:col: severity: message` header,
+/// followed by the source line and a `^^^` underline under the primary span.
+/// Further primary labels are underlined the same way under their own
+/// `file:line:col:` line, and secondary labels get a `file:line:col: note:`
+/// line. This renderer does not depend on `ariadne`, which makes it suitable
+/// for log files.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PlainRenderer;
+
+impl PlainRenderer {
+    /// Create a new plain-text renderer.
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Renderer for PlainRenderer {
+    fn render(&self, diagnostic: &Diagnostic, source_id: &str, source: &str) -> String {
+        // Writing to a String cannot fail
+        let mut out = String::new();
+        let index = LineIndex::new(source);
+        let severity = diagnostic.severity.as_str();
+
+        let primary = diagnostic.primary_label().or(diagnostic.labels.first());
+
+        match primary {
+            Some(label) => {
+                let _ = writeln!(
+                    out,
+                    "{}: {}: {}",
+                    location(&index, source_id, label.span.start),
+                    severity,
+                    diagnostic.message
+                );
+                write_underlined(&mut out, &index, label);
+            }
+            None => {
+                let _ = writeln!(out, "{}: {}: {}", source_id, severity, diagnostic.message);
+            }
+        }
+
+        for label in &diagnostic.labels {
+            if primary.is_some_and(|p| std::ptr::eq(p, label)) {
+                continue;
+            }
+            if label.style == LabelStyle::Primary {
+                let _ = writeln!(out, "{}:", location(&index, source_id, label.span.start));
+                write_underlined(&mut out, &index, label);
+                continue;
+            }
+            let _ = writeln!(
+                out,
+                "{}: note: {}",
+                location(&index, source_id, label.span.start),
+                label.message
+            );
+        }
+        for suggestion in &diagnostic.suggestions {
+            let _ = writeln!(out, "  = help: {}", suggestion.message);
+        }
+        for note in &diagnostic.notes {
+            let _ = writeln!(out, "  = note: {}", note);
+        }
+
+        out
+    }
+}
+
+/// One-based `file:line:col` for a byte offset.
+fn location(index: &LineIndex, source_id: &str, offset: usize) -> String {
+    let line = index.line_col(offset).line;
+    format!("{}:{}:{}", source_id, line + 1, index.char_col(offset) + 1)
+}
+
+/// Write the label's first source line with a caret underline beneath it.
+///
+/// Spans that run past the end of their first line are underlined to the end of
+/// that line and marked with the line they end on.
+fn write_underlined(out: &mut String, index: &LineIndex, label: &Label) {
+    let line = index.line_col(label.span.start).line;
+    let text = index.line_text(line).unwrap_or("");
+    let start = index.char_col(label.span.start) as usize;
+    let end_line = index.line_col(label.span.end).line;
+    let end = if end_line > line {
+        text.chars().count()
+    } else {
+        index.char_col(label.span.end) as usize
+    };
+
+    // Keep tabs from the source so the carets line up in any tab width
+    let padding: String = text
+        .chars()
+        .take(start)
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+
+    let _ = writeln!(out, "{}", text);
+    let _ = write!(
+        out,
+        "{}{}",
+        padding,
+        "^".repeat(end.saturating_sub(start).max(1))
+    );
+    if !label.message.is_empty() {
+        let _ = write!(out, " {}", label.message);
+    }
+    if end_line > line {
+        let _ = write!(out, " (continues to line {})", end_line + 1);
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::token::Span;
+
+    #[test]
+    fn render_single_line_span() {
+        let source = "save gold = 5\nYou have {gld} gold.\n";
+        let start = source.find("{gld}").unwrap();
+        let span = Span {
+            start,
+            end: start + 5,
+        };
+        let diagnostic = Diagnostic::error(
+            "undefined variable 'gld'",
+            span,
+            "not defined in this scope",
+        )
+        .with_suggestion("did you mean 'gold'?", span, "gold");
+
+        let output = PlainRenderer::new().render(&diagnostic, "shop.bobbin", source);
+
+        assert_eq!(
+            output,
+            "shop.bobbin:2:10: error: undefined variable 'gld'\n\
+             You have {gld} gold.\n\
+             \x20        ^^^^^ not defined in this scope\n\
+             \x20 = help: did you mean 'gold'?\n"
+        );
+    }
+
+    #[test]
+    fn render_multi_line_span() {
+        let source = "first line\nsecond\nthird\n";
+        let diagnostic = Diagnostic::warning(
+            "block spans lines",
+            Span { start: 6, end: 15 },
+            "starts here",
+        )
+        .with_secondary(Span { start: 18, end: 23 }, "related")
+        .with_note("only the first line is underlined");
+
+        let output = PlainRenderer::new().render(&diagnostic, "a.bobbin", source);
+
+        assert_eq!(
+            output,
+            "a.bobbin:1:7: warning: block spans lines\n\
+             first line\n\
+             \x20     ^^^^ starts here (continues to line 2)\n\
+             a.bobbin:3:1: note: related\n\
+             \x20 = note: only the first line is underlined\n"
+        );
+    }
+
+    #[test]
+    fn render_two_primary_labels() {
+        let source = "save gold = 10\nset gold = \"lots\"\n";
+        let diagnostic = Diagnostic::error(
+            "type mismatch in assignment",
+            Span { start: 5, end: 9 },
+            "declared as a number",
+        )
+        .with_primary(Span { start: 26, end: 32 }, "assigned a string");
+
+        let output = PlainRenderer::new().render(&diagnostic, "a.bobbin", source);
+
+        assert_eq!(
+            output,
+            "a.bobbin:1:6: error: type mismatch in assignment\n\
+             save gold = 10\n\
+             \x20    ^^^^ declared as a number\n\
+             a.bobbin:2:12:\n\
+             set gold = \"lots\"\n\
+             \x20          ^^^^^^ assigned a string\n"
+        );
+    }
+
+    #[test]
+    fn render_without_labels() {
+        let diagnostic = Diagnostic {
+            labels: vec![],
+            ..Diagnostic::error(
+                "choice index 3 out of bounds",
+                Span { start: 0, end: 0 },
+                "",
+            )
+        };
+
+        let output = PlainRenderer::new().render(&diagnostic, "a.bobbin", "");
+
+        assert_eq!(output, "a.bobbin: error: choice index 3 out of bounds\n");
+    }
+
+    #[test]
+    fn underline_keeps_tabs() {
+        let source = "\tHi {x}\n";
+        let diagnostic = Diagnostic::error("undefined variable 'x'", Span { start: 4, end: 7 }, "");
+
+        let output = PlainRenderer::new().render(&diagnostic, "a.bobbin", source);
+
+        assert!(output.ends_with("\tHi {x}\n\t   ^^^\n"), "{:?}", output);
+    }
+}
@@ -5,7 +5,7 @@
 
 use ariadne::{Color, Config, IndexType, Label as AriadneLabel, Report, ReportKind, Source};
 
-use super::{Diagnostic, LabelStyle, Severity};
+use super::{Diagnostic, LabelStyle, Severity, filter_by_severity};
 
 /// Trait for rendering diagnostics to a string.
 ///
@@ -28,6 +28,21 @@ pub trait Renderer {
             .collect::<Vec<_>>()
             .join("\n")
     }
+
+    /// Render the diagnostics at `min` severity or above, skipping the rest.
+    fn render_filtered(
+        &self,
+        diagnostics: &[Diagnostic],
+        min: Severity,
+        source_id: &str,
+        source: &str,
+    ) -> String {
+        filter_by_severity(diagnostics, min)
+            .into_iter()
+            .map(|d| self.render(d, source_id, source))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
 }
 
 /// Ariadne-based renderer for beautiful terminal output.
@@ -61,10 +76,15 @@ impl Renderer for AriadneRenderer {
             Severity::Help => ReportKind::Advice,
         };
 
-        // Start building the report with the first label's span as the primary location
-        let offset = diagnostic.labels.first().map(|l| l.span.start).unwrap_or(0);
+        // Report at the first primary label; ariadne draws every label, so any
+        // further primary labels are still shown
+        let offset = diagnostic
+            .primary_label()
+            .or(diagnostic.labels.first())
+            .map(|l| l.span.start)
+            .unwrap_or(0);
 
-        let mut builder = Report::<(&str, std::ops::Range<usize>)>::build(kind, source_id, offset)
+        let mut builder = Report::build(kind, (source_id, offset..offset))
             .with_config(
                 Config::default()
                     .with_color(self.colors)
@@ -143,6 +163,24 @@ mod tests {
         assert!(output.contains("did you mean"));
     }
 
+    #[test]
+    fn render_each_suggestion_and_note() {
+        let span = Span { start: 7, end: 11 };
+        let diagnostic = Diagnostic::error("undefined variable 'naem'", span, "not defined")
+            .with_suggestion("did you mean 'name'?", span, "name")
+            .with_suggestion("did you mean 'team'?", span, "team")
+            .with_note("first note")
+            .with_note("second note");
+
+        let renderer = AriadneRenderer::without_colors();
+        let output = renderer.render(&diagnostic, "test.bobbin", "Hello, naem!");
+
+        assert!(output.contains("did you mean 'name'?"), "{}", output);
+        assert!(output.contains("did you mean 'team'?"), "{}", output);
+        assert!(output.contains("first note"), "{}", output);
+        assert!(output.contains("second note"), "{}", output);
+    }
+
     #[test]
     fn render_with_secondary_label() {
         let diagnostic = Diagnostic::error(
@@ -160,6 +198,46 @@ mod tests {
         assert!(output.contains("previously declared"));
     }
 
+    #[test]
+    fn render_with_two_primary_labels() {
+        let source = "save gold = 10\nset gold = \"lots\"\n";
+        let diagnostic = Diagnostic::error(
+            "type mismatch in assignment",
+            Span { start: 19, end: 23 },
+            "declared as a number",
+        )
+        .with_primary(Span { start: 26, end: 32 }, "assigned a string");
+
+        let renderer = AriadneRenderer::without_colors();
+        let output = renderer.render(&diagnostic, "test.bobbin", source);
+
+        assert!(output.contains("declared as a number"), "{}", output);
+        assert!(output.contains("assigned a string"), "{}", output);
+    }
+
+    #[test]
+    fn render_filtered_skips_lower_severities() {
+        let source = "temp x = 1\nHello, foo!";
+        let diagnostics = [
+            Diagnostic::warning(
+                "unused variable 'x'",
+                Span { start: 5, end: 6 },
+                "never read",
+            ),
+            Diagnostic::error(
+                "undefined variable 'foo'",
+                Span { start: 18, end: 21 },
+                "not defined",
+            ),
+        ];
+
+        let renderer = AriadneRenderer::without_colors();
+        let output = renderer.render_filtered(&diagnostics, Severity::Error, "test.bobbin", source);
+
+        assert!(output.contains("undefined variable 'foo'"), "{}", output);
+        assert!(!output.contains("unused variable"), "{}", output);
+    }
+
     #[test]
     fn render_multiline() {
         // Test that multiline source renders correctly
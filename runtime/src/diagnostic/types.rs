@@ -43,6 +43,49 @@ impl Diagnostic {
         }
     }
 
+    /// Create a new note diagnostic with a primary label.
+    pub fn note(message: impl Into<String>, span: Span, label: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Note,
+            message: message.into(),
+            labels: vec![Label::primary(span, label)],
+            notes: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// The first primary label, if any.
+    ///
+    /// A diagnostic can have several primary labels, such as both sides of a
+    /// mismatch. The first one is where the diagnostic is reported: it sets
+    /// the header location and, for LSP, the diagnostic's range.
+    pub fn primary_label(&self) -> Option<&Label> {
+        self.labels
+            .iter()
+            .find(|label| label.style == LabelStyle::Primary)
+    }
+
+    /// The span of the first primary label, if any.
+    pub fn primary_span(&self) -> Option<Span> {
+        self.primary_label().map(|label| label.span)
+    }
+
+    /// Every label except the first primary one, in order. These are what an
+    /// LSP client receives as related information.
+    pub fn related_labels(&self) -> impl Iterator<Item = &Label> {
+        let primary = self.primary_label();
+        self.labels
+            .iter()
+            .filter(move |label| !primary.is_some_and(|p| std::ptr::eq(p, *label)))
+    }
+
+    /// Add another primary label, for a problem that is equally about two
+    /// places.
+    pub fn with_primary(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label::primary(span, message));
+        self
+    }
+
     /// Add a secondary label to this diagnostic.
     pub fn with_secondary(mut self, span: Span, message: impl Into<String>) -> Self {
         self.labels.push(Label::secondary(span, message));
@@ -55,6 +98,17 @@ impl Diagnostic {
         self
     }
 
+    /// Promote a warning to an error, for builds that deny warnings. Other
+    /// severities are left alone.
+    pub fn denied(mut self) -> Self {
+        if self.severity == Severity::Warning {
+            self.severity = Severity::Error;
+            self.notes
+                .push("this warning is treated as an error because warnings are denied".into());
+        }
+        self
+    }
+
     /// Add a suggestion with replacement text.
     pub fn with_suggestion(
         mut self,
@@ -72,7 +126,7 @@ impl Diagnostic {
 }
 
 /// The severity level of a diagnostic.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Severity {
     /// A fatal error that prevents compilation.
     Error,
@@ -84,6 +138,43 @@ pub enum Severity {
     Help,
 }
 
+impl Severity {
+    /// Lowercase name used in plain-text output (e.g. `error`, `warning`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        }
+    }
+
+    /// Whether this severity is `min` or more serious. Errors outrank
+    /// warnings, which outrank notes and help; notes and help rank equally.
+    pub fn is_at_least(self, min: Severity) -> bool {
+        self.rank() >= min.rank()
+    }
+
+    /// The LSP `DiagnosticSeverity` code: 1 for errors, 2 for warnings, 3
+    /// (information) for notes, and 4 (hint) for help.
+    pub fn lsp_code(self) -> u8 {
+        match self {
+            Severity::Error => 1,
+            Severity::Warning => 2,
+            Severity::Note => 3,
+            Severity::Help => 4,
+        }
+    }
+
+    fn rank(self) -> u8 {
+        match self {
+            Severity::Error => 2,
+            Severity::Warning => 1,
+            Severity::Note | Severity::Help => 0,
+        }
+    }
+}
+
 /// A labeled span in the source code.
 #[derive(Debug, Clone)]
 pub struct Label {
@@ -134,3 +225,29 @@ pub struct Suggestion {
     /// The replacement text.
     pub replacement: String,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn primary_label_is_the_first_primary_not_the_first_label() {
+        let diagnostic = Diagnostic {
+            labels: vec![
+                Label::secondary(Span { start: 0, end: 4 }, "declared here"),
+                Label::primary(Span { start: 10, end: 14 }, "first"),
+                Label::primary(Span { start: 20, end: 24 }, "second"),
+            ],
+            ..Diagnostic::error("type mismatch", Span { start: 0, end: 0 }, "")
+        };
+
+        assert_eq!(diagnostic.primary_label().unwrap().message, "first");
+        assert_eq!(diagnostic.primary_span(), Some(Span { start: 10, end: 14 }));
+
+        let related: Vec<&str> = diagnostic
+            .related_labels()
+            .map(|label| label.message.as_str())
+            .collect();
+        assert_eq!(related, ["declared here", "second"]);
+    }
+}
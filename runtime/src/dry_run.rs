@@ -0,0 +1,142 @@
+//! Exploring every path through a script without a player.
+//!
+//! A dry run executes the bytecode from the start and forks at each choice,
+//! so QA can find runtime errors (missing save or extern variables, bad
+//! arithmetic) on branches nobody clicked through. Writes go to a scratch
+//! layer over the real storage, which is never modified.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+
+use crate::chunk::{Chunk, Value};
+use crate::storage::{HostState, VariableStorage};
+use crate::vm::{RuntimeError, StepResult, VM};
+
+/// Which choices a dry run follows at each choice set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChoiceStrategy {
+    /// Follow every choice.
+    #[default]
+    All,
+    /// Follow only the first choice, the path a player mashing "continue" takes.
+    First,
+}
+
+impl ChoiceStrategy {
+    fn choices(self, count: usize) -> std::ops::Range<usize> {
+        match self {
+            ChoiceStrategy::All => 0..count,
+            ChoiceStrategy::First => 0..count.min(1),
+        }
+    }
+}
+
+/// Run `chunk` from the start along every path `strategy` allows and collect
+/// the runtime errors hit, each reported once. Each branch continues the
+/// random sequence of the path that led to it.
+///
+/// Each line and each choice branch is explored once, which keeps `goto`
+/// loops finite. A line reached again along a different path is not re-run,
+/// so errors that depend on the earlier path's state can be missed.
+pub(crate) fn dry_run(
+    chunk: &Chunk,
+    storage: &Arc<dyn VariableStorage>,
+    host: &Arc<dyn HostState>,
+    strategy: ChoiceStrategy,
+    seed: Option<u64>,
+) -> Vec<RuntimeError> {
+    let root = Scratch::over(Arc::clone(storage));
+    let mut vm = VM::new(chunk.clone(), root.clone(), Arc::clone(host));
+    if let Some(seed) = seed {
+        vm.set_seed(seed);
+    }
+    let mut pending = vec![(vm, root, None)];
+
+    // Pause points already explored: (offset, choice taken)
+    let mut explored: HashSet<(usize, Option<usize>)> = HashSet::new();
+    let mut failed_at: HashSet<usize> = HashSet::new();
+    let mut errors = Vec::new();
+
+    while let Some((mut vm, scratch, choice)) = pending.pop() {
+        let mut result = match choice {
+            Some(index) => vm.select_and_continue(index),
+            None => vm.step(),
+        };
+        loop {
+            match result {
+                Err(err) => {
+                    if failed_at.insert(vm.ip()) {
+                        errors.push(err);
+                    }
+                    break;
+                }
+                Ok(StepResult::Done) => break,
+                Ok(StepResult::Line(_) | StepResult::Wait(_)) => {
+                    if !explored.insert((vm.ip(), None)) {
+                        break;
+                    }
+                    result = vm.step();
+                }
+                Ok(StepResult::Choice(choices)) => {
+                    // Pushed in reverse so the first choice is explored first
+                    for index in strategy.choices(choices.len()).rev() {
+                        if explored.insert((vm.ip(), Some(index))) {
+                            let branch = scratch.fork();
+                            pending.push((vm.fork(branch.clone()), branch, Some(index)));
+                        }
+                    }
+                    break;
+                }
+            }
+        }
+    }
+    errors
+}
+
+/// Storage that records writes locally and reads through to a base storage.
+struct Scratch {
+    base: Arc<dyn VariableStorage>,
+    writes: RwLock<HashMap<String, Value>>,
+}
+
+impl Scratch {
+    fn over(base: Arc<dyn VariableStorage>) -> Arc<Self> {
+        Arc::new(Self {
+            base,
+            writes: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// A copy for a new branch, so its writes stay out of this one.
+    fn fork(&self) -> Arc<Self> {
+        Arc::new(Self {
+            base: Arc::clone(&self.base),
+            writes: RwLock::new(self.writes.read().unwrap().clone()),
+        })
+    }
+}
+
+impl VariableStorage for Scratch {
+    fn get(&self, name: &str) -> Option<Value> {
+        let written = self.writes.read().unwrap().get(name).cloned();
+        written.or_else(|| self.base.get(name))
+    }
+
+    fn set(&self, name: &str, value: Value) {
+        self.writes.write().unwrap().insert(name.to_string(), value);
+    }
+
+    fn initialize_if_absent(&self, name: &str, default: Value) {
+        if !self.base.contains(name) {
+            self.writes
+                .write()
+                .unwrap()
+                .entry(name.to_string())
+                .or_insert(default);
+        }
+    }
+
+    fn contains(&self, name: &str) -> bool {
+        self.writes.read().unwrap().contains_key(name) || self.base.contains(name)
+    }
+}
@@ -0,0 +1,242 @@
+//! The branching structure of a compiled script, for visualization.
+//!
+//! Designers want to see their dialogue as a node graph. `analyze_flow` walks
+//! the bytecode rather than the AST, so the graph shows what will actually
+//! run, with `goto` loops as edges back to earlier nodes.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::chunk::{Chunk, Instruction, TextKey, VarSource};
+
+/// A graph of the lines and choice sets reachable from the start of a script.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowGraph {
+    /// The first node the script reaches, or `None` for an empty chunk.
+    pub start: Option<usize>,
+    /// Every reachable node, ordered by id.
+    pub nodes: Vec<FlowNode>,
+    pub edges: Vec<FlowEdge>,
+}
+
+/// A line, choice set, or end of the script.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowNode {
+    /// Offset of the node's instruction in the chunk, unique within the graph.
+    pub id: usize,
+    pub kind: FlowNodeKind,
+    /// A line's text, or a choice set's choices separated by `" / "`. Empty for
+    /// the end. Interpolated variables show as `{name}`, other computed values
+    /// as `{…}`.
+    pub label: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FlowNodeKind {
+    Line,
+    ChoiceSet,
+    End,
+}
+
+/// Control passing from one node to the next.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FlowEdge {
+    pub from: usize,
+    pub to: usize,
+    /// The choice's text, for an edge out of a choice set.
+    pub label: Option<String>,
+}
+
+/// Build the flow graph of `chunk`.
+///
+/// Each node is visited once, so loops end the walk instead of repeating it.
+/// Nodes that no path reaches are left out.
+pub fn analyze_flow(chunk: &Chunk) -> FlowGraph {
+    let texts = texts(chunk);
+    let start = next_node(&chunk.code, 0);
+    let mut nodes = Vec::new();
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    let mut queue: VecDeque<usize> = start.into_iter().collect();
+
+    while let Some(id) = queue.pop_front() {
+        if !seen.insert(id) {
+            continue;
+        }
+        let mut link = |to: Option<usize>, label: Option<String>| {
+            if let Some(to) = to {
+                edges.push(FlowEdge {
+                    from: id,
+                    to,
+                    label,
+                });
+                queue.push_back(to);
+            }
+        };
+        let (kind, label) = match &chunk.code[id] {
+            Instruction::Line => {
+                link(next_node(&chunk.code, id + 1), None);
+                (FlowNodeKind::Line, texts[&id].concat())
+            }
+            Instruction::ChoiceSet { targets, .. } => {
+                let choices = &texts[&id];
+                for (target, text) in targets.iter().zip(choices) {
+                    link(next_node(&chunk.code, *target), Some(text.clone()));
+                }
+                (FlowNodeKind::ChoiceSet, choices.join(" / "))
+            }
+            _ => (FlowNodeKind::End, String::new()),
+        };
+        nodes.push(FlowNode { id, kind, label });
+    }
+
+    nodes.sort_by_key(|node| node.id);
+    FlowGraph {
+        start,
+        nodes,
+        edges,
+    }
+}
+
+/// The first `Line`, `ChoiceSet`, or `Return` that running from `offset`
+/// reaches, following jumps. `None` if the code runs out or jumps in a circle
+/// first.
+fn next_node(code: &[Instruction], mut offset: usize) -> Option<usize> {
+    let mut jumped = HashSet::new();
+    while let Some(instruction) = code.get(offset) {
+        match instruction {
+            Instruction::Line | Instruction::ChoiceSet { .. } | Instruction::Return => {
+                return Some(offset);
+            }
+            Instruction::Jump { target } => {
+                if !jumped.insert(offset) {
+                    return None;
+                }
+                offset = *target;
+            }
+            _ => offset += 1,
+        }
+    }
+    None
+}
+
+/// A piece of text being built on the stack.
+#[derive(Debug, Clone)]
+enum Piece {
+    Text(String),
+    Local(usize),
+    Computed,
+}
+
+/// The text of each `Line` (one entry) and `ChoiceSet` (one per choice), by
+/// offset.
+///
+/// Tracks what the code pushes in instruction order, which matches the order
+/// text is built in: each line's and choice's text is computed just before
+/// the instruction that shows it.
+fn texts(chunk: &Chunk) -> HashMap<usize, Vec<String>> {
+    let mut stack: Vec<Vec<Piece>> = Vec::new();
+    let mut texts = HashMap::new();
+    for (offset, instruction) in chunk.code.iter().enumerate() {
+        let pops = match instruction {
+            Instruction::Constant { index } => {
+                let text = chunk
+                    .constants
+                    .get(*index)
+                    .map(|value| value.to_string_value())
+                    .unwrap_or_default();
+                stack.push(vec![Piece::Text(text)]);
+                0
+            }
+            Instruction::GetLocal { slot } => {
+                stack.push(vec![Piece::Local(*slot)]);
+                0
+            }
+            Instruction::GetStorage { name } | Instruction::GetHost { name } => {
+                stack.push(vec![Piece::Text(format!("{{{}}}", name))]);
+                0
+            }
+            Instruction::SetLocal { .. }
+            | Instruction::SetStorage { .. }
+            | Instruction::InitStorage { .. }
+            | Instruction::ShortCircuit { .. } => 1,
+            Instruction::Not | Instruction::Negate => {
+                stack.pop();
+                stack.push(vec![Piece::Computed]);
+                0
+            }
+            Instruction::Add
+            | Instruction::Subtract
+            | Instruction::Multiply
+            | Instruction::Divide
+            | Instruction::Equal
+            | Instruction::NotEqual
+            | Instruction::Less
+            | Instruction::LessEqual
+            | Instruction::Greater
+            | Instruction::GreaterEqual
+            | Instruction::Random => {
+                stack.truncate(stack.len().saturating_sub(2));
+                stack.push(vec![Piece::Computed]);
+                0
+            }
+            Instruction::Concat { count } => {
+                let parts = stack.split_off(stack.len().saturating_sub(*count));
+                stack.push(parts.concat());
+                0
+            }
+            Instruction::Line => {
+                let pieces = stack.pop().unwrap_or_default();
+                let key = chunk.line_keys.get(&offset);
+                texts.insert(offset, vec![render(&pieces, key)]);
+                0
+            }
+            Instruction::ChoiceSet { count, .. } => {
+                let choices = stack.split_off(stack.len().saturating_sub(*count));
+                let keys = chunk.choice_keys.get(&offset);
+                let rendered = choices
+                    .iter()
+                    .enumerate()
+                    .map(|(index, pieces)| render(pieces, keys.and_then(|keys| keys.get(index))))
+                    .collect();
+                texts.insert(offset, rendered);
+                0
+            }
+            // A declared local stays on the stack as the variable's value
+            Instruction::DeclareLocal { .. }
+            | Instruction::CheckBool { .. }
+            | Instruction::Capture { .. }
+            | Instruction::Format { .. }
+            | Instruction::Wait { .. }
+            | Instruction::Jump { .. }
+            | Instruction::Return => 0,
+        };
+        stack.truncate(stack.len().saturating_sub(pops));
+    }
+    texts
+}
+
+/// Join `pieces`, naming locals from the variables the text's string table key
+/// lists.
+fn render(pieces: &[Piece], key: Option<&TextKey>) -> String {
+    let local_name = |slot: usize| {
+        key?.vars.iter().find_map(|(name, source)| match source {
+            VarSource::Local { slot: s } if *s == slot => Some(name.as_str()),
+            _ => None,
+        })
+    };
+    pieces
+        .iter()
+        .map(|piece| match piece {
+            Piece::Text(text) => text.clone(),
+            Piece::Local(slot) => match local_name(*slot) {
+                Some(name) => format!("{{{}}}", name),
+                None => "{…}".to_string(),
+            },
+            Piece::Computed => "{…}".to_string(),
+        })
+        .collect()
+}
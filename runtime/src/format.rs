@@ -0,0 +1,216 @@
+//! Canonical layout for Bobbin source.
+//!
+//! The formatter works from the token stream rather than raw text, so it only
+//! rewrites whitespace the scanner treats as layout: indentation, blank lines,
+//! and the spacing inside declarations. Line and choice text, interpolations
+//! included, and comments are copied from the source unchanged.
+
+use crate::scanner::Scanner;
+use crate::token::{Token, TokenKind};
+
+/// Spaces per nesting level in formatted output.
+const INDENT_WIDTH: usize = 4;
+
+/// Rewrite `source` in the canonical layout.
+///
+/// - Each nesting level is indented by four spaces
+/// - Trailing whitespace is removed and runs of blank lines collapse to one
+/// - Declarations, assignments, and other keyword statements get single spaces
+///   between their parts (`save gold = 10`, `set gold += 5`, `== start ==`)
+/// - The result ends with exactly one newline, unless its last line ends in a
+///   literal `\`, which a newline would turn into a line continuation
+///
+/// Formatting is idempotent. Source with lexical errors (such as indentation
+/// that mixes tabs and spaces) is returned unchanged, since its layout cannot
+/// be trusted.
+pub fn format_source(source: &str) -> String {
+    let mut formatter = Formatter {
+        source,
+        out: String::new(),
+        depth: 0,
+        line: Vec::new(),
+        line_start: 0,
+        previous_end: None,
+    };
+    for token in Scanner::new(source).tokens() {
+        let Ok(token) = token else {
+            return source.to_string();
+        };
+        match token.kind {
+            // Indents and dedents open a line, except for the dedents that
+            // close a file without a trailing newline
+            TokenKind::Indent => {
+                formatter.flush(token.span.start);
+                formatter.depth += 1;
+            }
+            TokenKind::Dedent => {
+                formatter.flush(token.span.start);
+                formatter.depth = formatter.depth.saturating_sub(1);
+            }
+            TokenKind::NewLine => formatter.flush(token.span.start),
+            _ => {
+                formatter.line.push(token);
+                continue;
+            }
+        }
+        formatter.line_start = formatter.line_start.max(token.span.end);
+    }
+    formatter.flush(source.len());
+    formatter.out
+}
+
+struct Formatter<'a> {
+    source: &'a str,
+    out: String,
+    depth: usize,
+    /// Tokens of the statement being collected
+    line: Vec<Token<'a>>,
+    /// Where the layout before the statement being collected ends
+    line_start: usize,
+    /// Where the previous statement's text ended
+    previous_end: Option<usize>,
+}
+
+impl Formatter<'_> {
+    /// Write the collected statement, which ends at `end` (its newline).
+    fn flush(&mut self, end: usize) {
+        let comment = self.line.pop_if(|token| token.kind == TokenKind::Comment);
+        let first = self.line.first().copied();
+        // The statement's text starts after the layout before it, which can
+        // be ahead of its first token: a text block's opening quotes have no
+        // token of their own, and an empty text block has no tokens at all
+        let from = first.map_or(end, |first| first.span.start);
+        let layout = &self.source[self.line_start.min(from)..from];
+        let start = from - layout.trim_start().len();
+        if first.is_none() && (start == from || comment.is_some()) {
+            return;
+        }
+
+        if let Some(previous_end) = self.previous_end
+            && self.source[previous_end..start].matches('\n').count() > 1
+        {
+            self.out.push('\n');
+        }
+        self.previous_end = Some(end);
+
+        self.out.push_str(&" ".repeat(self.depth * INDENT_WIDTH));
+        match first {
+            Some(Token {
+                kind:
+                    TokenKind::Temp
+                    | TokenKind::Save
+                    | TokenKind::Const
+                    | TokenKind::Set
+                    | TokenKind::Extern
+                    | TokenKind::Goto
+                    | TokenKind::Include
+                    | TokenKind::Wait
+                    | TokenKind::End,
+                ..
+            }) => self.push_spaced(),
+            Some(Token {
+                kind: TokenKind::LabelMarker,
+                ..
+            }) => {
+                self.push_spaced();
+                self.out.push_str(" ==");
+            }
+            Some(
+                marker @ Token {
+                    kind: TokenKind::Choice,
+                    ..
+                },
+            ) => {
+                // Keep the marker's space even for an empty choice, or the line
+                // would stop being a choice. Only spaces are layout here; a tab
+                // belongs to the text, where it keeps a `#` from starting a tag.
+                self.out.push_str("- ");
+                self.push_text(self.source[marker.span.end..end].trim_start_matches(' '));
+            }
+            // Text is kept verbatim; the end of the line also covers a text
+            // block's closing quotes, which have no token of their own
+            _ => self.push_text(&self.source[start..end]),
+        }
+        if let Some(comment) = comment {
+            self.out.push(' ');
+            self.out.push_str(comment.lexeme);
+        }
+        // A `\` that ends the file is literal; a newline after it would make it
+        // a continuation
+        if !(end == self.source.len() && self.out.ends_with('\\') && comment.is_none()) {
+            self.out.push('\n');
+        }
+        self.line.clear();
+    }
+
+    /// Write line or choice text without its trailing whitespace, unless that
+    /// whitespace decides what a final `\` means: a line break after it is
+    /// kept, since the continuation ends on a line with nothing else on it,
+    /// and a tab after it is kept as one, since the tab keeps it literal.
+    fn push_text(&mut self, text: &str) {
+        let trimmed = text.trim_end();
+        self.out.push_str(trimmed);
+        if !trimmed.ends_with('\\') {
+            return;
+        }
+        let rest = &text[trimmed.len()..];
+        if rest.contains(['\n', '\r']) {
+            self.out.push('\n');
+        } else if rest.contains(|c| c != ' ') {
+            self.out.push('\t');
+        }
+    }
+
+    /// Write the collected tokens separated by single spaces, except just
+    /// inside parentheses, before a comma, between a function name and its
+    /// arguments, and after a unary minus.
+    fn push_spaced(&mut self) {
+        let mut previous: Option<TokenKind> = None;
+        let mut after_negation = false;
+        for token in &self.line {
+            let tight = after_negation
+                || matches!(
+                    (previous, token.kind),
+                    (None | Some(TokenKind::LeftParen), _)
+                        | (_, TokenKind::RightParen | TokenKind::Comma)
+                        | (Some(TokenKind::Identifier), TokenKind::LeftParen)
+                );
+            if !tight {
+                self.out.push(' ');
+            }
+            self.out.push_str(token.lexeme);
+            after_negation = token.kind == TokenKind::Minus && starts_operand(previous);
+            previous = Some(token.kind);
+        }
+    }
+}
+
+/// Whether a token after `previous` starts an operand, which makes a `-`
+/// there a negation rather than a subtraction.
+fn starts_operand(previous: Option<TokenKind>) -> bool {
+    matches!(
+        previous,
+        None | Some(
+            TokenKind::Equals
+                | TokenKind::PlusEquals
+                | TokenKind::MinusEquals
+                | TokenKind::StarEquals
+                | TokenKind::SlashEquals
+                | TokenKind::LeftParen
+                | TokenKind::Comma
+                | TokenKind::Plus
+                | TokenKind::Minus
+                | TokenKind::Star
+                | TokenKind::Slash
+                | TokenKind::EqualEqual
+                | TokenKind::BangEqual
+                | TokenKind::Less
+                | TokenKind::LessEqual
+                | TokenKind::Greater
+                | TokenKind::GreaterEqual
+                | TokenKind::Not
+                | TokenKind::And
+                | TokenKind::Or
+        )
+    )
+}
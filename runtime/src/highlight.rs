@@ -0,0 +1,122 @@
+//! Token classification for syntax highlighting.
+//!
+//! Editors and grammar bridges want to color a script without going through
+//! diagnostics, and they need an answer even for source that does not parse.
+//! `highlight` runs only the scanner and maps each token to a small, stable set
+//! of kinds.
+
+use crate::scanner::Scanner;
+use crate::token::{Span, TokenKind};
+
+/// What a highlighted region of source is.
+///
+/// New kinds are only ever added, so editors can map these to theme scopes
+/// once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightKind {
+    /// Statement keywords, logical operators, booleans, and built-in function
+    /// names
+    Keyword,
+    /// Variable and label names
+    Variable,
+    /// Dialogue text, string literals, and source the scanner could not make
+    /// sense of
+    StringText,
+    /// Operators, parentheses, interpolation braces, and choice and label
+    /// markers
+    Punctuation,
+    Comment,
+    Tag,
+    /// Number literals and number formats (the `0.2` in `{price:0.2}`)
+    Number,
+}
+
+/// A classified region of source, as byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HighlightSpan {
+    pub span: Span,
+    pub kind: HighlightKind,
+}
+
+/// Classify the tokens of `source`, in source order.
+///
+/// Never fails: a region with a lexical error is reported as
+/// [`HighlightKind::StringText`] and scanning carries on after it. Layout
+/// (indentation and newlines) and the quotes around a text block are not
+/// covered by any span.
+pub fn highlight(source: &str) -> Vec<HighlightSpan> {
+    let mut spans = Vec::new();
+    let mut tokens = Scanner::new(source).iter().peekable();
+    while let Some(token) = tokens.next() {
+        let (span, kind) = match token {
+            Ok(token) => {
+                let called = matches!(
+                    tokens.peek(),
+                    Some(Ok(next)) if next.kind == TokenKind::LeftParen
+                );
+                match classify(token.kind, called) {
+                    Some(kind) => (token.span, kind),
+                    None => continue,
+                }
+            }
+            Err(err) => (err.span(), HighlightKind::StringText),
+        };
+        if !span.is_empty() {
+            spans.push(HighlightSpan { span, kind });
+        }
+    }
+    spans
+}
+
+/// The highlight kind of a token, or `None` for layout. `called` is set when
+/// the token is directly followed by `(`.
+fn classify(kind: TokenKind, called: bool) -> Option<HighlightKind> {
+    let kind = match kind {
+        TokenKind::Temp
+        | TokenKind::Save
+        | TokenKind::Const
+        | TokenKind::Set
+        | TokenKind::Extern
+        | TokenKind::Goto
+        | TokenKind::Include
+        | TokenKind::Wait
+        | TokenKind::End
+        | TokenKind::And
+        | TokenKind::Or
+        | TokenKind::Not
+        | TokenKind::True
+        | TokenKind::False => HighlightKind::Keyword,
+        TokenKind::Identifier if called => HighlightKind::Keyword,
+        TokenKind::Identifier => HighlightKind::Variable,
+        TokenKind::String | TokenKind::TextSegment => HighlightKind::StringText,
+        TokenKind::Number | TokenKind::FormatSpec => HighlightKind::Number,
+        TokenKind::Equals
+        | TokenKind::Plus
+        | TokenKind::Minus
+        | TokenKind::Star
+        | TokenKind::Slash
+        | TokenKind::LeftParen
+        | TokenKind::RightParen
+        | TokenKind::Comma
+        | TokenKind::EqualEqual
+        | TokenKind::BangEqual
+        | TokenKind::Less
+        | TokenKind::LessEqual
+        | TokenKind::Greater
+        | TokenKind::GreaterEqual
+        | TokenKind::PlusEquals
+        | TokenKind::MinusEquals
+        | TokenKind::StarEquals
+        | TokenKind::SlashEquals
+        | TokenKind::OpenBrace
+        | TokenKind::CloseBrace
+        | TokenKind::Choice
+        | TokenKind::LabelMarker => HighlightKind::Punctuation,
+        TokenKind::Tag => HighlightKind::Tag,
+        TokenKind::Comment => HighlightKind::Comment,
+        TokenKind::Indent | TokenKind::Dedent | TokenKind::NewLine | TokenKind::Eof => {
+            return None;
+        }
+    };
+    Some(kind)
+}
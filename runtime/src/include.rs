@@ -0,0 +1,348 @@
+//! Assembling one script from several files.
+//!
+//! `include "path"` splices another file's statements into the including file
+//! at that point. The core crate never touches the file system: a
+//! [`SourceProvider`] supplies the text of each included file. Every file is
+//! laid out in one shared offset space, recorded in a [`SourceMap`], so spans
+//! in errors and bytecode stay unambiguous across files.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::BobbinError;
+use crate::ast::{Script, Stmt};
+use crate::chunk::Chunk;
+use crate::compiler::Compiler;
+use crate::diagnostic::{Diagnostic, DiagnosticContext, IntoDiagnostic};
+use crate::parser::{ParseError, Parser};
+use crate::resolver::Resolver;
+use crate::scanner::{LexicalError, Scanner};
+use crate::token::{Span, Token};
+
+/// Supplies the source of included files.
+pub trait SourceProvider {
+    /// Return the text of the file at `path`, or `None` if there is no such file.
+    ///
+    /// Paths are already resolved against the including file, with `.` and `..`
+    /// segments folded and `/` as the separator.
+    fn load(&self, path: &str) -> Option<String>;
+}
+
+impl SourceProvider for HashMap<String, String> {
+    fn load(&self, path: &str) -> Option<String> {
+        self.get(path).cloned()
+    }
+}
+
+/// A file read while compiling a script with its includes.
+#[derive(Debug, Clone)]
+pub struct SourceFile {
+    pub path: String,
+    pub source: String,
+    /// Offset of this file's first byte in the combined span space
+    pub offset: usize,
+}
+
+/// Every file that makes up a script, laid out end to end.
+///
+/// Spans produced by `compile_with_includes` are offsets into this combined
+/// space. Use [`SourceMap::localize`] to turn one back into a file and a span
+/// within that file, e.g. before rendering a diagnostic.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    /// All files, in the order they were first included. The entry file is first.
+    pub fn files(&self) -> &[SourceFile] {
+        &self.files
+    }
+
+    /// The file containing `offset`. An offset at the very end of a file
+    /// belongs to that file.
+    pub fn file_at(&self, offset: usize) -> Option<&SourceFile> {
+        self.files
+            .iter()
+            .find(|file| (file.offset..=file.offset + file.source.len()).contains(&offset))
+    }
+
+    /// Split a combined span into its file and a span relative to that file.
+    pub fn localize(&self, span: Span) -> Option<(&SourceFile, Span)> {
+        let file = self.file_at(span.start)?;
+        let local = Span {
+            start: span.start - file.offset,
+            end: span.end.saturating_sub(file.offset).min(file.source.len()),
+        };
+        Some((file, local))
+    }
+
+    /// Offset for the next file. Files are separated by one byte so that the
+    /// end of one file is never the start of the next.
+    fn next_offset(&self) -> usize {
+        self.files
+            .last()
+            .map_or(0, |file| file.offset + file.source.len() + 1)
+    }
+}
+
+/// An include that could not be expanded.
+#[derive(Debug, Clone)]
+pub enum IncludeError {
+    /// The source provider has no file at the resolved path.
+    NotFound { path: String, span: Span },
+    /// A file includes itself, directly or through other files.
+    Cycle {
+        /// Every file on the cycle, starting and ending with the same path
+        chain: Vec<String>,
+        span: Span,
+    },
+}
+
+impl IntoDiagnostic for IncludeError {
+    fn into_diagnostic(self, _ctx: &DiagnosticContext) -> Diagnostic {
+        match self {
+            IncludeError::NotFound { path, span } => Diagnostic::error(
+                format!("included file '{}' not found", path),
+                span,
+                "no file at this path",
+            )
+            .with_note("Include paths are relative to the including file"),
+            IncludeError::Cycle { chain, span } => Diagnostic::error(
+                format!("include cycle: {}", chain.join(" -> ")),
+                span,
+                "this file is already being included",
+            )
+            .with_note("Move the shared statements into a file that neither one includes"),
+        }
+    }
+}
+
+/// Everything produced by compiling a script together with its includes.
+#[derive(Debug)]
+pub struct Compilation {
+    /// The bytecode, or every error found across all files.
+    pub chunk: Result<Chunk, BobbinError>,
+    /// The files that were read, for mapping spans back to their file.
+    pub sources: SourceMap,
+}
+
+/// Compile the script at `path`, loading the files it includes from `provider`.
+///
+/// Each `include` is replaced by the included file's statements, so its
+/// declarations and labels are visible to everything after it. A file
+/// included more than once is only expanded the first time. Missing files
+/// and include cycles are reported as parse errors at the quoted path.
+pub fn compile_with_includes(
+    path: &str,
+    source: &str,
+    provider: &dyn SourceProvider,
+) -> Compilation {
+    let (script, sources) = expand_includes(path, source, provider);
+    let chunk = script.map_err(BobbinError::Parse).and_then(|ast| {
+        let symbols = Resolver::new(&ast).analyze()?;
+        Ok(Compiler::new(&ast, &symbols).compile()?)
+    });
+    Compilation { chunk, sources }
+}
+
+/// Parse the script at `path` with its includes spliced in, along with the
+/// files that were read. Fails with every parse and include error if any
+/// file has one.
+pub(crate) fn expand_includes(
+    path: &str,
+    source: &str,
+    provider: &dyn SourceProvider,
+) -> (Result<Script, Vec<ParseError>>, SourceMap) {
+    let mut loader = Loader {
+        provider,
+        sources: SourceMap::default(),
+        next_id: 0,
+        errors: Vec::new(),
+        open: Vec::new(),
+        loaded: HashSet::new(),
+    };
+    let statements = loader.load(path, source.to_string());
+    let script = if loader.errors.is_empty() {
+        Ok(Script { statements })
+    } else {
+        Err(loader.errors)
+    };
+    (script, loader.sources)
+}
+
+/// Parses files and splices their includes, depth first.
+struct Loader<'p> {
+    provider: &'p dyn SourceProvider,
+    sources: SourceMap,
+    /// NodeIds continue across files so they stay unique in the merged script
+    next_id: usize,
+    errors: Vec<ParseError>,
+    /// Files whose includes are being expanded, outermost first
+    open: Vec<String>,
+    loaded: HashSet<String>,
+}
+
+impl Loader<'_> {
+    /// Parse one file and return its statements with includes expanded.
+    fn load(&mut self, path: &str, source: String) -> Vec<Stmt> {
+        let offset = self.sources.next_offset();
+        let tokens = Scanner::new(&source)
+            .tokens()
+            .map(|token| shift_token(token, offset));
+        let (parsed, next_id) = Parser::new(tokens)
+            .with_first_id(self.next_id)
+            .parse_counted();
+        self.next_id = next_id;
+        self.sources.files.push(SourceFile {
+            path: path.to_string(),
+            source,
+            offset,
+        });
+        self.loaded.insert(path.to_string());
+
+        let statements = match parsed {
+            Ok(script) => script.statements,
+            Err(errors) => {
+                self.errors.extend(errors);
+                return Vec::new();
+            }
+        };
+
+        self.open.push(path.to_string());
+        let mut expanded = Vec::with_capacity(statements.len());
+        for stmt in statements {
+            let Stmt::Include {
+                path: target,
+                path_span,
+                ..
+            } = &stmt
+            else {
+                expanded.push(stmt);
+                continue;
+            };
+
+            let target = resolve_path(path, target);
+            if let Some(start) = self.open.iter().position(|open| *open == target) {
+                let mut chain = self.open[start..].to_vec();
+                chain.push(target);
+                self.errors.push(
+                    IncludeError::Cycle {
+                        chain,
+                        span: *path_span,
+                    }
+                    .into(),
+                );
+            } else if self.loaded.contains(&target) {
+                // Already spliced in elsewhere; declaring everything twice would clash
+            } else if let Some(source) = self.provider.load(&target) {
+                expanded.extend(self.load(&target, source));
+            } else {
+                self.errors.push(
+                    IncludeError::NotFound {
+                        path: target,
+                        span: *path_span,
+                    }
+                    .into(),
+                );
+            }
+        }
+        self.open.pop();
+        expanded
+    }
+}
+
+/// Move a token (or lexical error) into the combined span space.
+fn shift_token<'a>(
+    token: Result<Token<'a>, LexicalError>,
+    offset: usize,
+) -> Result<Token<'a>, LexicalError> {
+    let shift = |span: Span| Span {
+        start: span.start + offset,
+        end: span.end + offset,
+    };
+    match token {
+        Ok(token) => Ok(Token {
+            span: shift(token.span),
+            ..token
+        }),
+        Err(LexicalError::Unexpected { message, span }) => Err(LexicalError::Unexpected {
+            message,
+            span: shift(span),
+        }),
+        Err(LexicalError::ControlCharacter { character, span }) => {
+            Err(LexicalError::ControlCharacter {
+                character,
+                span: shift(span),
+            })
+        }
+    }
+}
+
+/// Resolve an include path against the path of the file that contains it.
+///
+/// Resolution is purely lexical: `.` segments are dropped and `..` removes the
+/// preceding segment. A path starting with `/` is taken as is.
+fn resolve_path(from: &str, path: &str) -> String {
+    let mut segments: Vec<&str> = if path.starts_with('/') {
+        vec![""]
+    } else {
+        let mut dir: Vec<&str> = from.split('/').collect();
+        dir.pop(); // The including file's own name
+        dir
+    };
+
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => match segments.last() {
+                // Never climb above the root
+                Some(&"") => {}
+                Some(last) if *last != ".." => {
+                    segments.pop();
+                }
+                _ => segments.push(".."),
+            },
+            _ => segments.push(segment),
+        }
+    }
+
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_path;
+
+    #[test]
+    fn resolves_relative_to_including_file() {
+        assert_eq!(
+            resolve_path("main.bobbin", "common.bobbin"),
+            "common.bobbin"
+        );
+        assert_eq!(
+            resolve_path("act1/intro.bobbin", "shared.bobbin"),
+            "act1/shared.bobbin"
+        );
+        assert_eq!(
+            resolve_path("act1/intro.bobbin", "../common/vars.bobbin"),
+            "common/vars.bobbin"
+        );
+        assert_eq!(
+            resolve_path("act1/intro.bobbin", "./a/../b.bobbin"),
+            "act1/b.bobbin"
+        );
+    }
+
+    #[test]
+    fn keeps_leading_parent_segments() {
+        assert_eq!(
+            resolve_path("main.bobbin", "../lib.bobbin"),
+            "../lib.bobbin"
+        );
+        assert_eq!(
+            resolve_path("/game/main.bobbin", "/lib.bobbin"),
+            "/lib.bobbin"
+        );
+        assert_eq!(resolve_path("/main.bobbin", "../lib.bobbin"), "/lib.bobbin");
+    }
+}
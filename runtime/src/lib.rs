@@ -1,29 +1,61 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::sync::Arc;
 
 use crate::compiler::{CompileError, Compiler};
-use crate::diagnostic::{DiagnosticContext, IntoDiagnostic, JaroWinklerMatcher};
+use crate::diagnostic::{
+    DiagnosticContext, IntoDiagnostic, JaroWinklerMatcher, LineIndex, Matcher, SourcePosition,
+};
 use crate::parser::{ParseError, Parser};
 use crate::resolver::{Resolver, SemanticError};
 use crate::scanner::Scanner;
-use crate::vm::{StepResult, VM};
+use crate::token::Span;
+use crate::vm::{StepResult, VM, guard_host_call};
 
-pub use crate::chunk::Value;
+pub use crate::ast::NodeId;
+pub use crate::chunk::{
+    BYTECODE_VERSION, BytecodeVersionError, Chunk, Instruction, LogicOp, NumberFormat, TextKey,
+    Value, VarSource,
+};
+pub use crate::dry_run::ChoiceStrategy;
+pub use crate::flow::{FlowEdge, FlowGraph, FlowNode, FlowNodeKind, analyze_flow};
+pub use crate::format::format_source;
+pub use crate::highlight::{HighlightKind, HighlightSpan, highlight};
+pub use crate::include::{
+    Compilation, SourceFile, SourceMap, SourceProvider, compile_with_includes,
+};
+pub use crate::loc::{LocEntry, LocKind, extract_strings};
+pub use crate::resolver::{DeclKind, SymbolTable};
+#[cfg(feature = "async")]
+pub use crate::storage::AsyncHostState;
 pub use crate::storage::{HostState, VariableStorage};
-pub use crate::vm::RuntimeError;
+pub use crate::validate::{
+    Metrics, PositionedDiagnostic, ValidateOptions, ValidationResult, diagnostics_as_positions,
+    render_diagnostics, validate, validate_full, validate_many, validate_streaming,
+    validate_with_cancel, validate_with_matcher, validate_with_metrics, validate_with_options,
+};
+pub use crate::vm::{Coverage, NextKind, RuntimeError};
 
-mod ast;
+pub mod ast;
 mod chunk;
 mod compiler;
 pub mod diagnostic;
+mod dry_run;
+mod flow;
+mod format;
+mod highlight;
+mod include;
+mod loc;
 mod parser;
+mod random;
 mod resolver;
-mod scanner;
+pub mod scanner;
 mod storage;
 pub mod token;
+mod validate;
 mod vm;
 
-pub use diagnostic::{AriadneRenderer, Diagnostic, Renderer};
+pub use diagnostic::{AriadneRenderer, Diagnostic, MarkdownRenderer, PlainRenderer, Renderer};
 
 #[derive(Debug, Clone)]
 pub enum BobbinError {
@@ -33,6 +65,8 @@ pub enum BobbinError {
         errors: Vec<SemanticError>,
         known_variables: Vec<String>,
     },
+    /// Warnings in a script built with `RuntimeBuilder::deny_warnings`.
+    DeniedWarnings(Vec<SemanticError>),
     Compile(CompileError),
     Runtime(RuntimeError),
 }
@@ -71,7 +105,11 @@ impl fmt::Display for BobbinError {
                 write!(f, "{} parse error(s)", errors.len())
             }
             BobbinError::Semantic { errors, .. } => {
-                write!(f, "{} semantic error(s)", errors.len())
+                let count = errors.iter().filter(|e| !e.is_warning()).count();
+                write!(f, "{} semantic error(s)", count)
+            }
+            BobbinError::DeniedWarnings(warnings) => {
+                write!(f, "{} denied warning(s)", warnings.len())
             }
             BobbinError::Compile(err) => {
                 write!(f, "compile error: {:?}", err)
@@ -106,6 +144,14 @@ impl BobbinError {
                     .map(|e| e.into_diagnostic(&ctx))
                     .collect()
             }
+            BobbinError::DeniedWarnings(warnings) => {
+                let matcher = JaroWinklerMatcher::default();
+                let ctx = DiagnosticContext::new(&[], &matcher);
+                warnings
+                    .into_iter()
+                    .map(|w| w.into_diagnostic(&ctx).denied())
+                    .collect()
+            }
             BobbinError::Compile(_err) => {
                 // CompileError is currently empty - handle when populated
                 vec![]
@@ -123,10 +169,15 @@ impl BobbinError {
     /// This is more efficient than `into_diagnostics()` when you need to retain the error,
     /// as it only clones individual errors rather than the entire `BobbinError`.
     pub fn to_diagnostics(&self) -> Vec<Diagnostic> {
+        self.to_diagnostics_with_matcher(&JaroWinklerMatcher::default())
+    }
+
+    /// Convert this error into diagnostics, using `matcher` for "did you mean?"
+    /// suggestions instead of the default Jaro-Winkler matcher.
+    pub fn to_diagnostics_with_matcher(&self, matcher: &dyn Matcher) -> Vec<Diagnostic> {
         match self {
             BobbinError::Parse(errors) => {
-                let matcher = JaroWinklerMatcher::default();
-                let ctx = DiagnosticContext::new(&[], &matcher);
+                let ctx = DiagnosticContext::new(&[], matcher);
                 errors
                     .iter()
                     .map(|e| e.clone().into_diagnostic(&ctx))
@@ -136,20 +187,25 @@ impl BobbinError {
                 errors,
                 known_variables,
             } => {
-                let matcher = JaroWinklerMatcher::default();
-                let ctx = DiagnosticContext::new(known_variables, &matcher);
+                let ctx = DiagnosticContext::new(known_variables, matcher);
                 errors
                     .iter()
                     .map(|e| e.clone().into_diagnostic(&ctx))
                     .collect()
             }
+            BobbinError::DeniedWarnings(warnings) => {
+                let ctx = DiagnosticContext::new(&[], matcher);
+                warnings
+                    .iter()
+                    .map(|w| w.clone().into_diagnostic(&ctx).denied())
+                    .collect()
+            }
             BobbinError::Compile(_err) => {
                 // CompileError is currently empty - handle when populated
                 vec![]
             }
             BobbinError::Runtime(err) => {
-                let matcher = JaroWinklerMatcher::default();
-                let ctx = DiagnosticContext::new(&[], &matcher);
+                let ctx = DiagnosticContext::new(&[], matcher);
                 vec![err.clone().into_diagnostic(&ctx)]
             }
         }
@@ -164,14 +220,222 @@ impl BobbinError {
         // AriadneRenderer normalizes line endings internally
         renderer.render_all(&diagnostics, source_id, source)
     }
+
+    /// Convert this error into diagnostics paired with the start and end
+    /// positions of each one's primary span in `source`.
+    ///
+    /// Columns are counted in bytes; build a [`LineIndex`]
+    /// directly for UTF-16 or grapheme columns. A diagnostic with no primary
+    /// span is placed at the start of the source.
+    pub fn to_positioned_diagnostics(
+        &self,
+        source: &str,
+    ) -> Vec<(Diagnostic, SourcePosition, SourcePosition)> {
+        let index = LineIndex::new(source);
+        self.to_diagnostics()
+            .into_iter()
+            .map(|diagnostic| {
+                let (start, end) = diagnostic
+                    .primary_span()
+                    .map_or((0, 0), |span| (span.start, span.end));
+                let (start, end) = (index.line_col(start), index.line_col(end));
+                (diagnostic, start, end)
+            })
+            .collect()
+    }
+}
+
+/// Compile a script to bytecode without creating a runtime.
+///
+/// Runs the full front end (scan, parse, resolve, compile) and nothing else:
+/// no storage or host is needed, and no part of the script runs, so tools
+/// such as `analyze_flow` and `Chunk::disassemble` can work on any script.
+/// The resulting chunk can be executed with `Runtime::from_chunk`, or
+/// serialized ahead of time with the `serde` feature so games can skip
+/// compilation at load time.
+///
+/// `include` directives are rejected; use `compile_with_includes` for scripts
+/// split across files.
+pub fn compile_to_chunk(script: &str) -> Result<Chunk, BobbinError> {
+    compile(script, false, false)
 }
 
+/// Compile a script, failing with `DeniedWarnings` on any warning if
+/// `deny_warnings` is set.
+fn compile(script: &str, deny_warnings: bool, beats: bool) -> Result<Chunk, BobbinError> {
+    let tokens = Scanner::new(script).tokens();
+    let ast = Parser::new(tokens).parse()?;
+    let symbols = Resolver::new(&ast).analyze()?;
+    if deny_warnings && !symbols.warnings.is_empty() {
+        return Err(BobbinError::DeniedWarnings(symbols.warnings));
+    }
+    let mut compiler = Compiler::new(&ast, &symbols);
+    if beats {
+        compiler = compiler.with_beats(script);
+    }
+    Ok(compiler.compile()?)
+}
+
+/// Options for creating a [`Runtime`].
+///
+/// `Runtime::new` and the other constructors are shorthands for a builder
+/// with default options.
+///
+/// # Example
+///
+/// ```ignore
+/// let runtime = RuntimeBuilder::new(storage, host)
+///     .seed(42)
+///     .trim_lines(false)
+///     .build(script)?;
+/// ```
+pub struct RuntimeBuilder {
+    storage: Arc<dyn VariableStorage>,
+    host: Arc<dyn HostState>,
+    seed: Option<u64>,
+    step_budget: Option<usize>,
+    trim_lines: bool,
+    deny_warnings: bool,
+    blank_lines_as_beats: bool,
+    catch_host_panics: bool,
+    string_table: Option<HashMap<String, String>>,
+}
+
+impl RuntimeBuilder {
+    pub fn new(storage: Arc<dyn VariableStorage>, host: Arc<dyn HostState>) -> Self {
+        Self {
+            storage,
+            host,
+            seed: None,
+            step_budget: None,
+            trim_lines: true,
+            deny_warnings: false,
+            blank_lines_as_beats: false,
+            catch_host_panics: false,
+            string_table: None,
+        }
+    }
+
+    /// Seed the generator behind `random(min, max)`, as `Runtime::with_seed`
+    /// does.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Fail with `StepBudgetExceeded` when a single `advance` or
+    /// `select_choice` runs more than `instructions` instructions, instead of
+    /// hanging on a runaway script. Unlimited by default.
+    pub fn step_budget(mut self, instructions: usize) -> Self {
+        self.step_budget = Some(instructions);
+        self
+    }
+
+    /// Whether to trim lines and choices, as `Runtime::with_trim_lines`.
+    pub fn trim_lines(mut self, trim: bool) -> Self {
+        self.trim_lines = trim;
+        self
+    }
+
+    /// Refuse to build a script that has warnings, failing with
+    /// `DeniedWarnings` instead. Off by default.
+    pub fn deny_warnings(mut self, deny: bool) -> Self {
+        self.deny_warnings = deny;
+        self
+    }
+
+    /// Turn a blank line after a line of dialogue into a beat: an empty
+    /// line the host can show as a pause. Blank lines before the first
+    /// line, after the last, or after anything other than a line are
+    /// skipped as usual. Off by default.
+    pub fn blank_lines_as_beats(mut self, beats: bool) -> Self {
+        self.blank_lines_as_beats = beats;
+        self
+    }
+
+    /// Fail the step with `HostCallbackPanicked` when a host lookup or the
+    /// line filter panics during `advance` or `select_choice`, instead of
+    /// letting the panic unwind through the runtime. Off by default.
+    ///
+    /// The panic hook still runs, so the message is printed as usual. A
+    /// caught panic can leave host state half-updated, and it does not help
+    /// in builds with `panic = "abort"`.
+    pub fn catch_host_panics(mut self, catch: bool) -> Self {
+        self.catch_host_panics = catch;
+        self
+    }
+
+    /// Play in another language, as `Runtime::with_string_table`. A bad
+    /// entry fails `build`.
+    pub fn string_table(mut self, table: HashMap<String, String>) -> Self {
+        self.string_table = Some(table);
+        self
+    }
+
+    /// Compile `script` and start the runtime at its first line.
+    pub fn build(self, script: &str) -> Result<Runtime, BobbinError> {
+        let chunk = compile(script, self.deny_warnings, self.blank_lines_as_beats)?;
+        self.build_from_chunk(chunk)
+    }
+
+    /// Start the runtime on precompiled bytecode, as `Runtime::from_chunk`.
+    pub fn build_from_chunk(self, chunk: Chunk) -> Result<Runtime, BobbinError> {
+        let mut vm = VM::new(chunk, Arc::clone(&self.storage), Arc::clone(&self.host));
+        if let Some(seed) = self.seed {
+            vm.set_seed(seed);
+        }
+        if let Some(budget) = self.step_budget {
+            vm.set_step_budget(budget);
+        }
+        vm.set_catch_host_panics(self.catch_host_panics);
+        if let Some(table) = self.string_table {
+            vm.set_string_table(table)?;
+        }
+        let mut runtime = Runtime {
+            vm,
+            storage: self.storage,
+            host: self.host,
+            seed: self.seed,
+            trim_lines: self.trim_lines,
+            catch_host_panics: self.catch_host_panics,
+            line_filter: None,
+            current_line: None,
+            current_choices: None,
+            filtered_line: None,
+            filtered_choices: None,
+            trimmed_choices: None,
+            current_wait: None,
+            is_done: false,
+        };
+        runtime.step_vm()?;
+        Ok(runtime)
+    }
+}
+
+/// A host hook that rewrites each line and choice before it is shown.
+type LineFilter = Box<dyn Fn(&str) -> String + Send + Sync>;
+
 pub struct Runtime {
     vm: VM,
     storage: Arc<dyn VariableStorage>,
     host: Arc<dyn HostState>,
+    /// Seed for `random(min, max)`, if one was given
+    seed: Option<u64>,
+    /// Trim spaces and tabs from the ends of lines and choices
+    trim_lines: bool,
+    /// Turn panics in the line filter into `HostCallbackPanicked`
+    catch_host_panics: bool,
+    line_filter: Option<LineFilter>,
     current_line: Option<String>,
     current_choices: Option<Vec<String>>,
+    /// `current_line` after the line filter, if one is set
+    filtered_line: Option<String>,
+    /// `current_choices` after the line filter, if one is set
+    filtered_choices: Option<Vec<String>>,
+    /// The choices on show trimmed, if trimming changes any of them
+    trimmed_choices: Option<Vec<String>>,
+    /// Seconds to hold before the next advance, while paused on a `wait`
+    current_wait: Option<f64>,
     is_done: bool,
 }
 
@@ -203,21 +467,92 @@ impl Runtime {
         storage: Arc<dyn VariableStorage>,
         host: Arc<dyn HostState>,
     ) -> Result<Self, BobbinError> {
-        let tokens = Scanner::new(script).tokens();
-        let ast = Parser::new(tokens).parse()?;
-        let symbols = Resolver::new(&ast).analyze()?;
-        let chunk = Compiler::new(&ast, &symbols).compile()?;
+        RuntimeBuilder::new(storage, host).build(script)
+    }
 
-        let mut runtime = Self {
-            vm: VM::new(chunk, Arc::clone(&storage), Arc::clone(&host)),
-            storage,
-            host,
-            current_line: None,
-            current_choices: None,
-            is_done: false,
-        };
-        runtime.step_vm()?;
-        Ok(runtime)
+    /// Create a runtime from precompiled bytecode.
+    ///
+    /// Skips scanning, parsing, and resolution entirely. Use `compile_to_chunk`
+    /// to produce the chunk, typically at build time.
+    pub fn from_chunk(
+        chunk: Chunk,
+        storage: Arc<dyn VariableStorage>,
+        host: Arc<dyn HostState>,
+    ) -> Result<Self, BobbinError> {
+        RuntimeBuilder::new(storage, host).build_from_chunk(chunk)
+    }
+
+    /// Create a runtime whose `random(min, max)` values come from a generator
+    /// seeded with `seed`.
+    ///
+    /// Two runtimes with the same seed produce the same values along the same
+    /// path, which keeps dice rolls reproducible in tests. Runtimes created
+    /// with `new` draw an unpredictable seed.
+    pub fn with_seed(
+        script: &str,
+        storage: Arc<dyn VariableStorage>,
+        host: Arc<dyn HostState>,
+        seed: u64,
+    ) -> Result<Self, BobbinError> {
+        RuntimeBuilder::new(storage, host).seed(seed).build(script)
+    }
+
+    /// Play the script in another language, using translations keyed by the
+    /// ids `extract_strings` reports.
+    ///
+    /// Each translation is a template in the source text's syntax: `{name}` and
+    /// `{name:format}` interpolate the same variables the original text uses,
+    /// `{0}`, `{1}`, and so on the values of its other expressions in order,
+    /// and `{{` and `}}` are literal braces. Lines and choices without an entry
+    /// keep their original text; entries for unknown ids are ignored. The text
+    /// currently shown is re-rendered.
+    ///
+    /// Returns `BadTranslation` if an entry is malformed or interpolates a
+    /// variable its original text does not.
+    pub fn with_string_table(
+        mut self,
+        table: HashMap<String, String>,
+    ) -> Result<Self, RuntimeError> {
+        self.vm.set_string_table(table)?;
+        if self.current_line.is_some()
+            && let Some(translated) = self.vm.translate_current_line()?
+        {
+            self.current_line = Some(translated);
+        }
+        if let Some(choices) = &mut self.current_choices {
+            let translated = self.vm.translate_current_choices()?;
+            for (choice, translation) in choices.iter_mut().zip(translated) {
+                if let Some(translation) = translation {
+                    *choice = translation;
+                }
+            }
+        }
+        self.apply_line_filter(self.catch_host_panics)?;
+        Ok(self)
+    }
+
+    /// Whether to trim spaces and tabs from both ends of each line and choice.
+    /// On by default.
+    ///
+    /// Spacing inside the text is kept, including any at the edges of an
+    /// interpolated value that ends up in the middle. Diagnostics still point
+    /// at the untrimmed source. Applies to the text currently shown too.
+    pub fn with_trim_lines(mut self, trim: bool) -> Self {
+        self.trim_lines = trim;
+        self
+    }
+
+    /// Rewrite the text of each line and choice before it is shown, for
+    /// markup, profanity filters, and the like. Replaces any earlier filter.
+    ///
+    /// The filter sees the final text, after interpolation and translation,
+    /// and its result is trimmed like any other line. Diagnostics still point
+    /// at the source. Applies to the text currently shown too; a panic while
+    /// doing so is never caught, since it unwinds straight back to the caller.
+    pub fn set_line_filter(&mut self, filter: Box<dyn Fn(&str) -> String + Send + Sync>) {
+        self.line_filter = Some(filter);
+        // Not catching, the filter cannot fail
+        let _ = self.apply_line_filter(false);
     }
 
     /// Get a reference to the storage for external access.
@@ -230,15 +565,166 @@ impl Runtime {
         &self.host
     }
 
+    /// Disassemble the compiled script for debugging.
+    ///
+    /// Returns one instruction per line with its index and operands. The format
+    /// is meant for humans and may change between versions.
+    pub fn disassemble(&self) -> String {
+        self.vm.chunk().disassemble()
+    }
+
+    /// The graph of lines and choice sets this script can reach, for
+    /// visualization. See [`analyze_flow`].
+    pub fn flow_graph(&self) -> FlowGraph {
+        analyze_flow(self.vm.chunk())
+    }
+
+    /// Extern variables the script reads that the host does not currently provide.
+    ///
+    /// Call this after construction to fail fast instead of hitting
+    /// `MissingExternVariable` partway through the dialogue. Externs that are
+    /// declared but never read are not checked, and neither are externs the
+    /// host has a default for.
+    pub fn check_externs(&self) -> Vec<String> {
+        self.vm
+            .chunk()
+            .extern_names()
+            .into_iter()
+            .filter(|name| {
+                self.host.lookup(name).is_none() && self.host.default_for(name).is_none()
+            })
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Run the script from the start along every path `strategy` allows and
+    /// return the runtime errors found, each reported once.
+    ///
+    /// Reads go to this runtime's storage and host, but writes stay in a
+    /// scratch layer, so the dialogue in progress and the saved state are
+    /// untouched. Each line and choice branch is explored once, which keeps
+    /// `goto` loops finite. A seeded runtime's dry run rolls the same
+    /// `random(min, max)` values as a playthrough along the same path.
+    pub fn dry_run(&self, strategy: ChoiceStrategy) -> Vec<RuntimeError> {
+        dry_run::dry_run(
+            self.vm.chunk(),
+            &self.storage,
+            &self.host,
+            strategy,
+            self.seed,
+        )
+    }
+
     pub fn current_line(&self) -> &str {
-        self.current_line.as_deref().unwrap_or("")
+        let line = self
+            .filtered_line
+            .as_deref()
+            .or(self.current_line.as_deref())
+            .unwrap_or("");
+        if self.trim_lines { trim(line) } else { line }
+    }
+
+    /// Whether a line is on show. True for an empty line, such as a beat;
+    /// false during choices and waits, and once the dialogue has ended
+    /// without a final line.
+    pub fn has_line(&self) -> bool {
+        self.current_line.is_some()
     }
 
     pub fn current_choices(&self) -> &[String] {
-        self.current_choices.as_deref().unwrap_or(&[])
+        if self.trim_lines
+            && let Some(trimmed) = &self.trimmed_choices
+        {
+            return trimmed;
+        }
+        self.filtered_choices
+            .as_deref()
+            .or(self.current_choices.as_deref())
+            .unwrap_or(&[])
+    }
+
+    /// Id of the line currently being shown, for use with `visit_count`.
+    pub fn current_line_node(&self) -> Option<NodeId> {
+        self.current_line.as_ref()?;
+        self.vm.current_line_node()
+    }
+
+    /// Ids of the choices currently offered, in the same order as `current_choices`.
+    pub fn current_choice_nodes(&self) -> &[NodeId] {
+        if self.current_choices.is_none() {
+            return &[];
+        }
+        self.vm.current_choice_nodes()
+    }
+
+    /// Tags of the currently offered choice at `index`, without the `#`.
+    ///
+    /// Empty when the choice has no tags, the index is out of range, or no
+    /// choices are being offered.
+    pub fn choice_tags(&self, index: usize) -> &[String] {
+        if self.current_choices.is_none() {
+            return &[];
+        }
+        self.vm.choice_tags(index)
+    }
+
+    /// How many times a line has been shown or a choice selected this playthrough.
+    ///
+    /// Lines count when they are reached; choices count when they are selected,
+    /// not when they are offered. Counts persist for the lifetime of the runtime.
+    pub fn visit_count(&self, node: NodeId) -> u32 {
+        self.vm.visit_count(node)
+    }
+
+    /// Whether a line has been shown or a choice selected at least once.
+    pub fn has_visited(&self, node: NodeId) -> bool {
+        self.visit_count(node) > 0
+    }
+
+    /// How many of the script's lines and choices this playthrough has
+    /// reached, for QA runs that look for dialogue no test visits.
+    ///
+    /// Kept across `advance` and `select_choice`; `reset` starts it over.
+    pub fn coverage(&self) -> Coverage {
+        let total_nodes = self.vm.chunk().node_spans.len();
+        Coverage {
+            visited_nodes: total_nodes - self.vm.unvisited_nodes().count(),
+            total_nodes,
+        }
+    }
+
+    /// Source spans of the lines and choices not reached yet, in source
+    /// order. A choice's span covers just its own line, not its branch.
+    pub fn unvisited_spans(&self) -> Vec<Span> {
+        let spans = &self.vm.chunk().node_spans;
+        let mut unvisited: Vec<Span> = self.vm.unvisited_nodes().map(|node| spans[&node]).collect();
+        unvisited.sort_by_key(|span| span.start);
+        unvisited
+    }
+
+    /// Start the dialogue over from its first line, clearing visit counts
+    /// and coverage.
+    ///
+    /// Save variables already in storage keep their values, since `save`
+    /// declarations only fill in missing ones. Temps start fresh.
+    pub fn reset(&mut self) -> Result<(), RuntimeError> {
+        self.vm.reset();
+        self.current_line = None;
+        self.current_choices = None;
+        self.is_done = false;
+        self.step_vm()
     }
 
-    /// Advance to the next line of dialogue.
+    /// How the script declares the variable `name`, so a debugger knows
+    /// whether to read it from storage or the host.
+    ///
+    /// `None` for names the script does not declare. A temp keeps its kind
+    /// even while the dialogue is outside its scope.
+    pub fn var_kind(&self, name: &str) -> Option<DeclKind> {
+        self.vm.chunk().var_kinds.get(name).copied()
+    }
+
+    /// Advance to the next line of dialogue, or to the next `wait`.
     ///
     /// Returns an error if a runtime error occurs (e.g., missing save variable).
     pub fn advance(&mut self) -> Result<(), RuntimeError> {
@@ -256,22 +742,137 @@ impl Runtime {
         self.current_choices.is_some()
     }
 
+    /// What the dialogue stops on after the current line or wait, so a UI can
+    /// show "continue" or "end" before the player advances.
+    ///
+    /// Reads ahead without running anything, so storage is not touched. While
+    /// choices are on offer this is `Choice`, since the next step is picking
+    /// one.
+    pub fn peek_next(&self) -> NextKind {
+        if self.current_choices.is_some() {
+            NextKind::Choice
+        } else if self.is_done {
+            NextKind::Done
+        } else {
+            self.vm.peek_next()
+        }
+    }
+
+    /// Seconds to pause for, when the dialogue is stopped on a `wait`.
+    ///
+    /// There is no current line during a wait. The host should hold for the
+    /// duration and then call `advance` to continue.
+    pub fn current_wait(&self) -> Option<f64> {
+        self.current_wait
+    }
+
+    /// Select a choice and continue to the next line.
+    ///
+    /// An out-of-range index returns `InvalidChoiceIndex`, carrying the texts of
+    /// the choices on offer, and leaves the runtime waiting on the same choices,
+    /// so the host can retry with a valid index.
     pub fn select_choice(&mut self, index: usize) -> Result<(), RuntimeError> {
         if self.current_choices.is_some() {
+            let result = self
+                .vm
+                .select_and_continue(index)
+                .map_err(|err| self.with_choice_texts(err))?;
             self.current_choices = None;
-            let result = self.vm.select_and_continue(index)?;
-            self.handle_step_result(result);
+            self.trimmed_choices = None;
+            self.handle_step_result(result)?;
         }
         Ok(())
     }
 
+    /// Advance as `advance` does, awaiting `host` for the extern variables
+    /// the step reads instead of looking them up in this runtime's host.
+    ///
+    /// A variable `host` does not provide falls back to this runtime's
+    /// `HostState::default_for`. Each lookup is made fresh for the step, so
+    /// remote values may change between lines. The step budget counts the
+    /// instructions run between lookups. Dropping the future before it
+    /// finishes leaves the dialogue partway through the step.
+    #[cfg(feature = "async")]
+    pub async fn advance_async<H: AsyncHostState>(&mut self, host: &H) -> Result<(), RuntimeError> {
+        if self.is_done {
+            return Ok(());
+        }
+        let result = {
+            let mut step = self.vm.async_step();
+            let started = step.step();
+            step.finish(started, host).await?
+        };
+        self.handle_step_result(result)
+    }
+
+    /// Select a choice as `select_choice` does, awaiting `host` for the
+    /// extern variables read on the way to the next line, as
+    /// `advance_async` does.
+    #[cfg(feature = "async")]
+    pub async fn select_choice_async<H: AsyncHostState>(
+        &mut self,
+        index: usize,
+        host: &H,
+    ) -> Result<(), RuntimeError> {
+        if self.current_choices.is_none() {
+            return Ok(());
+        }
+        let result = {
+            let mut step = self.vm.async_step();
+            let started = step.select_and_continue(index);
+            step.finish(started, host).await
+        };
+        let result = result.map_err(|err| self.with_choice_texts(err))?;
+        self.current_choices = None;
+        self.trimmed_choices = None;
+        self.handle_step_result(result)
+    }
+
+    /// `err` with the texts of the choices on offer filled in, if it is an
+    /// `InvalidChoiceIndex`. The VM only knows how many choices there are.
+    fn with_choice_texts(&self, err: RuntimeError) -> RuntimeError {
+        match err {
+            RuntimeError::InvalidChoiceIndex { index, count, .. } => {
+                RuntimeError::InvalidChoiceIndex {
+                    index,
+                    count,
+                    choices: self.current_choices().to_vec(),
+                }
+            }
+            err => err,
+        }
+    }
+
+    /// Index of the `#default` choice among the choices on offer, if there is
+    /// one. Hosts can use it to start a timer or highlight the fallback.
+    pub fn default_choice(&self) -> Option<usize> {
+        self.current_choices.as_ref()?;
+        self.vm.default_choice()
+    }
+
+    /// Select the `#default` choice, as when the player lets a timed choice run
+    /// out, and continue to the next line.
+    ///
+    /// Returns `NoDefaultChoice` if the choices on offer have no default, and
+    /// leaves the runtime waiting on them.
+    pub fn select_default(&mut self) -> Result<(), RuntimeError> {
+        if self.current_choices.is_none() {
+            return Ok(());
+        }
+        let index = self
+            .vm
+            .default_choice()
+            .ok_or(RuntimeError::NoDefaultChoice)?;
+        self.select_choice(index)
+    }
+
     fn step_vm(&mut self) -> Result<(), RuntimeError> {
         let result = self.vm.step()?;
-        self.handle_step_result(result);
-        Ok(())
+        self.handle_step_result(result)
     }
 
-    fn handle_step_result(&mut self, result: StepResult) {
+    fn handle_step_result(&mut self, result: StepResult) -> Result<(), RuntimeError> {
+        self.current_wait = None;
         match result {
             StepResult::Line(text) => {
                 self.current_line = Some(text);
@@ -282,10 +883,65 @@ impl Runtime {
                 self.current_line = None;
                 self.current_choices = Some(choices);
             }
+            StepResult::Wait(seconds) => {
+                self.current_line = None;
+                self.current_wait = Some(seconds);
+                self.is_done = self.vm.is_at_end();
+            }
             StepResult::Done => {
                 self.current_line = None;
                 self.is_done = true;
             }
         }
+        self.apply_line_filter(self.catch_host_panics)
     }
+
+    /// Run the line filter, if one is set, over the text on show, and trim
+    /// the resulting choices. With `catch` set, a panic in the filter fails
+    /// with `HostCallbackPanicked`.
+    fn apply_line_filter(&mut self, catch: bool) -> Result<(), RuntimeError> {
+        let filter = self.line_filter.as_deref();
+        let apply = |filter: &dyn Fn(&str) -> String, text: &str| {
+            guard_host_call(catch, "line filter", || filter(text))
+        };
+        self.filtered_line = filter
+            .zip(self.current_line.as_deref())
+            .map(|(filter, line)| apply(filter, line))
+            .transpose()?;
+        self.filtered_choices = filter
+            .zip(self.current_choices.as_deref())
+            .map(|(filter, choices)| {
+                choices
+                    .iter()
+                    .map(|choice| apply(filter, choice))
+                    .collect::<Result<_, _>>()
+            })
+            .transpose()?;
+        self.trimmed_choices = trimmed(
+            self.filtered_choices
+                .as_deref()
+                .or(self.current_choices.as_deref())
+                .unwrap_or(&[]),
+        );
+        Ok(())
+    }
+}
+
+/// `text` without spaces and tabs at either end.
+fn trim(text: &str) -> &str {
+    text.trim_matches([' ', '\t'])
+}
+
+/// `choices` trimmed, or `None` if none of them would change, so the common
+/// case allocates nothing.
+fn trimmed(choices: &[String]) -> Option<Vec<String>> {
+    choices
+        .iter()
+        .any(|choice| trim(choice).len() != choice.len())
+        .then(|| {
+            choices
+                .iter()
+                .map(|choice| trim(choice).to_string())
+                .collect()
+        })
 }
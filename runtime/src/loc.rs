@@ -0,0 +1,205 @@
+//! Extracting translatable text from Bobbin source.
+//!
+//! Translation happens outside the engine, in PO files or spreadsheets, so
+//! every line and choice needs an identifier that survives a round trip. Each
+//! one gets a hash of where it sits in the script; tags are left alone, since
+//! they are categories rather than names and need not be unique.
+//! `Runtime::with_string_table` plays translations back by the same
+//! identifiers.
+
+use std::collections::HashMap;
+
+use crate::BobbinError;
+use crate::ast::{Choice, NodeId, Script, Stmt, TextPart};
+use crate::chunk::NumberFormat;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::token::Span;
+
+/// Whether an extracted string is a dialogue line or a choice.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LocKind {
+    Line,
+    Choice,
+}
+
+/// One translatable string.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocEntry {
+    /// Stable identifier: a 16-digit hex hash of the statement's position.
+    pub id: String,
+    /// The text as an author would write it: variable interpolations are kept
+    /// verbatim (`{gold:0.2}`), expressions become placeholders numbered in
+    /// order (`{0}` for `{gold + 1}`), and literal braces are escaped again
+    /// (`{{`).
+    pub text: String,
+    pub kind: LocKind,
+    pub span: Span,
+}
+
+/// Collect every line and choice in `source`, in source order.
+///
+/// Hashed identifiers depend only on the statement's position among its
+/// siblings and enclosing choices, not on its text, so fixing a typo keeps the
+/// identifier while inserting a line above shifts those below it. `include`
+/// directives are not followed.
+pub fn extract_strings(source: &str) -> Result<Vec<LocEntry>, BobbinError> {
+    let tokens = Scanner::new(source).tokens();
+    let ast = Parser::new(tokens).parse()?;
+    let mut entries = Vec::new();
+    walk(&ast.statements, "", &mut |id, text| {
+        let (parts, kind, span) = match text {
+            Text::Line { parts, span, .. } => (parts, LocKind::Line, span),
+            Text::Choice(choice) => (choice.parts.as_slice(), LocKind::Choice, choice.span),
+        };
+        entries.push(LocEntry {
+            id,
+            text: author_text(parts, source),
+            kind,
+            span,
+        });
+    });
+    Ok(entries)
+}
+
+/// The string table id of every line and choice in `script`, by node.
+pub(crate) fn string_ids(script: &Script) -> HashMap<NodeId, String> {
+    let mut ids = HashMap::new();
+    walk(&script.statements, "", &mut |id, text| {
+        let node = match text {
+            Text::Line { id, .. } => id,
+            Text::Choice(choice) => choice.id,
+        };
+        ids.insert(node, id);
+    });
+    ids
+}
+
+/// A translatable piece of text found by `walk`.
+enum Text<'a> {
+    Line {
+        id: NodeId,
+        parts: &'a [TextPart],
+        span: Span,
+    },
+    Choice(&'a Choice),
+}
+
+/// Call `visit` with the string table id of each line and choice, in source order.
+fn walk<'a>(statements: &'a [Stmt], path: &str, visit: &mut impl FnMut(String, Text<'a>)) {
+    for (index, stmt) in statements.iter().enumerate() {
+        let path = format!("{}/{}", path, index);
+        match stmt {
+            Stmt::Line { id, parts, span } => visit(
+                position_id("line", &path),
+                Text::Line {
+                    id: *id,
+                    parts,
+                    span: *span,
+                },
+            ),
+            Stmt::ChoiceSet { choices, .. } => {
+                for (choice_index, choice) in choices.iter().enumerate() {
+                    let path = format!("{}/{}", path, choice_index);
+                    visit(position_id("choice", &path), Text::Choice(choice));
+                    walk(&choice.nested, &path, visit);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn author_text(parts: &[TextPart], source: &str) -> String {
+    let mut text = String::new();
+    let mut expressions = 0;
+    for part in parts {
+        match part {
+            TextPart::Literal { text: literal, .. } => {
+                text.push_str(&literal.replace('{', "{{").replace('}', "}}"))
+            }
+            TextPart::VarRef { span, .. } => text.push_str(&source[span.start..span.end]),
+            // A translation cannot evaluate expressions of its own, so it
+            // refers to the original's values by number
+            TextPart::Expr { format, .. } => {
+                text.push_str(&match format {
+                    Some(format) => format!("{{{}:{}}}", expressions, format),
+                    None => format!("{{{}}}", expressions),
+                });
+                expressions += 1;
+            }
+        }
+    }
+    text
+}
+
+/// FNV-1a over the kind and position path. Written out rather than using
+/// `std::hash`, whose output may change between Rust releases.
+fn position_id(kind: &str, path: &str) -> String {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in kind.bytes().chain(path.bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    format!("{:016x}", hash)
+}
+
+/// A piece of translated text.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum TemplatePart {
+    Text(String),
+    Var {
+        name: String,
+        format: Option<NumberFormat>,
+    },
+}
+
+/// Split a translation into text and `{name}` or `{name:format}` placeholders,
+/// with `{{` and `}}` standing for literal braces as in source text. Spaces
+/// around a name or format are ignored, as in source text.
+pub(crate) fn parse_template(template: &str) -> Result<Vec<TemplatePart>, String> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if let Some(after) = rest.strip_prefix("{{") {
+            text.push('{');
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("}}") {
+            text.push('}');
+            rest = after;
+        } else if c == '{' {
+            let Some(close) = rest.find('}') else {
+                return Err("has an unclosed '{'".to_string());
+            };
+            let inner = &rest[1..close];
+            let (name, format) = match inner.split_once(':') {
+                Some((name, spec)) => match NumberFormat::parse(spec.trim()) {
+                    Some(format) => (name.trim(), Some(format)),
+                    None => return Err(format!("has an invalid number format '{}'", spec)),
+                },
+                None => (inner.trim(), None),
+            };
+            if name.is_empty() {
+                return Err("has an empty '{}'".to_string());
+            }
+            if !text.is_empty() {
+                parts.push(TemplatePart::Text(std::mem::take(&mut text)));
+            }
+            parts.push(TemplatePart::Var {
+                name: name.to_string(),
+                format,
+            });
+            rest = &rest[close + 1..];
+        } else if c == '}' {
+            return Err("has an unmatched '}'".to_string());
+        } else {
+            text.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !text.is_empty() {
+        parts.push(TemplatePart::Text(text));
+    }
+    Ok(parts)
+}
@@ -1,14 +1,39 @@
 use std::iter::Peekable;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::ast::{Choice, ExternDeclData, Literal, NodeId, Script, Stmt, TextPart, VarBindingData};
+use crate::ast::{
+    AssignOp, BinaryOp, Choice, CompoundAssignData, Expr, ExternDeclData, Literal, NodeId, Script,
+    Stmt, TextPart, UnaryOp, VarBindingData,
+};
+use crate::chunk::{LogicOp, NumberFormat};
 use crate::diagnostic::{Diagnostic, DiagnosticContext, IntoDiagnostic};
+use crate::include::IncludeError;
 use crate::scanner::LexicalError;
 use crate::token::{Span, Token, TokenKind};
 
 #[derive(Debug, Clone)]
 pub enum ParseError {
     Lexical(LexicalError),
-    Syntax { message: String, span: Span },
+    Syntax {
+        message: String,
+        span: Span,
+    },
+    /// The next token is not one the grammar allows at this point
+    UnexpectedToken {
+        /// Everything that would have been accepted, e.g. `"'='"` or
+        /// `"variable name"`
+        expected: Vec<&'static str>,
+        found: TokenKind,
+        /// Where in the statement the parser was, e.g. `"after 'set'"`
+        context: Option<String>,
+        span: Span,
+    },
+    Include(IncludeError),
+    /// A choice marker on an indented line that no choice opened, so there is
+    /// no choice set for it to join
+    StrayChoice {
+        span: Span,
+    },
 }
 
 impl From<LexicalError> for ParseError {
@@ -17,6 +42,12 @@ impl From<LexicalError> for ParseError {
     }
 }
 
+impl From<IncludeError> for ParseError {
+    fn from(err: IncludeError) -> Self {
+        ParseError::Include(err)
+    }
+}
+
 impl IntoDiagnostic for ParseError {
     fn into_diagnostic(self, ctx: &DiagnosticContext) -> Diagnostic {
         match self {
@@ -24,6 +55,35 @@ impl IntoDiagnostic for ParseError {
             ParseError::Syntax { message, span } => {
                 Diagnostic::error(format!("syntax error: {}", message), span, &message)
             }
+            ParseError::UnexpectedToken {
+                expected,
+                found,
+                context,
+                span,
+            } => {
+                let mut expected = one_of(&expected);
+                if let Some(context) = context {
+                    expected = format!("{} {}", expected, context);
+                }
+                Diagnostic::error(
+                    format!(
+                        "syntax error: expected {}, found {}",
+                        expected,
+                        found.describe()
+                    ),
+                    span,
+                    format!("expected {}", expected),
+                )
+            }
+            ParseError::Include(include_err) => include_err.into_diagnostic(ctx),
+            ParseError::StrayChoice { span } => Diagnostic::error(
+                "choice marker out of place",
+                span,
+                "indented under a line that is not a choice",
+            )
+            .with_note(
+                "Choices in a set line up with the text before them; only what follows a choice is indented under it",
+            ),
         }
     }
 }
@@ -31,7 +91,13 @@ impl IntoDiagnostic for ParseError {
 pub struct Parser<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> {
     tokens: Peekable<I>,
     errors: Vec<ParseError>,
+    /// Errors reported so far, including any handed to `on_error`
+    error_count: usize,
+    /// Receives each error as it is found, instead of `errors`
+    on_error: Option<&'a mut dyn FnMut(ParseError)>,
     next_id: usize,
+    /// Stop before the next top-level statement once this is set
+    cancel: Option<&'a AtomicBool>,
 }
 
 impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
@@ -39,10 +105,35 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
         Self {
             tokens: tokens.peekable(),
             errors: Vec::new(),
+            error_count: 0,
+            on_error: None,
             next_id: 0,
+            cancel: None,
         }
     }
 
+    /// Hand each error to `on_error` as soon as it is found rather than
+    /// collecting them. The parse still fails if there were any, but the
+    /// error list it returns is empty.
+    pub fn with_error_sink(mut self, on_error: &'a mut dyn FnMut(ParseError)) -> Self {
+        self.on_error = Some(on_error);
+        self
+    }
+
+    /// Stop parsing before the next top-level statement once `cancel` is set,
+    /// keeping what was parsed so far.
+    pub fn with_cancel(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Number nodes from `first_id` instead of zero, so that scripts parsed
+    /// separately and then merged never share a NodeId.
+    pub fn with_first_id(mut self, first_id: usize) -> Self {
+        self.next_id = first_id;
+        self
+    }
+
     fn next_id(&mut self) -> NodeId {
         let id = NodeId(self.next_id);
         self.next_id += 1;
@@ -62,6 +153,35 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
         }
     }
 
+    /// Report that the next token is none of `expected`.
+    fn unexpected(&mut self, expected: &[&'static str], context: Option<String>) {
+        let span = self.current_span();
+        self.unexpected_at(expected, context, span);
+    }
+
+    /// Report that the next token is none of `expected`, pointing at `span`
+    /// rather than the token itself.
+    fn unexpected_at(&mut self, expected: &[&'static str], context: Option<String>, span: Span) {
+        let found = match self.tokens.peek() {
+            Some(Ok(t)) => t.kind,
+            _ => TokenKind::Eof,
+        };
+        self.report(ParseError::UnexpectedToken {
+            expected: expected.to_vec(),
+            found,
+            context,
+            span,
+        });
+    }
+
+    fn report(&mut self, error: ParseError) {
+        self.error_count += 1;
+        match &mut self.on_error {
+            Some(on_error) => on_error(error),
+            None => self.errors.push(error),
+        }
+    }
+
     /// Consume and return the next token.
     /// Only call when you've already verified a token exists via peek/check.
     fn advance(&mut self) -> Token<'a> {
@@ -75,8 +195,14 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
             Some(Ok(t)) => match t.kind {
                 TokenKind::Temp => Some(self.temp_declaration()),
                 TokenKind::Save => Some(self.save_declaration()),
+                TokenKind::Const => Some(self.const_declaration()),
                 TokenKind::Extern => Some(self.extern_declaration()),
                 TokenKind::Set => Some(self.assignment()),
+                TokenKind::Goto => Some(self.goto_statement()),
+                TokenKind::Include => Some(self.include_statement()),
+                TokenKind::Wait => Some(self.wait_statement()),
+                TokenKind::End => Some(self.end_statement()),
+                TokenKind::LabelMarker => Some(self.label_statement()),
                 TokenKind::TextSegment | TokenKind::OpenBrace => Some(self.line_statement()),
                 TokenKind::Choice => Some(self.choice_set()),
                 _ => None,
@@ -85,22 +211,65 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
         }
     }
 
-    pub fn parse(mut self) -> Result<Script, Vec<ParseError>> {
+    pub fn parse(self) -> Result<Script, Vec<ParseError>> {
+        self.parse_counted().0
+    }
+
+    /// Parse, also returning the first NodeId left unused.
+    pub fn parse_counted(mut self) -> (Result<Script, Vec<ParseError>>, usize) {
+        let statements = self.parse_statements();
+        let result = if self.error_count == 0 {
+            Ok(Script { statements })
+        } else {
+            Err(self.errors)
+        };
+        (result, self.next_id)
+    }
+
+    /// Parse the whole input, returning every statement that parsed cleanly
+    /// alongside the errors.
+    ///
+    /// A broken statement is dropped without losing the statements around it,
+    /// so tooling can still outline or resolve the rest of the script.
+    pub fn parse_recover(mut self) -> (Script, Vec<ParseError>) {
+        let statements = self.parse_statements();
+        (Script { statements }, self.errors)
+    }
+
+    /// Try to parse a statement, discarding it if it reported an error.
+    ///
+    /// Choice sets are kept: their broken nested statements are already
+    /// dropped, and the rest of the set is still useful.
+    fn parse_clean_statement(&mut self) -> Option<Option<Stmt>> {
+        let errors_before = self.error_count;
+        let stmt = self.try_parse_statement()?;
+        let clean = self.error_count == errors_before || matches!(stmt, Stmt::ChoiceSet { .. });
+        Some(clean.then_some(stmt))
+    }
+
+    fn parse_statements(&mut self) -> Vec<Stmt> {
         let mut statements = Vec::new();
 
         loop {
+            if self
+                .cancel
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+            {
+                break;
+            }
+
             // Handle errors first
             if matches!(self.tokens.peek(), Some(Err(_))) {
                 if let Some(Err(e)) = self.tokens.next() {
-                    self.errors.push(e.into());
+                    self.report(e.into());
                 }
                 self.synchronize();
                 continue;
             }
 
             // Try to parse a statement
-            if let Some(stmt) = self.try_parse_statement() {
-                statements.push(stmt);
+            if let Some(stmt) = self.parse_clean_statement() {
+                statements.extend(stmt);
                 continue;
             }
 
@@ -108,19 +277,15 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
             match self.tokens.peek() {
                 None => break,
                 Some(Ok(token)) => match token.kind {
-                    TokenKind::NewLine | TokenKind::Indent | TokenKind::Dedent => {
-                        // Skip newlines and indent/dedent tokens at top level
+                    TokenKind::Indent => self.stray_indent(),
+                    TokenKind::NewLine | TokenKind::Comment | TokenKind::Dedent => {
+                        // Skip newlines, comments, and dedent tokens at top level
                         self.advance();
                     }
                     TokenKind::Eof => break,
                     _ => {
                         // Unexpected token at statement level
-                        let span = token.span;
-                        let kind = token.kind;
-                        self.errors.push(ParseError::Syntax {
-                            message: format!("Unexpected token: {:?}", kind),
-                            span,
-                        });
+                        self.unexpected(&["dialogue text", "a choice", "a statement"], None);
                         self.advance();
                     }
                 },
@@ -128,27 +293,33 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
             }
         }
 
-        if self.errors.is_empty() {
-            Ok(Script { statements })
-        } else {
-            Err(self.errors)
-        }
+        statements
     }
 
     /// Parse a temp declaration: temp name = value
     fn temp_declaration(&mut self) -> Stmt {
         let start_token = self.advance(); // Consume 'temp'
-        let data = self.parse_var_binding("temp", start_token.span.start);
+        let mut data = self.parse_var_binding("temp", start_token.span.start);
+        data.doc = self.trailing_comment();
         Stmt::TempDecl(data)
     }
 
     /// Parse a save declaration: save name = value
     fn save_declaration(&mut self) -> Stmt {
         let start_token = self.advance(); // Consume 'save'
-        let data = self.parse_var_binding("save", start_token.span.start);
+        let mut data = self.parse_var_binding("save", start_token.span.start);
+        data.doc = self.trailing_comment();
         Stmt::SaveDecl(data)
     }
 
+    /// Parse a const declaration: const name = value
+    fn const_declaration(&mut self) -> Stmt {
+        let start_token = self.advance(); // Consume 'const'
+        let mut data = self.parse_var_binding("const", start_token.span.start);
+        data.doc = self.trailing_comment();
+        Stmt::ConstDecl(data)
+    }
+
     /// Parse an extern declaration: extern name (no initializer)
     fn extern_declaration(&mut self) -> Stmt {
         let start_token = self.advance(); // Consume 'extern'
@@ -159,11 +330,7 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
             let token = self.advance();
             (token.lexeme.to_string(), token.span.end)
         } else {
-            let span = self.current_span();
-            self.errors.push(ParseError::Syntax {
-                message: "Expected identifier after 'extern'".to_string(),
-                span,
-            });
+            self.unexpected(&["variable name"], Some("after 'extern'".to_string()));
             self.synchronize();
             (String::new(), start_token.span.end)
         };
@@ -175,14 +342,323 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
                 start: start_token.span.start,
                 end,
             },
+            doc: self.trailing_comment(),
         })
     }
 
-    /// Parse an assignment: set name = value
+    /// Parse a goto: goto name
+    fn goto_statement(&mut self) -> Stmt {
+        let start_token = self.advance(); // Consume 'goto'
+        let (label, label_span) = self.parse_statement_name("goto", start_token.span);
+        Stmt::Goto {
+            label,
+            label_span,
+            span: start_token.span.merge(label_span),
+        }
+    }
+
+    /// Parse a wait: wait seconds
+    fn wait_statement(&mut self) -> Stmt {
+        let start_token = self.advance(); // Consume 'wait'
+
+        if !self.check(TokenKind::Number) {
+            self.unexpected(&["duration in seconds"], Some("after 'wait'".to_string()));
+            self.synchronize();
+            return Stmt::Wait {
+                seconds: 0.0,
+                span: start_token.span,
+            };
+        }
+
+        let token = self.advance();
+        let seconds: f64 = token.lexeme.parse().unwrap_or(0.0);
+        if seconds < 0.0 {
+            self.report(ParseError::Syntax {
+                message: "Wait duration cannot be negative".to_string(),
+                span: token.span,
+            });
+        }
+        self.expect_line_end("wait");
+        Stmt::Wait {
+            seconds,
+            span: start_token.span.merge(token.span),
+        }
+    }
+
+    /// Parse an end: end
+    fn end_statement(&mut self) -> Stmt {
+        let token = self.advance(); // Consume 'end'
+        self.expect_line_end("end");
+        Stmt::End { span: token.span }
+    }
+
+    /// Parse an include: include "path"
+    fn include_statement(&mut self) -> Stmt {
+        let start_token = self.advance(); // Consume 'include'
+
+        if !self.check(TokenKind::String) {
+            self.unexpected(&["quoted path"], Some("after 'include'".to_string()));
+            self.synchronize();
+            return Stmt::Include {
+                path: String::new(),
+                path_span: start_token.span,
+                span: start_token.span,
+            };
+        }
+
+        let token = self.advance();
+        self.expect_line_end("include");
+        let lexeme = token.lexeme;
+        Stmt::Include {
+            path: unescape_string(&lexeme[1..lexeme.len() - 1]),
+            path_span: token.span,
+            span: start_token.span.merge(token.span),
+        }
+    }
+
+    /// Parse a label: == name ==
+    fn label_statement(&mut self) -> Stmt {
+        let start_token = self.advance(); // Consume '=='
+        let (name, name_span) = self.parse_statement_name("label", start_token.span);
+        Stmt::Label {
+            name,
+            span: start_token.span.merge(name_span),
+        }
+    }
+
+    /// Parse the identifier that ends a goto or label line.
+    /// Returns the name and its span; on error, an empty name at the keyword.
+    fn parse_statement_name(&mut self, what: &str, keyword: Span) -> (String, Span) {
+        if !self.check(TokenKind::Identifier) {
+            self.unexpected(&["label name"], Some(format!("in {}", what)));
+            self.synchronize();
+            return (String::new(), keyword);
+        }
+
+        let token = self.advance();
+        self.expect_line_end(what);
+        (token.lexeme.to_string(), token.span)
+    }
+
+    /// Consume a statement's trailing `// comment`, returning its trimmed text
+    /// unless it is empty.
+    fn trailing_comment(&mut self) -> Option<String> {
+        if !self.check(TokenKind::Comment) {
+            return None;
+        }
+        let token = self.advance();
+        let text = token.lexeme.trim_start_matches('/').trim();
+        (!text.is_empty()).then(|| text.to_string())
+    }
+
+    /// Report anything left on the line after a statement that must end it.
+    fn expect_line_end(&mut self, what: &str) {
+        if !matches!(
+            self.tokens.peek(),
+            None | Some(Ok(Token {
+                kind: TokenKind::NewLine | TokenKind::Comment | TokenKind::Dedent | TokenKind::Eof,
+                ..
+            }))
+        ) {
+            self.unexpected(&["end of line"], Some(format!("after {}", what)));
+            self.synchronize();
+        }
+    }
+
+    /// Parse an assignment: set name = value, or set name op= value
     fn assignment(&mut self) -> Stmt {
         let start_token = self.advance(); // Consume 'set'
-        let data = self.parse_var_binding("set", start_token.span.start);
-        Stmt::Assignment(data)
+        let start = start_token.span.start;
+        let id = self.next_id();
+
+        let Some(name) = self.parse_binding_name("set") else {
+            return Stmt::Assignment(VarBindingData {
+                id,
+                name: String::new(),
+                value: missing_value(start),
+                span: Span { start, end: start },
+                doc: None,
+            });
+        };
+
+        let op = match self.tokens.peek() {
+            Some(Ok(t)) => match t.kind {
+                TokenKind::PlusEquals => Some(AssignOp::Add),
+                TokenKind::MinusEquals => Some(AssignOp::Subtract),
+                TokenKind::StarEquals => Some(AssignOp::Multiply),
+                TokenKind::SlashEquals => Some(AssignOp::Divide),
+                _ => None,
+            },
+            _ => None,
+        };
+        let Some(op) = op else {
+            return Stmt::Assignment(self.finish_var_binding("set", start, id, name));
+        };
+        self.advance(); // Consume the operator
+
+        let value = self.parse_expression();
+        Stmt::CompoundAssignment(CompoundAssignData {
+            id,
+            name,
+            op,
+            span: Span {
+                start,
+                end: value.span().end,
+            },
+            value,
+        })
+    }
+
+    /// Parse an expression, reporting a `)` with no matching `(`.
+    fn parse_expression(&mut self) -> Expr {
+        let expr = self.parse_binary(0);
+        while self.check(TokenKind::RightParen) {
+            let token = self.advance();
+            self.report(ParseError::Syntax {
+                message: "Unmatched ')'".to_string(),
+                span: token.span,
+            });
+        }
+        expr
+    }
+
+    /// Parse operands joined by infix operators that bind at least as tightly
+    /// as `min_precedence`. All infix operators are left-associative.
+    fn parse_binary(&mut self, min_precedence: u8) -> Expr {
+        let mut left = self.parse_unary();
+        while let Some((op, precedence)) = self.peek_infix()
+            && precedence >= min_precedence
+        {
+            self.advance();
+            let right = self.parse_binary(precedence + 1);
+            let span = left.span().merge(right.span());
+            let (left_box, right_box) = (Box::new(left), Box::new(right));
+            left = match op {
+                Infix::Logic(op) => Expr::Logical {
+                    op,
+                    left: left_box,
+                    right: right_box,
+                    span,
+                },
+                Infix::Binary(op) => Expr::Binary {
+                    op,
+                    left: left_box,
+                    right: right_box,
+                    span,
+                },
+            };
+        }
+        left
+    }
+
+    /// The infix operator at the current token and its precedence, if any.
+    /// Higher binds tighter.
+    fn peek_infix(&mut self) -> Option<(Infix, u8)> {
+        let kind = match self.tokens.peek() {
+            Some(Ok(token)) => token.kind,
+            _ => return None,
+        };
+        let infix = match kind {
+            TokenKind::Or => (Infix::Logic(LogicOp::Or), 1),
+            TokenKind::And => (Infix::Logic(LogicOp::And), 2),
+            TokenKind::EqualEqual => (Infix::Binary(BinaryOp::Equal), 3),
+            TokenKind::BangEqual => (Infix::Binary(BinaryOp::NotEqual), 3),
+            TokenKind::Less => (Infix::Binary(BinaryOp::Less), 3),
+            TokenKind::LessEqual => (Infix::Binary(BinaryOp::LessEqual), 3),
+            TokenKind::Greater => (Infix::Binary(BinaryOp::Greater), 3),
+            TokenKind::GreaterEqual => (Infix::Binary(BinaryOp::GreaterEqual), 3),
+            TokenKind::Plus => (Infix::Binary(BinaryOp::Add), 4),
+            TokenKind::Minus => (Infix::Binary(BinaryOp::Subtract), 4),
+            TokenKind::Star => (Infix::Binary(BinaryOp::Multiply), 5),
+            TokenKind::Slash => (Infix::Binary(BinaryOp::Divide), 5),
+            _ => return None,
+        };
+        Some(infix)
+    }
+
+    /// Parse a `not` or `-` prefix, or a plain operand.
+    fn parse_unary(&mut self) -> Expr {
+        let op = if self.check(TokenKind::Not) {
+            UnaryOp::Not
+        } else if self.check(TokenKind::Minus) {
+            UnaryOp::Negate
+        } else {
+            return self.parse_operand();
+        };
+        let token = self.advance();
+        let operand = self.parse_unary();
+        Expr::Unary {
+            op,
+            span: token.span.merge(operand.span()),
+            operand: Box::new(operand),
+        }
+    }
+
+    /// Parse a single operand: a literal, a variable, or a parenthesized expression
+    fn parse_operand(&mut self) -> Expr {
+        if self.check(TokenKind::LeftParen) {
+            let open = self.advance();
+            let inner = self.parse_binary(0);
+            if self.check(TokenKind::RightParen) {
+                self.advance();
+            } else {
+                self.report(ParseError::Syntax {
+                    message: "Unclosed '('".to_string(),
+                    span: open.span,
+                });
+            }
+            return inner;
+        }
+        if self.check(TokenKind::Identifier) {
+            let token = self.advance();
+            if self.check(TokenKind::LeftParen) {
+                return self.finish_call(token);
+            }
+            return Expr::Var {
+                id: self.next_id(),
+                name: token.lexeme.to_string(),
+                span: token.span,
+            };
+        }
+        let start = self.current_span().start;
+        let (value, end) = self.parse_literal();
+        Expr::Literal {
+            value,
+            span: Span { start, end },
+        }
+    }
+
+    /// Parse the arguments of a call whose name was just consumed: `(1, 6)`
+    fn finish_call(&mut self, name: Token<'a>) -> Expr {
+        let open = self.advance();
+        let mut args = Vec::new();
+        if !self.check(TokenKind::RightParen) {
+            loop {
+                args.push(self.parse_binary(0));
+                if !self.check(TokenKind::Comma) {
+                    break;
+                }
+                self.advance();
+            }
+        }
+        let end = if self.check(TokenKind::RightParen) {
+            self.advance().span.end
+        } else {
+            self.report(ParseError::Syntax {
+                message: "Unclosed '('".to_string(),
+                span: open.span,
+            });
+            args.last().map_or(open.span.end, |arg| arg.span().end)
+        };
+        Expr::Call {
+            name: name.lexeme.to_string(),
+            name_span: name.span,
+            args,
+            span: Span {
+                start: name.span.start,
+                end,
+            },
+        }
     }
 
     /// Parse a literal value (string, number, or boolean)
@@ -215,19 +691,13 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
                     (Literal::Bool(false), token.span.end)
                 }
                 _ => {
-                    let span = t.span;
-                    self.errors.push(ParseError::Syntax {
-                        message: "Expected literal value".to_string(),
-                        span,
-                    });
-                    (Literal::Bool(false), span.end)
+                    let end = t.span.end;
+                    self.unexpected(&OPERAND, None);
+                    (Literal::Bool(false), end)
                 }
             },
             _ => {
-                self.errors.push(ParseError::Syntax {
-                    message: "Expected literal value".to_string(),
-                    span: Span { start: 0, end: 0 },
-                });
+                self.unexpected(&OPERAND, None);
                 (Literal::Bool(false), 0)
             }
         }
@@ -240,57 +710,78 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
         let id = self.next_id();
 
         // Expect identifier
-        let name = if self.check(TokenKind::Identifier) {
-            let token = self.advance();
-            token.lexeme.to_string()
-        } else {
-            let span = self.current_span();
-            self.errors.push(ParseError::Syntax {
-                message: format!("Expected identifier after '{}'", keyword),
-                span,
-            });
-            self.synchronize();
+        let Some(name) = self.parse_binding_name(keyword) else {
             return VarBindingData {
                 id,
                 name: String::new(),
-                value: Literal::Bool(false),
+                value: missing_value(start),
                 span: Span { start, end: start },
+                doc: None,
             };
         };
 
+        self.finish_var_binding(keyword, start, id, name)
+    }
+
+    /// Parse the identifier after a binding keyword, reporting an error and
+    /// synchronizing if it is missing.
+    fn parse_binding_name(&mut self, keyword: &str) -> Option<String> {
+        if self.check(TokenKind::Identifier) {
+            let token = self.advance();
+            Some(token.lexeme.to_string())
+        } else {
+            self.unexpected(&["variable name"], Some(format!("after '{}'", keyword)));
+            self.synchronize();
+            None
+        }
+    }
+
+    /// Parse the `= value` part of a variable binding whose name was already consumed.
+    fn finish_var_binding(
+        &mut self,
+        keyword: &str,
+        start: usize,
+        id: NodeId,
+        name: String,
+    ) -> VarBindingData {
         // Expect '='
         if self.check(TokenKind::Equals) {
             self.advance();
         } else {
-            let span = self.current_span();
-            self.errors.push(ParseError::Syntax {
-                message: format!("Expected '=' in {} statement", keyword),
-                span,
-            });
+            let expected: &[&str] = if keyword == "set" {
+                &["'='", "'+='", "'-='", "'*='", "'/='"]
+            } else {
+                &["'='"]
+            };
+            self.unexpected(expected, Some(format!("in {} statement", keyword)));
             self.synchronize();
             return VarBindingData {
                 id,
                 name,
-                value: Literal::Bool(false),
+                value: missing_value(start),
                 span: Span { start, end: start },
+                doc: None,
             };
         }
 
-        // Parse literal value
-        let (value, end) = self.parse_literal();
-
+        let value = self.parse_expression();
         VarBindingData {
             id,
             name,
+            span: Span {
+                start,
+                end: value.span().end,
+            },
             value,
-            span: Span { start, end },
+            doc: None,
         }
     }
 
     /// Parse a line statement (text content with possible interpolation)
     fn line_statement(&mut self) -> Stmt {
+        let id = self.next_id();
         let (parts, span) = self.parse_text_parts();
-        Stmt::Line { parts, span }
+        Stmt::Line { id, parts, span }
     }
 
     /// Parse text parts until newline (TextSegment, interpolations)
@@ -318,44 +809,9 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
                         if start.is_none() {
                             start = Some(open.span.start);
                         }
-
-                        // Expect identifier
-                        match self.tokens.peek() {
-                            Some(Ok(t)) if t.kind == TokenKind::Identifier => {
-                                let id_token = self.advance();
-                                let var_name = id_token.lexeme.to_string();
-
-                                // Expect close brace
-                                match self.tokens.peek() {
-                                    Some(Ok(t)) if t.kind == TokenKind::CloseBrace => {
-                                        let close = self.advance();
-                                        end = close.span.end;
-                                        parts.push(TextPart::VarRef {
-                                            id: self.next_id(),
-                                            name: var_name,
-                                            span: Span {
-                                                start: open.span.start,
-                                                end: close.span.end,
-                                            },
-                                        });
-                                    }
-                                    _ => {
-                                        self.errors.push(ParseError::Syntax {
-                                            message: "Expected '}' after variable name".to_string(),
-                                            span: id_token.span,
-                                        });
-                                        end = id_token.span.end;
-                                    }
-                                }
-                            }
-                            _ => {
-                                self.errors.push(ParseError::Syntax {
-                                    message: "Expected variable name after '{'".to_string(),
-                                    span: open.span,
-                                });
-                                end = open.span.end;
-                            }
-                        }
+                        let (part, part_end) = self.parse_interpolation(open.span);
+                        parts.extend(part);
+                        end = part_end;
                     }
                     TokenKind::NewLine | TokenKind::Eof | TokenKind::Dedent => {
                         // End of text content
@@ -368,7 +824,7 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
                 },
                 Some(Err(_)) => {
                     if let Some(Err(e)) = self.tokens.next() {
-                        self.errors.push(e.into());
+                        self.report(e.into());
                     }
                     break;
                 }
@@ -383,28 +839,141 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
         (parts, span)
     }
 
+    /// Parse an interpolation after its `{`, returning the part and where it ends.
+    ///
+    /// A lone variable name becomes a `VarRef`; anything else is an expression.
+    fn parse_interpolation(&mut self, open: Span) -> (Option<TextPart>, usize) {
+        let empty = match self.tokens.peek() {
+            Some(Ok(t)) => matches!(
+                t.kind,
+                TokenKind::CloseBrace
+                    | TokenKind::FormatSpec
+                    | TokenKind::NewLine
+                    | TokenKind::Eof
+                    | TokenKind::Dedent
+            ),
+            _ => true,
+        };
+        if empty {
+            self.unexpected_at(
+                &["variable name", "expression"],
+                Some("after '{'".to_string()),
+                open,
+            );
+            return (None, open.end);
+        }
+
+        let expr = self.parse_expression();
+        let format = self.parse_format_spec();
+        if !self.check(TokenKind::CloseBrace) {
+            let what = match expr {
+                Expr::Var { .. } => "variable name",
+                _ => "expression",
+            };
+            self.unexpected_at(&["'}'"], Some(format!("after {}", what)), expr.span());
+            // Skip the rest of the interpolation so it is not reported again
+            while let Some(Ok(t)) = self.tokens.peek() {
+                match t.kind {
+                    TokenKind::CloseBrace => {
+                        let close = self.advance();
+                        return (None, close.span.end);
+                    }
+                    TokenKind::NewLine
+                    | TokenKind::Eof
+                    | TokenKind::Dedent
+                    | TokenKind::TextSegment => break,
+                    _ => {
+                        self.advance();
+                    }
+                }
+            }
+            return (None, expr.span().end);
+        }
+
+        let close = self.advance();
+        let span = Span {
+            start: open.start,
+            end: close.span.end,
+        };
+        let part = match expr {
+            Expr::Var {
+                id,
+                name,
+                span: name_span,
+            } => TextPart::VarRef {
+                id,
+                name,
+                name_span,
+                format,
+                span,
+            },
+            expr => TextPart::Expr { expr, format, span },
+        };
+        (Some(part), span.end)
+    }
+
+    /// Parse an optional `:format` after an interpolated value.
+    fn parse_format_spec(&mut self) -> Option<NumberFormat> {
+        if !self.check(TokenKind::FormatSpec) {
+            return None;
+        }
+        let token = self.advance();
+        let spec = token.lexeme.trim();
+        let format = NumberFormat::parse(spec);
+        if format.is_none() {
+            self.report(ParseError::Syntax {
+                message: format!(
+                    "Invalid format '{}' - expected '0', '0.N', or a trailing '%'",
+                    spec
+                ),
+                span: token.span,
+            });
+        }
+        format
+    }
+
     fn choice_set(&mut self) -> Stmt {
-        let mut choices = Vec::new();
+        let mut choices: Vec<Choice> = Vec::new();
+        let mut first_marker: Option<Span> = None;
 
         loop {
             // Consume the Choice token ("- ")
             let choice_token = self.advance();
             let start = choice_token.span.start;
+            first_marker.get_or_insert(choice_token.span);
+            let id = self.next_id();
 
             // Parse the choice text (may contain interpolation)
             let (parts, text_span) = self.parse_text_parts();
-            let end = if text_span.end > 0 {
+            let mut end = if text_span.end > 0 {
                 text_span.end
             } else {
                 choice_token.span.end
             };
 
-            // Expect newline after choice text
-            if !matches!(self.tokens.peek(), Some(Ok(t)) if t.kind == TokenKind::NewLine) {
-                self.errors.push(ParseError::Syntax {
-                    message: "Expected newline after choice".to_string(),
+            // Trailing tags are metadata, not part of the displayed text
+            let mut tags = Vec::new();
+            while self.check(TokenKind::Tag) {
+                let token = self.advance();
+                tags.push(token.lexeme[1..].to_string());
+                end = token.span.end;
+            }
+            let default = tags.iter().any(|tag| tag == DEFAULT_TAG);
+            tags.retain(|tag| tag != DEFAULT_TAG);
+            if default && choices.iter().any(|choice| choice.default) {
+                self.report(ParseError::Syntax {
+                    message: "A choice set can only have one #default choice".to_string(),
                     span: Span { start, end },
                 });
+            }
+
+            // Expect newline after choice text
+            if !matches!(self.tokens.peek(), Some(Ok(t)) if t.kind == TokenKind::NewLine) {
+                self.unexpected_at(
+                    &["end of line"],
+                    Some("after choice".to_string()),
+                    Span { start, end },
+                );
                 self.synchronize();
                 break;
             }
@@ -413,18 +982,46 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
 
             // Parse any nested content under this choice
             let nested = self.parse_nested_content();
+            let branch_span = match (nested.first(), nested.last()) {
+                (Some(first), Some(last)) => Span {
+                    start: first.span().start,
+                    end: extent_end(last),
+                },
+                _ => Span { start: end, end },
+            };
 
             choices.push(Choice {
+                id,
                 parts,
                 span: Span { start, end },
+                tags,
+                default,
                 nested,
+                branch_span,
             });
 
             if !matches!(self.tokens.peek(), Some(Ok(t)) if t.kind == TokenKind::Choice) {
                 break;
             }
         }
-        Stmt::ChoiceSet { choices }
+        let first_marker = first_marker.expect("choice_set called at a choice marker");
+        let span = Span {
+            start: first_marker.start,
+            end: choices.last().map_or(first_marker.end, |c| c.span.end),
+        };
+        Stmt::ChoiceSet { choices, span }
+    }
+
+    /// Skip an indent that no choice opened. The lines under it still parse
+    /// as usual, but a choice there has no set to join, so it is reported.
+    fn stray_indent(&mut self) {
+        self.advance(); // Consume the Indent
+        if let Some(Ok(token)) = self.tokens.peek()
+            && token.kind == TokenKind::Choice
+        {
+            let span = token.span;
+            self.report(ParseError::StrayChoice { span });
+        }
     }
 
     /// Parse nested content under a choice (after Indent, before Dedent).
@@ -443,15 +1040,15 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
             // Handle errors first
             if matches!(self.tokens.peek(), Some(Err(_))) {
                 if let Some(Err(e)) = self.tokens.next() {
-                    self.errors.push(e.into());
+                    self.report(e.into());
                 }
                 self.synchronize();
                 continue;
             }
 
             // Try to parse a statement
-            if let Some(stmt) = self.try_parse_statement() {
-                statements.push(stmt);
+            if let Some(stmt) = self.parse_clean_statement() {
+                statements.extend(stmt);
                 continue;
             }
 
@@ -463,7 +1060,8 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
                         self.advance(); // Consume Dedent
                         break;
                     }
-                    TokenKind::NewLine | TokenKind::Indent => {
+                    TokenKind::Indent => self.stray_indent(),
+                    TokenKind::NewLine | TokenKind::Comment => {
                         self.advance();
                     }
                     TokenKind::Eof => break,
@@ -485,7 +1083,7 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
                 None => return,
                 Some(Err(_)) => {
                     if let Some(Err(e)) = self.tokens.next() {
-                        self.errors.push(e.into());
+                        self.report(e.into());
                     }
                 }
                 Some(Ok(token)) => match token.kind {
@@ -503,6 +1101,39 @@ impl<'a, I: Iterator<Item = Result<Token<'a>, LexicalError>>> Parser<'a, I> {
     }
 }
 
+/// The tag that marks a choice as its set's default rather than adding
+/// metadata.
+const DEFAULT_TAG: &str = "default";
+
+/// What can start an operand, for errors where a value is missing
+const OPERAND: [&str; 3] = ["literal value", "variable name", "'('"];
+
+/// An infix operator: short-circuiting or not.
+#[derive(Debug, Clone, Copy)]
+enum Infix {
+    Logic(LogicOp),
+    Binary(BinaryOp),
+}
+
+/// Stand-in value for a binding whose value could not be parsed.
+fn missing_value(at: usize) -> Expr {
+    Expr::Literal {
+        value: Literal::Bool(false),
+        span: Span { start: at, end: at },
+    }
+}
+
+/// Where `stmt` ends in the source, counting the branches of a choice set,
+/// which its own span leaves out.
+fn extent_end(stmt: &Stmt) -> usize {
+    match stmt {
+        Stmt::ChoiceSet { choices, span } => choices
+            .last()
+            .map_or(span.end, |choice| choice.branch_span.end.max(span.end)),
+        _ => stmt.span().end,
+    }
+}
+
 /// Unescape a string literal (handle \n, \t, \", \\)
 fn unescape_string(s: &str) -> String {
     let mut result = String::with_capacity(s.len());
@@ -529,3 +1160,38 @@ fn unescape_string(s: &str) -> String {
 
     result
 }
+
+/// List `items` as alternatives: `a`, `a or b`, `a, b, or c`.
+fn one_of(items: &[&str]) -> String {
+    match items {
+        [] => String::new(),
+        [only] => only.to_string(),
+        [first, second] => format!("{} or {}", first, second),
+        [rest @ .., last] => format!("{}, or {}", rest.join(", "), last),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scanner::Scanner;
+
+    #[test]
+    fn cancel_mid_parse_stops_early() {
+        // Every line is a parse error, so the error count shows how far it got
+        let source = "temp = 1\n".repeat(10_000);
+        let cancel = AtomicBool::new(false);
+        let mut seen = 0;
+        let tokens = Scanner::new(&source).tokens().inspect(|_| {
+            seen += 1;
+            if seen == 500 {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        });
+
+        let (_, errors) = Parser::new(tokens).with_cancel(&cancel).parse_recover();
+
+        assert!(!errors.is_empty());
+        assert!(errors.len() < 1_000, "parsed {} lines", errors.len());
+    }
+}
@@ -0,0 +1,40 @@
+//! Seeded random numbers for `random(min, max)`.
+//!
+//! A script must replay identically from the same seed, in tests and across
+//! dry-run branches, so the generator is a small fixed algorithm (SplitMix64)
+//! whose output cannot change with a dependency update.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub(crate) fn seeded(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// A generator with an unpredictable seed, for runtimes that were not
+    /// given one.
+    pub(crate) fn from_entropy() -> Self {
+        Self::seeded(RandomState::new().build_hasher().finish())
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A whole number from `low` to `high`, inclusive. `low` must not exceed `high`.
+    pub(crate) fn range(&mut self, low: i64, high: i64) -> i64 {
+        let span = (i128::from(high) - i128::from(low) + 1) as u128;
+        let offset = (u128::from(self.next_u64()) * span) >> 64;
+        (i128::from(low) + offset as i128) as i64
+    }
+}
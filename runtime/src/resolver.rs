@@ -1,6 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::ast::{Choice, ExternDeclData, NodeId, Script, Stmt, TextPart, VarBindingData};
+use crate::ast::{
+    Builtin, Choice, CompoundAssignData, Expr, ExternDeclData, Literal, NodeId, Script, Stmt,
+    TextPart, VarBindingData,
+};
 use crate::diagnostic::{Diagnostic, DiagnosticContext, IntoDiagnostic};
 use crate::token::Span;
 
@@ -9,6 +13,16 @@ pub enum SemanticError {
     UndefinedVariable {
         name: String,
         span: Span,
+        /// Names referenced just before, most recent first, which
+        /// suggestions favor
+        recent: Vec<String>,
+    },
+    /// A `set` targets a name that was never declared.
+    AssignmentToUndefined {
+        name: String,
+        span: Span,
+        /// Names referenced just before, most recent first
+        recent: Vec<String>,
     },
     Shadowing {
         name: String,
@@ -19,20 +33,203 @@ pub enum SemanticError {
         name: String,
         span: Span,
     },
+    AssignmentToConst {
+        name: String,
+        span: Span,
+        declared: Span,
+    },
+    /// A choice set with no choices would leave the player stuck.
+    EmptyChoiceSet {
+        span: Span,
+    },
+    /// A `const` whose value is not a literal, so it cannot be inlined.
+    NonLiteralConst {
+        name: String,
+        span: Span,
+    },
+    /// A call to a function that is not built in.
+    UnknownFunction {
+        name: String,
+        span: Span,
+    },
+    /// A built-in function called with the wrong number of arguments.
+    WrongArgumentCount {
+        name: String,
+        expected: usize,
+        found: usize,
+        span: Span,
+    },
+    /// Warning: a choice whose text is empty or only whitespace.
+    EmptyChoiceText {
+        span: Span,
+    },
+    /// Warning: a choice with no branch, in a set where other choices have
+    /// one, so choosing it does nothing.
+    EmptyChoiceBranch {
+        span: Span,
+    },
+    /// Warning: a statement that follows a diverging statement in the same block.
+    UnreachableStatement {
+        span: Span,
+    },
+    /// Warning: a `save` variable that is assigned but never read, so its
+    /// value is persisted without ever being used.
+    WriteOnlySave {
+        name: String,
+        span: Span,
+        /// The first assignment to it
+        write: Span,
+    },
+    /// Warning: an assignment of a variable to itself, `set gold = gold`.
+    SelfAssignment {
+        name: String,
+        span: Span,
+    },
+    /// Warning: a line whose text, not counting interpolations, is longer
+    /// than the limit set with `with_max_line_chars`.
+    LongLine {
+        limit: usize,
+        length: usize,
+        span: Span,
+    },
+    /// Warning: two choices in the same set have identical static text.
+    DuplicateChoice {
+        text: String,
+        span: Span,
+        first: Span,
+    },
+    /// A `goto` names a label that is not defined anywhere in the script.
+    UndefinedLabel {
+        name: String,
+        span: Span,
+        /// Every label in the script, for "did you mean?" suggestions
+        known_labels: Vec<String>,
+    },
+    DuplicateLabel {
+        name: String,
+        span: Span,
+        original: Span,
+    },
+    /// Labels may only appear at the top level, not inside a choice branch.
+    NestedLabel {
+        name: String,
+        span: Span,
+    },
+    /// A forward `goto` would jump over a variable declaration, leaving the
+    /// variable uninitialized at the label.
+    GotoSkipsDeclaration {
+        label: String,
+        span: Span,
+        declaration: Span,
+    },
+    /// A `goto` loops back to itself without showing a line or choice,
+    /// which would hang the runtime.
+    EmptyLoop {
+        label: String,
+        span: Span,
+    },
+    /// Includes may only appear at the top level, not inside a choice branch.
+    NestedInclude {
+        path: String,
+        span: Span,
+    },
+    /// An include reached the resolver without being expanded, because the
+    /// script was compiled without a source provider.
+    UnresolvedInclude {
+        path: String,
+        span: Span,
+    },
+}
+
+impl SemanticError {
+    /// Whether this is a warning rather than an error.
+    ///
+    /// Warnings are reported alongside errors but never stop compilation.
+    pub fn is_warning(&self) -> bool {
+        matches!(
+            self,
+            SemanticError::DuplicateChoice { .. }
+                | SemanticError::EmptyChoiceText { .. }
+                | SemanticError::EmptyChoiceBranch { .. }
+                | SemanticError::UnreachableStatement { .. }
+                | SemanticError::WriteOnlySave { .. }
+                | SemanticError::SelfAssignment { .. }
+                | SemanticError::LongLine { .. }
+        )
+    }
+
+    /// The primary span of this error.
+    pub fn span(&self) -> Span {
+        match self {
+            SemanticError::UndefinedVariable { span, .. }
+            | SemanticError::AssignmentToUndefined { span, .. }
+            | SemanticError::Shadowing { span, .. }
+            | SemanticError::AssignmentToExtern { span, .. }
+            | SemanticError::AssignmentToConst { span, .. }
+            | SemanticError::EmptyChoiceSet { span }
+            | SemanticError::NonLiteralConst { span, .. }
+            | SemanticError::UnknownFunction { span, .. }
+            | SemanticError::WrongArgumentCount { span, .. }
+            | SemanticError::EmptyChoiceText { span }
+            | SemanticError::EmptyChoiceBranch { span }
+            | SemanticError::UnreachableStatement { span }
+            | SemanticError::WriteOnlySave { span, .. }
+            | SemanticError::SelfAssignment { span, .. }
+            | SemanticError::LongLine { span, .. }
+            | SemanticError::DuplicateChoice { span, .. }
+            | SemanticError::UndefinedLabel { span, .. }
+            | SemanticError::DuplicateLabel { span, .. }
+            | SemanticError::NestedLabel { span, .. }
+            | SemanticError::GotoSkipsDeclaration { span, .. }
+            | SemanticError::EmptyLoop { span, .. }
+            | SemanticError::NestedInclude { span, .. }
+            | SemanticError::UnresolvedInclude { span, .. } => *span,
+        }
+    }
 }
 
 impl IntoDiagnostic for SemanticError {
     fn into_diagnostic(self, ctx: &DiagnosticContext) -> Diagnostic {
         match self {
-            SemanticError::UndefinedVariable { name, span } => {
+            SemanticError::UndefinedVariable { name, span, recent } => {
                 let mut diag = Diagnostic::error(
                     format!("undefined variable '{}'", name),
                     span,
                     "not defined in this scope",
                 );
 
-                // Add "did you mean?" suggestion using fuzzy matching
-                if let Some(similar) = ctx.find_similar_variable(&name) {
+                // Add "did you mean?" suggestions using fuzzy matching, best first
+                let similar = ctx.find_similar_variables_near(&name, &recent);
+                for similar in &similar {
+                    diag = diag.with_suggestion(
+                        format!("did you mean '{}'?", similar),
+                        span,
+                        similar.to_string(),
+                    );
+                }
+
+                // Nothing like it is declared, so it is likely a host value
+                if similar.is_empty() {
+                    diag = diag.with_note(format!(
+                        "if this is provided by the game, declare it with `extern {}`",
+                        name
+                    ));
+                }
+
+                diag
+            }
+            SemanticError::AssignmentToUndefined { name, span, recent } => {
+                let mut diag = Diagnostic::error(
+                    format!("cannot assign to undefined variable '{}'", name),
+                    span,
+                    "not declared",
+                )
+                .with_note(format!(
+                    "declare it with `save {0} = ...` or `temp {0} = ...` first",
+                    name
+                ));
+
+                for similar in ctx.find_similar_variables_near(&name, &recent) {
                     diag = diag.with_suggestion(
                         format!("did you mean '{}'?", similar),
                         span,
@@ -62,10 +259,199 @@ impl IntoDiagnostic for SemanticError {
                 "Extern variables are provided by the host game and cannot be modified by scripts",
             )
             .with_note("Use 'save' or 'temp' to declare a mutable variable instead"),
+            SemanticError::AssignmentToConst {
+                name,
+                span,
+                declared,
+            } => Diagnostic::error(
+                format!("cannot assign to const '{}'", name),
+                span,
+                "const values cannot change",
+            )
+            .with_secondary(declared, "declared as const here")
+            .with_note("Use 'save' or 'temp' to declare a mutable variable instead"),
+            SemanticError::EmptyChoiceSet { span } => Diagnostic::error(
+                "choice set has no choices",
+                span,
+                "expected at least one choice here",
+            )
+            .with_note(
+                "The player would have nothing to select and the dialogue could not continue",
+            ),
+            SemanticError::NonLiteralConst { name, span } => Diagnostic::error(
+                format!("const '{}' must be a literal value", name),
+                span,
+                "computed value",
+            )
+            .with_note(
+                "Consts are inlined where they are used; use `temp` or `save` for computed values",
+            ),
+            SemanticError::UnknownFunction { name, span } => {
+                let available: Vec<&str> = Builtin::ALL.iter().map(|b| b.name()).collect();
+                Diagnostic::error(
+                    format!("unknown function '{}'", name),
+                    span,
+                    "not a built-in function",
+                )
+                .with_note(format!("Available functions: {}", available.join(", ")))
+            }
+            SemanticError::WrongArgumentCount {
+                name,
+                expected,
+                found,
+                span,
+            } => Diagnostic::error(
+                format!(
+                    "'{}' takes {} argument{}, found {}",
+                    name,
+                    expected,
+                    if expected == 1 { "" } else { "s" },
+                    found
+                ),
+                span,
+                "called here",
+            ),
+            SemanticError::EmptyChoiceText { span } => Diagnostic::warning(
+                "choice has no text",
+                span,
+                "this choice would show up blank",
+            )
+            .with_note("Add text after the '-' marker, or remove the choice"),
+            SemanticError::EmptyChoiceBranch { span } => Diagnostic::warning(
+                "choice branch is empty",
+                span,
+                "choosing this moves straight on",
+            )
+            .with_note("Indent what should happen under the choice; the other choices in the set have a branch"),
+            SemanticError::UnreachableStatement { span } => Diagnostic::warning(
+                "unreachable statement",
+                span,
+                "this statement can never run",
+            )
+            .with_note("The statement before it always jumps elsewhere"),
+            SemanticError::WriteOnlySave { name, span, write } => Diagnostic::warning(
+                format!("save variable '{}' is never read", name),
+                span,
+                "declared here",
+            )
+            .with_secondary(write, "assigned here")
+            .with_note(
+                "Its value goes into every save file but nothing uses it; read it or remove it",
+            ),
+            SemanticError::SelfAssignment { name, span } => Diagnostic::warning(
+                "assignment has no effect",
+                span,
+                format!("'{}' is assigned to itself", name),
+            )
+            .with_note(format!(
+                "To change it, use a compound assignment such as 'set {} += 1'",
+                name
+            )),
+            SemanticError::LongLine {
+                limit,
+                length,
+                span,
+            } => Diagnostic::warning(
+                format!(
+                    "line exceeds {} characters and may overflow the dialogue box",
+                    limit
+                ),
+                span,
+                format!("{} characters", length),
+            )
+            .with_note("Interpolated values are not counted, so the shown line may be longer still")
+            .with_note("Split the line in two, or shorten it"),
+            SemanticError::DuplicateChoice { text, span, first } => Diagnostic::warning(
+                format!("duplicate choice '{}'", text),
+                span,
+                "same text as an earlier choice",
+            )
+            .with_secondary(first, "first used here")
+            .with_note("Players cannot tell identical choices apart"),
+            SemanticError::UndefinedLabel {
+                name,
+                span,
+                known_labels,
+            } => {
+                let mut diag = Diagnostic::error(
+                    format!("undefined label '{}'", name),
+                    span,
+                    "no label with this name",
+                );
+                for (similar, _) in ctx
+                    .matcher
+                    .find_similar(&name, &known_labels)
+                    .into_iter()
+                    .take(ctx.max_suggestions)
+                {
+                    diag = diag.with_suggestion(
+                        format!("did you mean '{}'?", similar),
+                        span,
+                        similar.to_string(),
+                    );
+                }
+                diag
+            }
+            SemanticError::DuplicateLabel {
+                name,
+                span,
+                original,
+            } => Diagnostic::error(
+                format!("label '{}' is already defined", name),
+                span,
+                "redefined here",
+            )
+            .with_secondary(original, "first defined here")
+            .with_note("Every goto must lead to exactly one place"),
+            SemanticError::NestedLabel { name, span } => Diagnostic::error(
+                format!("label '{}' is inside a choice branch", name),
+                span,
+                "labels must be at the top level",
+            )
+            .with_note("Move the label out of the choice branch and goto it from there"),
+            SemanticError::GotoSkipsDeclaration {
+                label,
+                span,
+                declaration,
+            } => Diagnostic::error(
+                format!("goto '{}' skips a variable declaration", label),
+                span,
+                "jumps over a declaration",
+            )
+            .with_secondary(declaration, "this declaration would never run")
+            .with_note("Move the declaration above the goto, or the label above the declaration"),
+            SemanticError::EmptyLoop { label, span } => Diagnostic::error(
+                format!("goto '{}' loops forever", label),
+                span,
+                "loops back here without showing anything",
+            )
+            .with_note("Add a line or choice between the label and the goto"),
+            SemanticError::NestedInclude { path, span } => Diagnostic::error(
+                format!("include '{}' is inside a choice branch", path),
+                span,
+                "includes must be at the top level",
+            )
+            .with_note("Move the include to the top level of the file"),
+            SemanticError::UnresolvedInclude { path, span } => Diagnostic::error(
+                format!("cannot include '{}'", path),
+                span,
+                "no source provider to load this file",
+            )
+            .with_note("Compile with compile_with_includes to load included files"),
         }
     }
 }
 
+/// How a variable was declared.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DeclKind {
+    Temp,
+    Save,
+    Extern,
+    Const,
+}
+
 /// Symbol table built during semantic analysis.
 /// Maps each variable usage (by NodeId) to its storage location.
 #[derive(Debug, Default)]
@@ -76,6 +462,40 @@ pub struct SymbolTable {
     pub save_bindings: HashMap<NodeId, String>,
     /// Extern variable bindings: NodeId -> variable name
     pub extern_bindings: HashMap<NodeId, String>,
+    /// Const references: NodeId -> value to inline
+    pub const_bindings: HashMap<NodeId, Literal>,
+    /// Where each variable is declared: name -> (kind, declaration span).
+    /// Temps declared in sibling choice branches keep the first declaration.
+    pub declarations: HashMap<String, (DeclKind, Span)>,
+    /// The trailing `// comment` of each documented declaration: name -> text.
+    pub docs: HashMap<String, String>,
+    /// Warnings found during analysis (analysis still succeeded)
+    pub warnings: Vec<SemanticError>,
+}
+
+impl SymbolTable {
+    /// The doc comment of `name`'s declaration, for hover text.
+    pub fn doc(&self, name: &str) -> Option<&str> {
+        self.docs.get(name).map(String::as_str)
+    }
+
+    /// Every declared name of any kind, in declaration order.
+    pub fn all_variables(&self) -> Vec<String> {
+        let mut declared: Vec<_> = self.declarations.iter().collect();
+        declared.sort_by_key(|(_, (_, span))| span.start);
+        declared.into_iter().map(|(name, _)| name.clone()).collect()
+    }
+
+    /// Every `extern` name, in declaration order. The host must provide these.
+    pub fn required_externs(&self) -> Vec<String> {
+        let mut externs: Vec<_> = self
+            .declarations
+            .iter()
+            .filter(|(_, (kind, _))| *kind == DeclKind::Extern)
+            .collect();
+        externs.sort_by_key(|(_, (_, span))| span.start);
+        externs.into_iter().map(|(name, _)| name.clone()).collect()
+    }
 }
 
 /// Information about a declared temp variable
@@ -89,6 +509,16 @@ struct VarInfo {
 #[derive(Debug)]
 struct SaveVarInfo {
     span: Span, // for error messages (no slot - uses external storage)
+    read: bool,
+    /// First assignment, for the write-only warning
+    first_write: Option<Span>,
+}
+
+/// Information about a declared const
+#[derive(Debug)]
+struct ConstVarInfo {
+    span: Span, // for error messages (no slot - value is inlined)
+    value: Literal,
 }
 
 /// Information about a declared extern variable
@@ -108,10 +538,14 @@ struct Scope {
 #[derive(Debug)]
 pub struct Resolver<'a> {
     ast: &'a Script,
-    /// Temp variable scopes (block-scoped)
+    /// Temp variable scopes (block-scoped). Only the scopes enclosing the
+    /// statement being resolved are on the stack; a sibling branch's scope
+    /// is popped before the next branch is pushed.
     scopes: Vec<Scope>,
     /// Save variables (file-global)
     save_vars: HashMap<String, SaveVarInfo>,
+    /// Const variables (file-global, read-only, inlined)
+    const_vars: HashMap<String, ConstVarInfo>,
     /// Extern variables (file-global, read-only)
     extern_vars: HashMap<String, ExternVarInfo>,
     next_slot: usize,
@@ -121,7 +555,24 @@ pub struct Resolver<'a> {
     save_bindings: HashMap<NodeId, String>,
     /// Extern variable bindings: NodeId -> name
     extern_bindings: HashMap<NodeId, String>,
+    /// Const references: NodeId -> value
+    const_bindings: HashMap<NodeId, Literal>,
+    /// Declaration sites: name -> (kind, span)
+    declarations: HashMap<String, (DeclKind, Span)>,
+    /// Doc comments: name -> text
+    docs: HashMap<String, String>,
+    /// Top-level labels: name -> (statement index, span)
+    labels: HashMap<String, (usize, Span)>,
+    /// Index of the top-level statement being resolved
+    top_level_index: usize,
     errors: Vec<SemanticError>,
+    warnings: Vec<SemanticError>,
+    /// The last few names referenced, most recent first, for suggestions
+    recent: VecDeque<String>,
+    /// Warn on lines with more characters of text than this
+    max_line_chars: Option<usize>,
+    /// Stop before the next top-level statement once this is set
+    cancel: Option<&'a AtomicBool>,
 }
 
 impl<'a> Resolver<'a> {
@@ -133,30 +584,81 @@ impl<'a> Resolver<'a> {
                 start_slot: 0,
             }], // Start with global scope
             save_vars: HashMap::new(),
+            const_vars: HashMap::new(),
             extern_vars: HashMap::new(),
             next_slot: 0,
             bindings: HashMap::new(),
             save_bindings: HashMap::new(),
             extern_bindings: HashMap::new(),
+            const_bindings: HashMap::new(),
+            declarations: HashMap::new(),
+            docs: HashMap::new(),
+            labels: HashMap::new(),
+            top_level_index: 0,
             errors: Vec::new(),
+            warnings: Vec::new(),
+            recent: VecDeque::new(),
+            max_line_chars: None,
+            cancel: None,
         }
     }
 
+    /// Stop resolving before the next top-level statement once `cancel` is
+    /// set. The whole-script checks (unreachable code, empty loops, write-only
+    /// saves) are skipped then, since they would misfire on a partial walk.
+    pub fn with_cancel(mut self, cancel: &'a AtomicBool) -> Self {
+        self.cancel = Some(cancel);
+        self
+    }
+
+    /// Warn on lines with more than `max` characters of text, for dialogue
+    /// boxes of a fixed width. Interpolations are not counted, since their
+    /// values are not known. Off (`None`) by default.
+    pub fn with_max_line_chars(mut self, max: Option<usize>) -> Self {
+        self.max_line_chars = max;
+        self
+    }
+
+    fn cancelled(&self) -> bool {
+        self.cancel
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+
     pub fn analyze(mut self) -> Result<SymbolTable, (Vec<SemanticError>, Vec<String>)> {
+        // Labels are collected first so a goto can jump forward
+        self.collect_labels();
+
         // Walk the AST
-        for stmt in &self.ast.statements {
+        for (index, stmt) in self.ast.statements.iter().enumerate() {
+            if self.cancelled() {
+                break;
+            }
+            self.top_level_index = index;
             self.resolve_stmt(stmt);
         }
+        if !self.cancelled() {
+            self.check_unreachable(&self.ast.statements);
+            self.check_empty_loops();
+            self.check_write_only_saves();
+        }
 
         if self.errors.is_empty() {
             Ok(SymbolTable {
                 bindings: self.bindings,
                 save_bindings: self.save_bindings,
                 extern_bindings: self.extern_bindings,
+                const_bindings: self.const_bindings,
+                declarations: self.declarations,
+                docs: self.docs,
+                warnings: self.warnings,
             })
         } else {
+            // Report warnings alongside errors, in source order
             let known_vars = self.known_variables();
-            Err((self.errors, known_vars))
+            let mut diagnostics = self.errors;
+            diagnostics.append(&mut self.warnings);
+            diagnostics.sort_by_key(|e| e.span().start);
+            Err((diagnostics, known_vars))
         }
     }
 
@@ -172,6 +674,9 @@ impl<'a> Resolver<'a> {
         // Collect save variables
         vars.extend(self.save_vars.keys().cloned());
 
+        // Collect const variables
+        vars.extend(self.const_vars.keys().cloned());
+
         // Collect extern variables
         vars.extend(self.extern_vars.keys().cloned());
 
@@ -180,26 +685,136 @@ impl<'a> Resolver<'a> {
 
     fn resolve_stmt(&mut self, stmt: &Stmt) {
         match stmt {
-            Stmt::TempDecl(VarBindingData { id, name, span, .. }) => {
+            Stmt::TempDecl(VarBindingData {
+                id,
+                name,
+                value,
+                span,
+                doc,
+            }) => {
+                // The value cannot see the variable it initializes
+                self.resolve_expr(value);
                 self.declare_temp(*id, name, *span);
+                self.record_doc(name, doc);
             }
-            Stmt::SaveDecl(VarBindingData { id, name, span, .. }) => {
+            Stmt::SaveDecl(VarBindingData {
+                id,
+                name,
+                value,
+                span,
+                doc,
+            }) => {
+                self.resolve_expr(value);
                 self.declare_save(*id, name, *span);
+                self.record_doc(name, doc);
             }
-            Stmt::ExternDecl(ExternDeclData { id, name, span }) => {
+            Stmt::ConstDecl(VarBindingData {
+                name,
+                value,
+                span,
+                doc,
+                ..
+            }) => {
+                self.record_doc(name, doc);
+                let literal = match value {
+                    Expr::Literal { value, .. } => value.clone(),
+                    _ => {
+                        self.errors.push(SemanticError::NonLiteralConst {
+                            name: name.clone(),
+                            span: value.span(),
+                        });
+                        // Still declare it, so uses are not reported as undefined
+                        Literal::Bool(false)
+                    }
+                };
+                self.declare_const(name, &literal, *span);
+            }
+            Stmt::ExternDecl(ExternDeclData {
+                id,
+                name,
+                span,
+                doc,
+            }) => {
                 self.declare_extern(*id, name, *span);
+                self.record_doc(name, doc);
+            }
+            Stmt::Assignment(VarBindingData {
+                id,
+                name,
+                value,
+                span,
+                ..
+            }) => {
+                let errors = self.errors.len();
+                self.resolve_expr(value);
+                self.resolve_reference(*id, name, *span, true); // for_write = true
+                // Only the direct `set x = x`; an error already covers a
+                // name that does not resolve
+                if matches!(value, Expr::Var { name: source, .. } if source == name)
+                    && self.errors.len() == errors
+                {
+                    self.warnings.push(SemanticError::SelfAssignment {
+                        name: name.clone(),
+                        span: *span,
+                    });
+                }
             }
-            Stmt::Assignment(VarBindingData { id, name, span, .. }) => {
+            Stmt::CompoundAssignment(CompoundAssignData {
+                id,
+                name,
+                value,
+                span,
+                ..
+            }) => {
+                self.resolve_expr(value);
+                // The target is read and written through the same binding
                 self.resolve_reference(*id, name, *span, true); // for_write = true
             }
-            Stmt::Line { parts, .. } => {
+            Stmt::Line { parts, span, .. } => {
                 self.resolve_text_parts(parts);
+                self.check_line_length(parts, *span);
+            }
+            Stmt::Label { name, span } => {
+                if self.scopes.len() > 1 {
+                    self.errors.push(SemanticError::NestedLabel {
+                        name: name.clone(),
+                        span: *span,
+                    });
+                }
+            }
+            Stmt::Goto {
+                label, label_span, ..
+            } => {
+                self.resolve_goto(label, *label_span);
             }
-            Stmt::ChoiceSet { choices } => {
+            Stmt::Wait { .. } | Stmt::End { .. } => {}
+            Stmt::Include { path, span, .. } => {
+                // Top-level includes are spliced away before resolution
+                let path = path.clone();
+                let span = *span;
+                if self.scopes.len() > 1 {
+                    self.errors
+                        .push(SemanticError::NestedInclude { path, span });
+                } else {
+                    self.errors
+                        .push(SemanticError::UnresolvedInclude { path, span });
+                }
+            }
+            Stmt::ChoiceSet { choices, span } => {
+                if choices.is_empty() {
+                    self.errors
+                        .push(SemanticError::EmptyChoiceSet { span: *span });
+                }
                 // Resolve variable references in choice text
                 for choice in choices {
                     self.resolve_text_parts(&choice.parts);
+                    if static_text(&choice.parts).is_some_and(|text| text.trim().is_empty()) {
+                        self.warnings
+                            .push(SemanticError::EmptyChoiceText { span: choice.span });
+                    }
                 }
+                self.check_duplicate_choices(choices);
+                self.check_empty_branches(choices);
                 // Each choice branch gets its own scope
                 for choice in choices {
                     self.resolve_choice_branch(choice);
@@ -208,18 +823,273 @@ impl<'a> Resolver<'a> {
         }
     }
 
+    /// Warn when two fully static choices in the same set have the same text.
+    /// Choices with interpolation may differ at runtime, so they are skipped.
+    fn check_duplicate_choices(&mut self, choices: &[Choice]) {
+        let mut seen: HashMap<String, Span> = HashMap::new();
+        for choice in choices {
+            let Some(text) = static_text(&choice.parts) else {
+                continue;
+            };
+            if text.trim().is_empty() {
+                // Already reported as an empty choice
+                continue;
+            }
+            match seen.get(&text) {
+                Some(&first) => self.warnings.push(SemanticError::DuplicateChoice {
+                    text,
+                    span: choice.span,
+                    first,
+                }),
+                None => {
+                    seen.insert(text, choice.span);
+                }
+            }
+        }
+    }
+
+    /// Warn about choices without a branch when others in the set have one.
+    ///
+    /// A set where no choice has a branch is a menu that carries on to the
+    /// same place either way, so it is not reported.
+    fn check_empty_branches(&mut self, choices: &[Choice]) {
+        if choices.iter().all(|choice| choice.nested.is_empty()) {
+            return;
+        }
+        for choice in choices.iter().filter(|choice| choice.nested.is_empty()) {
+            let span = match (choice.parts.first(), choice.parts.last()) {
+                (Some(first), Some(last)) => Span {
+                    start: first.span().start,
+                    end: last.span().end,
+                },
+                _ => choice.span,
+            };
+            self.warnings
+                .push(SemanticError::EmptyChoiceBranch { span });
+        }
+    }
+
+    /// Warn if the literal text of a line is over the length limit.
+    fn check_line_length(&mut self, parts: &[TextPart], span: Span) {
+        let Some(limit) = self.max_line_chars else {
+            return;
+        };
+        let length = parts
+            .iter()
+            .map(|part| match part {
+                TextPart::Literal { text, .. } => text.chars().count(),
+                TextPart::VarRef { .. } | TextPart::Expr { .. } => 0,
+            })
+            .sum();
+        if length > limit {
+            self.warnings.push(SemanticError::LongLine {
+                limit,
+                length,
+                span,
+            });
+        }
+    }
+
+    /// Resolve a branch in a scope of its own, so temps declared in one
+    /// branch are invisible to its siblings.
     fn resolve_choice_branch(&mut self, choice: &Choice) {
         self.push_scope();
         for stmt in &choice.nested {
             self.resolve_stmt(stmt);
         }
+        self.check_unreachable(&choice.nested);
         self.pop_scope();
     }
 
+    /// Warn on the first statement in a block that follows a diverging one.
+    /// Later statements up to the next label are covered by that one warning,
+    /// since a label can be reached by a goto.
+    fn check_unreachable(&mut self, block: &[Stmt]) {
+        let mut unreachable = false;
+        let mut reported = false;
+        for stmt in block {
+            if matches!(stmt, Stmt::Label { .. }) {
+                unreachable = false;
+                reported = false;
+                continue;
+            }
+            if unreachable && !reported {
+                self.warnings
+                    .push(SemanticError::UnreachableStatement { span: stmt.span() });
+                reported = true;
+            }
+            unreachable |= stmt.diverges();
+        }
+    }
+
+    /// Record every top-level label, reporting duplicates.
+    fn collect_labels(&mut self) {
+        for (index, stmt) in self.ast.statements.iter().enumerate() {
+            if let Stmt::Label { name, span } = stmt {
+                if let Some(&(_, original)) = self.labels.get(name) {
+                    self.errors.push(SemanticError::DuplicateLabel {
+                        name: name.clone(),
+                        span: *span,
+                        original,
+                    });
+                } else {
+                    self.labels.insert(name.clone(), (index, *span));
+                }
+            }
+        }
+    }
+
+    /// Check that a goto's label exists and that the jump does not skip a
+    /// declaration the code after the label may depend on.
+    fn resolve_goto(&mut self, label: &str, span: Span) {
+        let Some(&(target, _)) = self.labels.get(label) else {
+            let mut known_labels: Vec<String> = self.labels.keys().cloned().collect();
+            known_labels.sort();
+            self.errors.push(SemanticError::UndefinedLabel {
+                name: label.to_string(),
+                span,
+                known_labels,
+            });
+            return;
+        };
+
+        // Only forward jumps can skip code; backward jumps re-run it
+        let after = self.top_level_index + 1;
+        let between = self.ast.statements.get(after..target).unwrap_or_default();
+        let skipped = between
+            .iter()
+            .find(|stmt| matches!(stmt, Stmt::TempDecl(_) | Stmt::SaveDecl(_)));
+        if let Some(declaration) = skipped {
+            self.errors.push(SemanticError::GotoSkipsDeclaration {
+                label: label.to_string(),
+                span,
+                declaration: declaration.span(),
+            });
+        }
+    }
+
+    /// Report top-level gotos that can run in a cycle without reaching a line
+    /// or choice. Gotos inside choice branches always follow a pause.
+    fn check_empty_loops(&mut self) {
+        let statements = &self.ast.statements;
+        for (index, stmt) in statements.iter().enumerate() {
+            let Stmt::Goto { label, span, .. } = stmt else {
+                continue;
+            };
+
+            // Follow execution from the label until it pauses, ends, or loops
+            let mut seen = HashSet::new();
+            let mut current = self.labels.get(label).map(|&(target, _)| target);
+            let mut loops = false;
+            while let Some(at) = current {
+                if !seen.insert(at) {
+                    break;
+                }
+                current = match statements.get(at) {
+                    None
+                    | Some(
+                        Stmt::Line { .. }
+                        | Stmt::ChoiceSet { .. }
+                        | Stmt::Wait { .. }
+                        | Stmt::End { .. },
+                    ) => None,
+                    Some(Stmt::Goto { .. }) if at == index => {
+                        loops = true;
+                        None
+                    }
+                    Some(Stmt::Goto { label, .. }) => {
+                        self.labels.get(label).map(|&(target, _)| target)
+                    }
+                    Some(_) => Some(at + 1),
+                };
+            }
+
+            // Report each cycle once, at its first goto
+            let first_goto = seen
+                .iter()
+                .filter(|&&at| matches!(statements.get(at), Some(Stmt::Goto { .. })))
+                .min()
+                .copied();
+            if loops && first_goto == Some(index) {
+                self.errors.push(SemanticError::EmptyLoop {
+                    label: label.clone(),
+                    span: *span,
+                });
+            }
+        }
+    }
+
+    /// Warn about save variables that are assigned but never read. A compound
+    /// assignment only feeds the variable back into itself, so it is not a read.
+    fn check_write_only_saves(&mut self) {
+        let mut write_only: Vec<_> = self
+            .save_vars
+            .iter()
+            .filter(|(_, info)| !info.read)
+            .filter_map(|(name, info)| {
+                info.first_write.map(|write| SemanticError::WriteOnlySave {
+                    name: name.clone(),
+                    span: info.span,
+                    write,
+                })
+            })
+            .collect();
+        write_only.sort_by_key(|warning| warning.span().start);
+        self.warnings.extend(write_only);
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal { .. } => {}
+            Expr::Var { id, name, span } => {
+                self.resolve_reference(*id, name, *span, false); // for_write = false
+            }
+            Expr::Unary { operand, .. } => self.resolve_expr(operand),
+            Expr::Binary { left, right, .. } | Expr::Logical { left, right, .. } => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Call {
+                name,
+                name_span,
+                args,
+                span,
+            } => {
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+                match Builtin::from_name(name) {
+                    None => self.errors.push(SemanticError::UnknownFunction {
+                        name: name.clone(),
+                        span: *name_span,
+                    }),
+                    Some(builtin) if builtin.arity() != args.len() => {
+                        self.errors.push(SemanticError::WrongArgumentCount {
+                            name: name.clone(),
+                            expected: builtin.arity(),
+                            found: args.len(),
+                            span: *span,
+                        })
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+    }
+
     fn resolve_text_parts(&mut self, parts: &[TextPart]) {
         for part in parts {
-            if let TextPart::VarRef { id, name, span } = part {
-                self.resolve_reference(*id, name, *span, false); // for_write = false
+            match part {
+                TextPart::Literal { .. } => {}
+                TextPart::VarRef {
+                    id,
+                    name,
+                    name_span,
+                    ..
+                } => {
+                    self.resolve_reference(*id, name, *name_span, false); // for_write = false
+                }
+                TextPart::Expr { expr, .. } => self.resolve_expr(expr),
             }
         }
     }
@@ -238,12 +1108,15 @@ impl<'a> Resolver<'a> {
         }
     }
 
-    /// Check if a variable name conflicts with save or extern variables.
+    /// Check if a variable name conflicts with save, const, or extern variables.
     /// Returns the span of the conflicting declaration, if any.
     fn find_global_conflict(&self, name: &str) -> Option<Span> {
         if let Some(info) = self.save_vars.get(name) {
             return Some(info.span);
         }
+        if let Some(info) = self.const_vars.get(name) {
+            return Some(info.span);
+        }
         if let Some(info) = self.extern_vars.get(name) {
             return Some(info.span);
         }
@@ -267,7 +1140,7 @@ impl<'a> Resolver<'a> {
 
     /// Declare a temp variable in the current (innermost) scope
     fn declare_temp(&mut self, id: NodeId, name: &str, span: Span) {
-        // Check for conflict with save/extern variables (file-global)
+        // Check for conflict with save/const/extern variables (file-global)
         if let Some(original) = self.find_global_conflict(name) {
             self.errors.push(SemanticError::Shadowing {
                 name: name.to_string(),
@@ -309,11 +1182,12 @@ impl<'a> Resolver<'a> {
 
         // Record binding for this declaration
         self.bindings.insert(id, slot);
+        self.record_declaration(name, DeclKind::Temp, span);
     }
 
     /// Declare a save variable (file-global, uses external storage)
     fn declare_save(&mut self, id: NodeId, name: &str, span: Span) {
-        // Check for conflict with save/extern variables (file-global)
+        // Check for conflict with save/const/extern variables (file-global)
         if let Some(original) = self.find_global_conflict(name) {
             self.errors.push(SemanticError::Shadowing {
                 name: name.to_string(),
@@ -334,16 +1208,57 @@ impl<'a> Resolver<'a> {
         }
 
         // Register the save variable (file-global)
-        self.save_vars
-            .insert(name.to_string(), SaveVarInfo { span });
+        self.save_vars.insert(
+            name.to_string(),
+            SaveVarInfo {
+                span,
+                read: false,
+                first_write: None,
+            },
+        );
 
         // Record binding for this declaration
         self.save_bindings.insert(id, name.to_string());
+        self.record_declaration(name, DeclKind::Save, span);
+    }
+
+    /// Declare a const (file-global, read-only, value inlined at each use)
+    fn declare_const(&mut self, name: &str, value: &Literal, span: Span) {
+        // Check for conflict with save/const/extern variables (file-global)
+        if let Some(original) = self.find_global_conflict(name) {
+            self.errors.push(SemanticError::Shadowing {
+                name: name.to_string(),
+                span,
+                original,
+            });
+            return;
+        }
+
+        // Check for conflict with any temp variable in any scope
+        if let Some(original) = self.find_temp_conflict(name, self.scopes.iter()) {
+            self.errors.push(SemanticError::Shadowing {
+                name: name.to_string(),
+                span,
+                original,
+            });
+            return;
+        }
+
+        // Register the const (file-global)
+        // Note: No binding recorded for the declaration itself - only for references
+        self.const_vars.insert(
+            name.to_string(),
+            ConstVarInfo {
+                span,
+                value: value.clone(),
+            },
+        );
+        self.record_declaration(name, DeclKind::Const, span);
     }
 
     /// Declare an extern variable (file-global, read-only, host-provided)
     fn declare_extern(&mut self, _id: NodeId, name: &str, span: Span) {
-        // Check for conflict with save/extern variables (file-global)
+        // Check for conflict with save/const/extern variables (file-global)
         if let Some(original) = self.find_global_conflict(name) {
             self.errors.push(SemanticError::Shadowing {
                 name: name.to_string(),
@@ -367,24 +1282,79 @@ impl<'a> Resolver<'a> {
         // Note: No binding recorded for the declaration itself - only for references
         self.extern_vars
             .insert(name.to_string(), ExternVarInfo { span });
+        self.record_declaration(name, DeclKind::Extern, span);
+    }
+
+    /// Remember where `name` was first declared, for tooling.
+    fn record_declaration(&mut self, name: &str, kind: DeclKind, span: Span) {
+        self.declarations
+            .entry(name.to_string())
+            .or_insert((kind, span));
     }
 
-    /// Resolve a variable reference - search temp scopes, save variables, then extern variables.
-    /// If for_write is true, this is an assignment target and extern variables are disallowed.
+    /// Remember the doc comment of `name`'s first documented declaration.
+    fn record_doc(&mut self, name: &str, doc: &Option<String>) {
+        if let Some(doc) = doc {
+            self.docs
+                .entry(name.to_string())
+                .or_insert_with(|| doc.clone());
+        }
+    }
+
+    /// Resolve a variable reference - search temp scopes, save variables, consts, then extern variables.
+    /// If for_write is true, this is an assignment target and const and extern variables are disallowed.
     fn resolve_reference(&mut self, id: NodeId, name: &str, span: Span, for_write: bool) {
+        if self.bind_reference(id, name, span, for_write) {
+            self.recent.retain(|recent| recent != name);
+            self.recent.push_front(name.to_string());
+            self.recent.truncate(RECENT_NAMES);
+            return;
+        }
+
+        let name = name.to_string();
+        let recent = self.recent.iter().cloned().collect();
+        self.errors.push(if for_write {
+            SemanticError::AssignmentToUndefined { name, span, recent }
+        } else {
+            SemanticError::UndefinedVariable { name, span, recent }
+        });
+    }
+
+    /// Bind a reference to its declaration, returning false if `name` is not
+    /// declared.
+    fn bind_reference(&mut self, id: NodeId, name: &str, span: Span, for_write: bool) -> bool {
         // Check temp scopes first (innermost to outermost)
         for scope in self.scopes.iter().rev() {
             if let Some(var_info) = scope.variables.get(name) {
                 // Record binding for this reference
                 self.bindings.insert(id, var_info.slot);
-                return;
+                return true;
             }
         }
 
         // Check save variables (file-global)
-        if self.save_vars.contains_key(name) {
+        if let Some(info) = self.save_vars.get_mut(name) {
+            if for_write {
+                info.first_write.get_or_insert(span);
+            } else {
+                info.read = true;
+            }
             self.save_bindings.insert(id, name.to_string());
-            return;
+            return true;
+        }
+
+        // Check const variables (file-global, read-only)
+        if let Some(info) = self.const_vars.get(name) {
+            if for_write {
+                self.errors.push(SemanticError::AssignmentToConst {
+                    name: name.to_string(),
+                    span,
+                    declared: info.span,
+                });
+                return true;
+            }
+            self.const_bindings.insert(id, info.value.clone());
+            return true;
         }
 
         // Check extern variables (file-global, read-only)
@@ -394,16 +1364,51 @@ impl<'a> Resolver<'a> {
                     name: name.to_string(),
                     span,
                 });
-                return;
+                return true;
             }
             self.extern_bindings.insert(id, name.to_string());
-            return;
+            return true;
         }
 
         // Not found in any scope
-        self.errors.push(SemanticError::UndefinedVariable {
-            name: name.to_string(),
-            span,
-        });
+        false
+    }
+}
+
+/// How many recently referenced names undefined-variable errors remember.
+const RECENT_NAMES: usize = 5;
+
+/// The text of `parts` if it contains no interpolation.
+fn static_text(parts: &[TextPart]) -> Option<String> {
+    parts
+        .iter()
+        .map(|part| match part {
+            TextPart::Literal { text, .. } => Some(text.as_str()),
+            TextPart::VarRef { .. } | TextPart::Expr { .. } => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_choice_set_is_an_error() {
+        // The parser never produces an empty set from source text, but the
+        // compiler relies on every set having at least one choice.
+        let span = Span { start: 0, end: 1 };
+        let ast = Script {
+            statements: vec![Stmt::ChoiceSet {
+                choices: vec![],
+                span,
+            }],
+        };
+
+        let (errors, _) = Resolver::new(&ast).analyze().unwrap_err();
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(errors[0], SemanticError::EmptyChoiceSet { span: s } if s == span));
+        assert!(!errors[0].is_warning());
     }
 }
@@ -3,7 +3,27 @@ use crate::token::{Span, Token, TokenKind};
 
 #[derive(Debug, Clone)]
 pub enum LexicalError {
-    Unexpected { message: &'static str, span: Span },
+    Unexpected {
+        message: &'static str,
+        span: Span,
+    },
+    /// A control character other than tab, newline, or carriage return, as
+    /// left by pasted content or a binary file opened by mistake
+    ControlCharacter {
+        character: char,
+        span: Span,
+    },
+}
+
+impl LexicalError {
+    /// Where the error is in the source.
+    pub fn span(&self) -> Span {
+        match self {
+            LexicalError::Unexpected { span, .. } | LexicalError::ControlCharacter { span, .. } => {
+                *span
+            }
+        }
+    }
 }
 
 impl IntoDiagnostic for LexicalError {
@@ -14,20 +34,38 @@ impl IntoDiagnostic for LexicalError {
                     Diagnostic::error(format!("lexical error: {}", message), span, message);
 
                 // Add helpful notes for specific error types
-                if message.contains("Tabs not allowed") {
-                    diag = diag.with_note("Bobbin uses spaces for indentation, not tabs");
+                if message.contains("mixed tabs and spaces") {
+                    diag = diag.with_note(
+                        "Indent with tabs or with spaces, whichever the file's first indented line uses",
+                    );
                 } else if message.contains("Unterminated string") {
                     diag = diag.with_note("Strings cannot span multiple lines");
+                } else if message.contains("Unterminated text block") {
+                    diag = diag.with_note("Close the block with '\"\"\"' on its own line");
                 } else if message.contains("Unexpected '}'") {
                     diag = diag.with_suggestion("use '}}' for a literal brace in text", span, "}}");
                 }
 
                 diag
             }
+            LexicalError::ControlCharacter { character, span } => Diagnostic::error(
+                format!(
+                    "lexical error: unexpected control character U+{:04X}",
+                    character as u32
+                ),
+                span,
+                "control character",
+            )
+            .with_note(
+                "Tabs, newlines, and carriage returns are the only control characters allowed",
+            ),
         }
     }
 }
 
+/// Opens and closes a multi-line text block.
+const BLOCK_QUOTES: &str = "\"\"\"";
+
 /// Scanning mode determines what tokens we expect next
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ScanMode {
@@ -35,16 +73,31 @@ enum ScanMode {
     Indentation,
     /// After indentation handled, check for keywords or text
     LineStart,
-    /// After a keyword (temp/save/set), expect: identifier = literal
+    /// After a keyword (temp/save/const/set), expect: identifier = literal.
+    /// After wait, expect: a number
     Declaration,
     /// After extern keyword, expect: identifier only (no initializer)
     ExternDeclaration,
+    /// After goto keyword, expect: label name
+    GotoTarget,
+    /// After include keyword, expect: quoted file path
+    IncludePath,
+    /// After a label's opening "==", expect: label name and optional closing "=="
+    Label,
+    /// After the last token of a keyword statement, expect: an optional comment
+    LineEnd,
     /// Scanning text content (dialogue lines, choice text)
     Text,
     /// Inside an interpolation {}, expect identifier
     Interpolation,
 }
 
+/// Converts Bobbin source into tokens.
+///
+/// Scanning is lazy: tokens are produced one at a time as the iterator returned
+/// by [`Scanner::iter`] is advanced, so tooling such as syntax highlighters can
+/// tokenize without building an AST. Lexical errors are yielded in place and
+/// scanning continues after them.
 #[derive(Debug)]
 pub struct Scanner<'a> {
     source: &'a str,
@@ -53,9 +106,24 @@ pub struct Scanner<'a> {
     /// Byte offset of current position
     current: usize,
     indent_stack: Vec<usize>,
+    /// Whether the file indents with tabs or spaces, set by its first
+    /// indented line
+    indent_char: Option<char>,
     pending_dedents: usize,
     /// Current scanning mode
     mode: ScanMode,
+    /// Text mode was just entered from the start of a line or a choice marker
+    text_start: bool,
+    /// Start of the open `"""` text block, where newlines are part of the text
+    block_start: Option<usize>,
+    /// The text being scanned belongs to a choice, so trailing `#tags` are allowed
+    choice_text: bool,
+    /// The last declaration token ends an operand, so a `-` after it subtracts
+    /// rather than starting a negative number
+    after_operand: bool,
+    /// A control character found inside a string or comment, reported right
+    /// after the token that contains it
+    pending_error: Option<LexicalError>,
 }
 
 impl<'a> Scanner<'a> {
@@ -65,47 +133,78 @@ impl<'a> Scanner<'a> {
             start: 0,
             current: 0,
             indent_stack: vec![0],
+            indent_char: None,
             pending_dedents: 0,
             mode: ScanMode::Indentation,
+            text_start: false,
+            block_start: None,
+            choice_text: false,
+            after_operand: false,
+            pending_error: None,
         }
     }
 
-    pub fn tokens(mut self) -> impl Iterator<Item = Result<Token<'a>, LexicalError>> {
-        std::iter::from_fn(move || {
-            let result = self.scan_token();
-            match &result {
-                Ok(token) if token.kind == TokenKind::Eof => None,
-                _ => Some(result),
-            }
-        })
+    /// Iterate over the tokens of the source, stopping before `Eof`.
+    pub fn iter(self) -> Tokens<'a> {
+        Tokens {
+            scanner: self,
+            done: false,
+        }
+    }
+
+    pub fn tokens(self) -> impl Iterator<Item = Result<Token<'a>, LexicalError>> {
+        self.iter()
     }
 
     fn scan_token(&mut self) -> Result<Token<'a>, LexicalError> {
+        if let Some(err) = self.pending_error.take() {
+            return Err(err);
+        }
+
         // Handle indentation when in Indentation mode
-        if self.mode == ScanMode::Indentation {
-            if let Some(token) = self.handle_indentation()? {
-                return Ok(token);
-            }
+        if self.mode == ScanMode::Indentation
+            && let Some(token) = self.handle_indentation()?
+        {
+            return Ok(token);
         }
 
         self.start = self.current;
 
         if self.is_at_end() {
+            if let Some(start) = self.block_start.take() {
+                return Err(LexicalError::Unexpected {
+                    message: "Unterminated text block - expected closing '\"\"\"'",
+                    span: Span {
+                        start,
+                        end: start + BLOCK_QUOTES.len(),
+                    },
+                });
+            }
             return Ok(self.make_token(TokenKind::Eof));
         }
 
-        // Handle newlines - transition to Indentation mode
-        if self.consume_newline() {
+        // Handle newlines - transition to Indentation mode.
+        // Inside a text block, newlines are text and the text scanner takes them.
+        if self.block_start.is_none() && self.consume_newline() {
             self.mode = ScanMode::Indentation;
             return Ok(self.make_token(TokenKind::NewLine));
         }
 
+        if let Some(c) = self.peek().filter(|&c| is_disallowed_control(c)) {
+            self.advance();
+            return Err(control_character(c, self.start));
+        }
+
         // Dispatch based on current mode
         match self.mode {
             ScanMode::Indentation => unreachable!("should have been handled above"),
             ScanMode::LineStart => self.scan_line_start(),
             ScanMode::Declaration => self.scan_declaration_content(),
             ScanMode::ExternDeclaration => self.scan_extern_declaration(),
+            ScanMode::GotoTarget => self.scan_goto_target(),
+            ScanMode::IncludePath => self.scan_include_path(),
+            ScanMode::Label => self.scan_label(),
+            ScanMode::LineEnd => self.scan_line_end(),
             ScanMode::Text => self.scan_text_content(),
             ScanMode::Interpolation => self.scan_interpolation_content(),
         }
@@ -120,6 +219,9 @@ impl<'a> Scanner<'a> {
         if let Some(tok) = self.try_keyword("save", TokenKind::Save, ScanMode::Declaration) {
             return Ok(tok);
         }
+        if let Some(tok) = self.try_keyword("const", TokenKind::Const, ScanMode::Declaration) {
+            return Ok(tok);
+        }
         if let Some(tok) = self.try_keyword("set", TokenKind::Set, ScanMode::Declaration) {
             return Ok(tok);
         }
@@ -129,13 +231,40 @@ impl<'a> Scanner<'a> {
             return Ok(tok);
         }
 
+        if let Some(tok) = self.try_keyword("goto", TokenKind::Goto, ScanMode::GotoTarget) {
+            return Ok(tok);
+        }
+        if let Some(tok) = self.try_keyword("wait", TokenKind::Wait, ScanMode::Declaration) {
+            return Ok(tok);
+        }
+        if let Some(tok) = self.try_keyword("include", TokenKind::Include, ScanMode::IncludePath) {
+            return Ok(tok);
+        }
+
+        // Statements that are a keyword alone
+        if let Some(tok) = self.try_bare_keyword("end", TokenKind::End) {
+            return Ok(tok);
+        }
+        if let Some(tok) = self.try_bare_keyword("done", TokenKind::End) {
+            return Ok(tok);
+        }
+
+        // Label marker
+        if let Some(tok) = self.try_keyword("==", TokenKind::LabelMarker, ScanMode::Label) {
+            return Ok(tok);
+        }
+
         // Choice marker
         if let Some(tok) = self.try_keyword("-", TokenKind::Choice, ScanMode::Text) {
+            self.text_start = true;
+            self.choice_text = true;
             return Ok(tok);
         }
 
         // Otherwise it's text content
         self.mode = ScanMode::Text;
+        self.text_start = true;
+        self.choice_text = false;
         self.scan_text_content()
     }
 
@@ -163,10 +292,30 @@ impl<'a> Scanner<'a> {
         let token = self.make_token(kind);
         self.skip_spaces();
         self.mode = next_mode;
+        self.after_operand = false;
         Some(token)
     }
 
-    /// Scan declaration content: identifier = literal
+    /// Try to match a keyword that makes up the whole statement, followed
+    /// only by spaces and an optional comment. Anything else after it makes
+    /// the line text, so a line such as "end of story" is unaffected.
+    fn try_bare_keyword(&mut self, keyword: &str, kind: TokenKind) -> Option<Token<'a>> {
+        let rest = self
+            .remaining()
+            .strip_prefix(keyword)?
+            .trim_start_matches(' ');
+        if !(rest.is_empty() || rest.starts_with(['\n', '\r']) || rest.starts_with("//")) {
+            return None;
+        }
+
+        self.advance_n(keyword.len());
+        let token = self.make_token(kind);
+        self.skip_spaces();
+        self.mode = ScanMode::LineEnd;
+        Some(token)
+    }
+
+    /// Scan declaration content: identifier = expression
     fn scan_declaration_content(&mut self) -> Result<Token<'a>, LexicalError> {
         self.skip_spaces();
         self.start = self.current;
@@ -174,60 +323,246 @@ impl<'a> Scanner<'a> {
         if self.is_at_end() || self.is_at_newline() {
             return Err(self.error("Unexpected end of declaration"));
         }
+        if self.remaining().starts_with("//") {
+            return Ok(self.scan_comment());
+        }
 
-        let c = self.peek().unwrap();
+        match self.scan_expression_token() {
+            Some(token) => token,
+            None => {
+                // Error recovery: advance past the invalid character to avoid infinite loop
+                self.advance();
+                Err(self.invalid_character("Unexpected character in declaration"))
+            }
+        }
+    }
+
+    /// Scan one token of an expression, starting at a non-space character, and
+    /// track whether it ends an operand. `None` if no token starts here.
+    fn scan_expression_token(&mut self) -> Option<Result<Token<'a>, LexicalError>> {
+        let token = match self.scan_operator_or_operand()? {
+            Ok(token) => token,
+            Err(err) => return Some(Err(err)),
+        };
+        self.after_operand = matches!(
+            token.kind,
+            TokenKind::Identifier
+                | TokenKind::Number
+                | TokenKind::String
+                | TokenKind::True
+                | TokenKind::False
+                | TokenKind::RightParen
+        );
+        Some(Ok(token))
+    }
+
+    fn scan_operator_or_operand(&mut self) -> Option<Result<Token<'a>, LexicalError>> {
+        let c = self.peek()?;
+
+        // Comparison operators, before '=' so that '==' is not an assignment
+        let comparison = match (c, self.peek_next()) {
+            ('=', Some('=')) => Some((TokenKind::EqualEqual, 2)),
+            ('!', Some('=')) => Some((TokenKind::BangEqual, 2)),
+            ('<', Some('=')) => Some((TokenKind::LessEqual, 2)),
+            ('>', Some('=')) => Some((TokenKind::GreaterEqual, 2)),
+            ('<', _) => Some((TokenKind::Less, 1)),
+            ('>', _) => Some((TokenKind::Greater, 1)),
+            _ => None,
+        };
+        if let Some((kind, len)) = comparison {
+            self.advance_n(len);
+            return Some(Ok(self.make_token(kind)));
+        }
 
         // Equals
         if c == '=' {
             self.advance();
-            return Ok(self.make_token(TokenKind::Equals));
+            return Some(Ok(self.make_token(TokenKind::Equals)));
+        }
+
+        // Compound assignment operators
+        let compound = match c {
+            '+' => Some(TokenKind::PlusEquals),
+            '-' => Some(TokenKind::MinusEquals),
+            '*' => Some(TokenKind::StarEquals),
+            '/' => Some(TokenKind::SlashEquals),
+            _ => None,
+        };
+        if let Some(kind) = compound
+            && self.peek_next() == Some('=')
+        {
+            self.advance_n(2);
+            return Some(Ok(self.make_token(kind)));
         }
 
         // String literal
         if c == '"' {
-            return self.scan_string();
+            return Some(self.scan_string());
         }
 
-        // Number literal (including negative)
-        if c.is_ascii_digit() || (c == '-' && self.peek_next().is_some_and(|n| n.is_ascii_digit()))
+        // Number literal, negative unless the '-' follows an operand (`gold -5`)
+        if c.is_ascii_digit()
+            || (c == '-'
+                && !self.after_operand
+                && self.peek_next().is_some_and(|n| n.is_ascii_digit()))
         {
-            return self.scan_number();
+            return Some(self.scan_number());
+        }
+
+        // Arithmetic operators and grouping
+        let single = match c {
+            '+' => Some(TokenKind::Plus),
+            '-' => Some(TokenKind::Minus),
+            '*' => Some(TokenKind::Star),
+            '/' => Some(TokenKind::Slash),
+            '(' => Some(TokenKind::LeftParen),
+            ')' => Some(TokenKind::RightParen),
+            ',' => Some(TokenKind::Comma),
+            _ => None,
+        };
+        if let Some(kind) = single {
+            self.advance();
+            return Some(Ok(self.make_token(kind)));
         }
 
         // Identifier or keyword (true/false)
         if c.is_ascii_alphabetic() || c == '_' {
-            return self.scan_identifier_or_keyword();
+            return Some(self.scan_identifier_or_keyword());
         }
 
-        // Error recovery: advance past the invalid character to avoid infinite loop
-        self.advance();
-        Err(self.error("Unexpected character in declaration"))
+        None
     }
 
     /// Scan extern declaration content: identifier only (no initializer)
     fn scan_extern_declaration(&mut self) -> Result<Token<'a>, LexicalError> {
+        self.scan_name("Expected identifier after 'extern'")
+    }
+
+    /// Scan goto content: the target label name
+    fn scan_goto_target(&mut self) -> Result<Token<'a>, LexicalError> {
+        self.scan_name("Expected label name after 'goto'")
+    }
+
+    /// Scan include content: the quoted path, then trailing spaces
+    fn scan_include_path(&mut self) -> Result<Token<'a>, LexicalError> {
+        self.skip_spaces();
+        self.start = self.current;
+
+        if self.peek() != Some('"') {
+            if !self.is_at_end() && !self.is_at_newline() {
+                // Error recovery: advance past the invalid character to avoid infinite loop
+                self.advance();
+            }
+            return Err(self.invalid_character("Expected quoted path after 'include'"));
+        }
+
+        self.mode = ScanMode::LineEnd;
+        let token = self.scan_string()?;
+        self.skip_spaces();
+        Ok(token)
+    }
+
+    /// Scan label content: the label name, then an optional closing "=="
+    fn scan_label(&mut self) -> Result<Token<'a>, LexicalError> {
+        let token = self.scan_name("Expected label name after '=='")?;
+        if self.source[self.current..].starts_with("==") {
+            self.advance_n(2);
+            self.skip_spaces();
+        }
+        Ok(token)
+    }
+
+    /// Scan what follows a statement's last token: a comment, or else the
+    /// start of another statement, which the parser reports.
+    fn scan_line_end(&mut self) -> Result<Token<'a>, LexicalError> {
+        self.skip_spaces();
+        self.start = self.current;
+        if self.remaining().starts_with("//") {
+            return Ok(self.scan_comment());
+        }
+        self.scan_line_start()
+    }
+
+    /// Scan a `//` comment up to the end of the line, leaving trailing spaces
+    /// out of the lexeme.
+    fn scan_comment(&mut self) -> Token<'a> {
+        let rest = self.remaining();
+        let line = &rest[..rest.find(['\n', '\r']).unwrap_or(rest.len())];
+        for (offset, c) in line.char_indices() {
+            self.defer_control_error(c, self.current + offset);
+        }
+        self.current += line.trim_end().len();
+        let token = self.make_token(TokenKind::Comment);
+        self.skip_spaces();
+        token
+    }
+
+    /// Scan a single identifier that ends the statement, then skip trailing spaces.
+    fn scan_name(&mut self, message: &'static str) -> Result<Token<'a>, LexicalError> {
         self.skip_spaces();
         self.start = self.current;
 
         if self.is_at_end() || self.is_at_newline() {
-            return Err(self.error("Expected identifier after 'extern'"));
+            return Err(self.error(message));
         }
 
         let c = self.peek().unwrap();
         if c.is_ascii_alphabetic() || c == '_' {
-            self.mode = ScanMode::LineStart;
-            return self.scan_identifier();
+            self.mode = ScanMode::LineEnd;
+            let token = self.scan_identifier()?;
+            self.skip_spaces();
+            return Ok(token);
         }
 
         // Error recovery: advance past the invalid character to avoid infinite loop
         self.advance();
-        Err(self.error("Expected identifier after 'extern'"))
+        Err(self.invalid_character(message))
     }
 
     /// Scan text content with interpolation support
     fn scan_text_content(&mut self) -> Result<Token<'a>, LexicalError> {
         self.start = self.current;
 
+        // A `"""` at the start of the text opens a block
+        if std::mem::take(&mut self.text_start) && self.remaining().starts_with(BLOCK_QUOTES) {
+            self.block_start = Some(self.current);
+            self.advance_n(BLOCK_QUOTES.len());
+            // Text starting on the next line does not begin with a newline
+            if self
+                .remaining()
+                .trim_start_matches(' ')
+                .starts_with(['\n', '\r'])
+            {
+                self.skip_spaces();
+                self.consume_newline();
+                self.skip_spaces();
+            }
+            return self.scan_text_content();
+        }
+
+        if self.block_start.is_some() {
+            if let Some(token) = self.scan_block_boundary()? {
+                return Ok(token);
+            }
+        } else if let Some(len) = self.continuation_len() {
+            // Trailing '\': join the next line with a single space
+            self.advance_n(len);
+            self.consume_newline();
+            self.skip_spaces();
+            return Ok(Token {
+                kind: TokenKind::TextSegment,
+                lexeme: " ",
+                span: Span {
+                    start: self.start,
+                    end: self.current,
+                },
+            });
+        }
+
+        if self.at_trailing_tags() {
+            return Ok(self.scan_tag());
+        }
+
         if self.is_at_end() || self.is_at_newline() {
             // Empty text at end of line - switch back to line start mode
             // This shouldn't normally happen, but handle gracefully
@@ -255,6 +590,7 @@ impl<'a> Scanner<'a> {
             }
             // Start of interpolation
             self.mode = ScanMode::Interpolation;
+            self.after_operand = false;
             return Ok(self.make_token(TokenKind::OpenBrace));
         }
 
@@ -277,10 +613,23 @@ impl<'a> Scanner<'a> {
             return Err(self.error("Unexpected '}' - use '}}' for literal brace"));
         }
 
-        // Scan text segment until { or } or newline
+        // Scan text segment until { or } or newline, or a line continuation
+        // or closing quotes
         while !self.is_at_end() && !self.is_at_newline() {
             let c = self.peek().unwrap();
-            if c == '{' || c == '}' {
+            if c == '{' || c == '}' || is_disallowed_control(c) {
+                break;
+            }
+            if self.block_start.is_some() && self.remaining().starts_with(BLOCK_QUOTES) {
+                break;
+            }
+            if self.block_start.is_none()
+                && (c == ' ' || c == '\\')
+                && self.continuation_len().is_some()
+            {
+                break;
+            }
+            if c == ' ' && self.at_trailing_tags() {
                 break;
             }
             self.advance();
@@ -289,6 +638,80 @@ impl<'a> Scanner<'a> {
         Ok(self.make_token(TokenKind::TextSegment))
     }
 
+    /// Handle the end of a line or the closing quotes inside a text block.
+    ///
+    /// Returns a `"\n"` text segment for a line break inside the block, even a
+    /// `\r\n` or `\r` one; the segment's span still covers the original bytes.
+    /// Leading spaces on each line of the block are not part of the text.
+    fn scan_block_boundary(&mut self) -> Result<Option<Token<'a>>, LexicalError> {
+        if self.is_at_newline() {
+            self.consume_newline();
+            self.skip_spaces();
+            // The line break before the closing quotes is not part of the text
+            if !self.remaining().starts_with(BLOCK_QUOTES) {
+                return Ok(Some(Token {
+                    kind: TokenKind::TextSegment,
+                    lexeme: "\n",
+                    span: Span {
+                        start: self.start,
+                        end: self.current,
+                    },
+                }));
+            }
+        }
+
+        if self.remaining().starts_with(BLOCK_QUOTES) {
+            self.advance_n(BLOCK_QUOTES.len());
+            self.block_start = None;
+            return self.scan_token().map(Some);
+        }
+
+        Ok(None)
+    }
+
+    /// Length of a line continuation at the current position: optional spaces,
+    /// a backslash, and optional spaces, directly followed by a line break.
+    fn continuation_len(&self) -> Option<usize> {
+        let rest = self.remaining();
+        let after = rest.trim_start_matches(' ').strip_prefix('\\')?;
+        let after = after.trim_start_matches(' ');
+        after
+            .starts_with(['\n', '\r'])
+            .then_some(rest.len() - after.len())
+    }
+
+    /// Whether the rest of a choice's line is only `#tags`, and they start
+    /// here or after the spaces here. A tag must follow a space.
+    fn at_trailing_tags(&self) -> bool {
+        if !self.choice_text || self.block_start.is_some() {
+            return false;
+        }
+        let after_space = self.peek() == Some(' ') || self.source[..self.current].ends_with(' ');
+        let rest = self.remaining();
+        let line = &rest[..rest.find(['\n', '\r']).unwrap_or(rest.len())];
+        let mut words = line.split(' ').filter(|word| !word.is_empty()).peekable();
+        after_space && words.peek().is_some() && words.all(is_tag)
+    }
+
+    /// Scan one `#tag` after skipping the spaces before it.
+    fn scan_tag(&mut self) -> Token<'a> {
+        self.skip_spaces();
+        self.start = self.current;
+        while self
+            .peek()
+            .is_some_and(|c| c != ' ' && c != '\n' && c != '\r')
+        {
+            self.advance();
+        }
+        let token = self.make_token(TokenKind::Tag);
+        self.skip_spaces();
+        token
+    }
+
+    fn remaining(&self) -> &'a str {
+        &self.source[self.current..]
+    }
+
     /// Scan inside an interpolation - expect identifier then }
     fn scan_interpolation_content(&mut self) -> Result<Token<'a>, LexicalError> {
         self.skip_spaces();
@@ -308,14 +731,26 @@ impl<'a> Scanner<'a> {
             return Ok(self.make_token(TokenKind::CloseBrace));
         }
 
-        // Identifier
-        if c.is_ascii_alphabetic() || c == '_' {
-            return self.scan_identifier();
+        // Format specifier: everything after ':' up to the closing brace
+        if c == ':' {
+            self.advance();
+            self.start = self.current;
+            while let Some(c) = self.peek() {
+                if c == '}' || c == '\n' || c == '\r' {
+                    break;
+                }
+                self.advance();
+            }
+            return Ok(self.make_token(TokenKind::FormatSpec));
+        }
+
+        if let Some(token) = self.scan_expression_token() {
+            return token;
         }
 
         // Error recovery: advance past the invalid character to avoid infinite loop
         self.advance();
-        Err(self.error("Invalid character in interpolation"))
+        Err(self.invalid_character("Invalid character in interpolation"))
     }
 
     /// Scan an identifier
@@ -345,6 +780,9 @@ impl<'a> Scanner<'a> {
         let kind = match lexeme {
             "true" => TokenKind::True,
             "false" => TokenKind::False,
+            "and" => TokenKind::And,
+            "or" => TokenKind::Or,
+            "not" => TokenKind::Not,
             _ => TokenKind::Identifier,
         };
 
@@ -368,6 +806,7 @@ impl<'a> Scanner<'a> {
             } else if c == '\n' || c == '\r' {
                 return Err(self.error("Unterminated string - newline in string literal"));
             } else {
+                self.defer_control_error(c, self.current);
                 self.advance();
             }
         }
@@ -409,11 +848,14 @@ impl<'a> Scanner<'a> {
         // 1. Emit pending dedents first
         if self.pending_dedents > 0 {
             self.pending_dedents -= 1;
+            if self.pending_dedents == 0 {
+                self.mode = ScanMode::LineStart;
+            }
             self.start = self.current;
             return Ok(Some(self.make_token(TokenKind::Dedent)));
         }
 
-        // 2. Process line start: skip blank lines and count leading spaces
+        // 2. Process line start: skip blank lines and measure indentation
         let spaces = match self.process_line_start()? {
             Some(count) => count,
             None => {
@@ -421,7 +863,9 @@ impl<'a> Scanner<'a> {
                 if self.indent_stack.len() > 1 {
                     self.indent_stack.pop();
                     self.pending_dedents = self.indent_stack.len() - 1;
-                    self.mode = ScanMode::LineStart;
+                    if self.pending_dedents == 0 {
+                        self.mode = ScanMode::LineStart;
+                    }
                     self.start = self.current;
                     return Ok(Some(self.make_token(TokenKind::Dedent)));
                 }
@@ -452,6 +896,10 @@ impl<'a> Scanner<'a> {
                 return Err(self.error("Inconsistent indentation"));
             }
             self.pending_dedents -= 1; // We emit one now
+            if self.pending_dedents > 0 {
+                // Stay here so the rest are emitted before the line's content
+                self.mode = ScanMode::Indentation;
+            }
             Ok(Some(self.make_token(TokenKind::Dedent)))
         } else {
             // Same level - no token
@@ -459,31 +907,49 @@ impl<'a> Scanner<'a> {
         }
     }
 
-    /// Skips blank lines and returns the leading space count of the first content line.
-    /// Returns None if EOF is reached.
+    /// Skips blank lines and returns the indentation width of the first content
+    /// line, counting each tab or space as one. Returns None if EOF is reached.
+    ///
+    /// A file indents with tabs or with spaces, never both: the first indented
+    /// line picks one, and any line that uses the other is an error.
     fn process_line_start(&mut self) -> Result<Option<usize>, LexicalError> {
         loop {
             self.start = self.current;
-            let mut spaces = 0;
-            while self.peek() == Some(' ') {
+            let (mut spaces, mut tabs) = (0, 0);
+            while let Some(c @ (' ' | '\t')) = self.peek() {
                 self.advance();
-                spaces += 1;
+                if c == ' ' {
+                    spaces += 1;
+                } else {
+                    tabs += 1;
+                }
             }
             if self.consume_newline() {
                 continue;
             }
-            if self.peek() == Some('\t') {
-                // Advance past the tab and skip to end of line to avoid infinite loop
-                while !self.is_at_end() && !self.is_at_newline() {
-                    self.advance();
-                }
-                return Err(self.error("Tabs not allowed in indentation, use spaces"));
-            }
             if self.is_at_end() {
                 return Ok(None);
             }
-            return Ok(Some(spaces));
+            let unit = match (spaces, tabs) {
+                (0, 0) => return Ok(Some(0)),
+                (_, 0) => ' ',
+                (0, _) => '\t',
+                _ => return Err(self.mixed_indentation()),
+            };
+            if *self.indent_char.get_or_insert(unit) != unit {
+                return Err(self.mixed_indentation());
+            }
+            return Ok(Some(spaces + tabs));
+        }
+    }
+
+    /// Error for a line indented with the wrong character, covering the whole
+    /// line. Skips to the end of the line so scanning can carry on.
+    fn mixed_indentation(&mut self) -> LexicalError {
+        while !self.is_at_end() && !self.is_at_newline() {
+            self.advance();
         }
+        self.error("Inconsistent indentation (mixed tabs and spaces)")
     }
 
     // =========================================================================
@@ -555,6 +1021,24 @@ impl<'a> Scanner<'a> {
         }
     }
 
+    /// The error for the character just consumed as invalid: its own error
+    /// for a control character, `message` for anything else.
+    fn invalid_character(&self, message: &'static str) -> LexicalError {
+        match self.source[self.start..self.current].chars().next() {
+            Some(c) if is_disallowed_control(c) => control_character(c, self.start),
+            _ => self.error(message),
+        }
+    }
+
+    /// If `c`, at `start` inside a string or comment, is a control character,
+    /// hold its error until the token that contains it is out. Only the first
+    /// one is kept.
+    fn defer_control_error(&mut self, c: char, start: usize) {
+        if is_disallowed_control(c) && self.pending_error.is_none() {
+            self.pending_error = Some(control_character(c, start));
+        }
+    }
+
     fn error(&self, message: &'static str) -> LexicalError {
         LexicalError::Unexpected {
             message,
@@ -565,3 +1049,55 @@ impl<'a> Scanner<'a> {
         }
     }
 }
+
+/// Control characters have no place in a script, apart from tabs and line
+/// breaks.
+fn is_disallowed_control(c: char) -> bool {
+    c < ' ' && !matches!(c, '\t' | '\n' | '\r')
+}
+
+fn control_character(character: char, start: usize) -> LexicalError {
+    LexicalError::ControlCharacter {
+        character,
+        span: Span {
+            start,
+            end: start + character.len_utf8(),
+        },
+    }
+}
+
+/// A tag is `#` followed by a name that starts with a letter or underscore
+/// and continues with letters, digits, `_`, or `-`.
+fn is_tag(word: &str) -> bool {
+    word.strip_prefix('#').is_some_and(|name| {
+        name.starts_with(|c: char| c.is_alphabetic() || c == '_')
+            && name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+    })
+}
+
+/// Lazy token iterator returned by [`Scanner::iter`].
+#[derive(Debug)]
+pub struct Tokens<'a> {
+    scanner: Scanner<'a>,
+    done: bool,
+}
+
+impl<'a> Iterator for Tokens<'a> {
+    type Item = Result<Token<'a>, LexicalError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.scanner.scan_token();
+        if matches!(&result, Ok(token) if token.kind == TokenKind::Eof) {
+            self.done = true;
+            return None;
+        }
+        Some(result)
+    }
+}
+
+impl std::iter::FusedIterator for Tokens<'_> {}
@@ -48,6 +48,11 @@ use crate::Value;
 ///     fn contains(&self, name: &str) -> bool {
 ///         self.values.read().unwrap().contains_key(name)
 ///     }
+///
+///     fn iter(&self) -> Vec<(String, Value)> {
+///         let values = self.values.read().unwrap();
+///         values.iter().map(|(name, value)| (name.clone(), value.clone())).collect()
+///     }
 /// }
 /// ```
 pub trait VariableStorage: Send + Sync {
@@ -72,6 +77,14 @@ pub trait VariableStorage: Send + Sync {
 
     /// Check if a variable exists in storage.
     fn contains(&self, name: &str) -> bool;
+
+    /// Snapshot every stored variable, in no particular order.
+    ///
+    /// Used by debug overlays and save inspectors. The default returns nothing;
+    /// implementations that can enumerate their contents should override it.
+    fn iter(&self) -> Vec<(String, Value)> {
+        Vec::new()
+    }
 }
 
 /// Interface for host-provided variables (read-only from Bobbin's perspective).
@@ -119,6 +132,29 @@ pub trait HostState: Send + Sync {
     /// Look up a host variable by name.
     ///
     /// Returns `Some(value)` if the variable exists, `None` otherwise.
-    /// A `None` return will cause `RuntimeError::MissingExternVariable` at runtime.
+    /// A `None` return falls back to [`HostState::default_for`].
     fn lookup(&self, name: &str) -> Option<Value>;
+
+    /// A stand-in for a variable that `lookup` does not provide.
+    ///
+    /// The default of `None` keeps missing variables an error
+    /// (`RuntimeError::MissingExternVariable`). A development host can return
+    /// placeholder values here so the dialogue runs before the game supplies
+    /// everything it declares.
+    fn default_for(&self, _name: &str) -> Option<Value> {
+        None
+    }
+}
+
+/// Host state that answers lookups asynchronously, for extern values that
+/// live somewhere slow, such as networked game state.
+///
+/// Passed to `Runtime::advance_async` and `Runtime::select_choice_async`,
+/// which await each lookup instead of blocking. Implementations can write
+/// `async fn lookup`. A `None` falls back to the runtime's own
+/// [`HostState::default_for`].
+#[cfg(feature = "async")]
+pub trait AsyncHostState {
+    /// Look up a host variable by name.
+    fn lookup(&self, name: &str) -> impl Future<Output = Option<Value>> + Send;
 }
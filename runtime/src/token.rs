@@ -10,8 +10,19 @@ pub enum TokenKind {
     // Keywords
     Temp,
     Save,
+    Const,
     Set,
     Extern,
+    Goto,
+    Include,
+    Wait,
+    /// `end` or `done`, alone on its line
+    End,
+
+    // Logical operators
+    And,
+    Or,
+    Not,
 
     // Identifiers and Literals
     Identifier,
@@ -22,22 +33,125 @@ pub enum TokenKind {
 
     // Symbols
     Equals,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LeftParen,
+    RightParen,
+    Comma,
+    EqualEqual,
+    BangEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    PlusEquals,
+    MinusEquals,
+    StarEquals,
+    SlashEquals,
     OpenBrace,
     CloseBrace,
 
     // Text (dialogue content between interpolations)
     TextSegment,
+    /// Number format inside an interpolation, after the ':' (e.g. `0.2`)
+    FormatSpec,
+    /// A `#tag` at the end of a choice's text; the lexeme includes the `#`
+    Tag,
+    /// A `//` comment at the end of a keyword statement; the lexeme includes
+    /// the `//`
+    Comment,
 
     // Structure
-    Choice, // Just the "- " marker
+    Choice,      // Just the "- " marker
+    LabelMarker, // The opening "==" of a label line
     Indent,
     Dedent,
     NewLine,
     Eof,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+impl TokenKind {
+    /// How the token reads in an error message, e.g. `'='` or `a number`.
+    pub fn describe(self) -> &'static str {
+        match self {
+            TokenKind::Temp => "'temp'",
+            TokenKind::Save => "'save'",
+            TokenKind::Const => "'const'",
+            TokenKind::Set => "'set'",
+            TokenKind::Extern => "'extern'",
+            TokenKind::Goto => "'goto'",
+            TokenKind::Include => "'include'",
+            TokenKind::Wait => "'wait'",
+            TokenKind::End => "'end'",
+            TokenKind::And => "'and'",
+            TokenKind::Or => "'or'",
+            TokenKind::Not => "'not'",
+            TokenKind::Identifier => "a name",
+            TokenKind::String => "a string",
+            TokenKind::Number => "a number",
+            TokenKind::True => "'true'",
+            TokenKind::False => "'false'",
+            TokenKind::Equals => "'='",
+            TokenKind::Plus => "'+'",
+            TokenKind::Minus => "'-'",
+            TokenKind::Star => "'*'",
+            TokenKind::Slash => "'/'",
+            TokenKind::LeftParen => "'('",
+            TokenKind::RightParen => "')'",
+            TokenKind::Comma => "','",
+            TokenKind::EqualEqual => "'=='",
+            TokenKind::BangEqual => "'!='",
+            TokenKind::Less => "'<'",
+            TokenKind::LessEqual => "'<='",
+            TokenKind::Greater => "'>'",
+            TokenKind::GreaterEqual => "'>='",
+            TokenKind::PlusEquals => "'+='",
+            TokenKind::MinusEquals => "'-='",
+            TokenKind::StarEquals => "'*='",
+            TokenKind::SlashEquals => "'/='",
+            TokenKind::OpenBrace => "'{'",
+            TokenKind::CloseBrace => "'}'",
+            TokenKind::TextSegment => "text",
+            TokenKind::FormatSpec => "a number format",
+            TokenKind::Tag => "a tag",
+            TokenKind::Comment => "a comment",
+            TokenKind::Choice => "a choice marker",
+            TokenKind::LabelMarker => "a label",
+            TokenKind::Indent => "an indented line",
+            TokenKind::Dedent => "the end of a block",
+            TokenKind::NewLine => "the end of the line",
+            TokenKind::Eof => "the end of the file",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     pub start: usize,
     pub end: usize,
 }
+
+impl Span {
+    /// The smallest span covering both `self` and `other`, including any gap
+    /// between them.
+    pub fn merge(self, other: Span) -> Span {
+        Span {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// Whether `offset` falls inside the span. The end is exclusive, so an
+    /// empty span contains nothing.
+    pub fn contains(self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// Whether the span covers no source.
+    pub fn is_empty(self) -> bool {
+        self.start >= self.end
+    }
+}
@@ -0,0 +1,422 @@
+//! Diagnostics-only checking of Bobbin source.
+//!
+//! Editors and build tools want every problem in a script without creating a
+//! `Runtime` or executing anything. `validate` runs the front end (scan, parse,
+//! resolve) and returns diagnostics ready for rendering.
+
+use std::collections::HashMap;
+use std::sync::atomic::AtomicBool;
+use std::time::{Duration, Instant};
+
+use crate::ast::Script;
+use crate::diagnostic::{
+    AriadneRenderer, Diagnostic, DiagnosticContext, IntoDiagnostic, JaroWinklerMatcher, LineIndex,
+    Matcher, Renderer, Severity, SourcePosition,
+};
+use crate::include::expand_includes;
+use crate::parser::{ParseError, Parser};
+use crate::resolver::{Resolver, SymbolTable};
+use crate::scanner::{LexicalError, Scanner};
+use crate::token::{Span, Token};
+
+/// Controls which analyses `validate_with_options` runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ValidateOptions {
+    /// Run name resolution after parsing. Disable for fast, syntax-only checks.
+    pub resolve: bool,
+    /// Include warning diagnostics in the result.
+    pub warnings: bool,
+    /// Report warnings as errors, so a CI check fails on them. Applies even
+    /// when `warnings` is off.
+    pub deny_warnings: bool,
+    /// Minimum similarity (0.0 to 1.0) for "did you mean?" suggestions.
+    pub matcher_threshold: f64,
+    /// Keep at most this many diagnostics, in source order, followed by a note
+    /// counting the rest. `None` keeps them all, in pipeline order.
+    pub max_diagnostics: Option<usize>,
+    /// Warn on lines with more characters of text than this, not counting
+    /// interpolations. `None` (the default) turns the check off.
+    pub max_line_chars: Option<usize>,
+}
+
+impl Default for ValidateOptions {
+    fn default() -> Self {
+        Self {
+            resolve: true,
+            warnings: true,
+            deny_warnings: false,
+            matcher_threshold: JaroWinklerMatcher::default().threshold,
+            max_diagnostics: None,
+            max_line_chars: None,
+        }
+    }
+}
+
+/// Everything produced while validating a script.
+///
+/// Lets tooling reuse the parsed AST and symbol table (for outlines, hover, or
+/// go-to-definition) instead of parsing the source a second time.
+#[derive(Debug)]
+pub struct ValidationResult {
+    /// All diagnostics found, in pipeline order.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The parsed script, if parsing succeeded.
+    pub ast: Option<Script>,
+    /// The statements that parsed cleanly, if parsing failed.
+    ///
+    /// Broken statements are left out, so outlines and completion keep working
+    /// while the script has syntax errors. The resolver can run over it, but
+    /// references to anything declared on a broken line will not resolve.
+    pub partial_ast: Option<Script>,
+    /// The resolved symbols, if resolution ran and succeeded.
+    pub symbols: Option<SymbolTable>,
+}
+
+/// Where the time went while validating a script.
+///
+/// Only collected by `validate_with_metrics`; the other entry points do not
+/// read the clock.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    pub scan: Duration,
+    pub parse: Duration,
+    /// `None` if resolution did not run because the script has syntax errors.
+    pub resolve: Option<Duration>,
+    /// Tokens scanned, including any that failed to scan.
+    pub tokens: usize,
+    /// Statements parsed, counting those nested under choices.
+    pub statements: usize,
+}
+
+/// A diagnostic reduced to plain values, for editor bindings that want every
+/// problem in one call across an FFI boundary.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PositionedDiagnostic {
+    /// Start of the primary span, or the start of the source if there is none.
+    pub start: SourcePosition,
+    /// End of the primary span, exclusive.
+    pub end: SourcePosition,
+    /// LSP severity code; see [`Severity::lsp_code`].
+    pub severity: u8,
+    pub message: String,
+}
+
+/// Check a script and return all diagnostics, using default options.
+pub fn validate(source: &str) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    validate_streaming(source, &mut |diag| diagnostics.push(diag));
+    diagnostics
+}
+
+/// Check a script with default options, handing each diagnostic to
+/// `on_diagnostic` instead of collecting them, for batch linting of very
+/// large scripts.
+///
+/// Diagnostics arrive in the order they are produced, the same order
+/// `validate` returns them in. Syntax errors are delivered while parsing is
+/// still under way. Semantic diagnostics are delivered once resolution
+/// finishes, since their suggestions draw on every declaration in the script.
+pub fn validate_streaming(source: &str, on_diagnostic: &mut dyn FnMut(Diagnostic)) {
+    let options = ValidateOptions::default();
+    let matcher = JaroWinklerMatcher::new(options.matcher_threshold);
+    let ctx = DiagnosticContext::new(&[], &matcher);
+    let mut deliver = |diag: Diagnostic| {
+        if let Some(diag) = admit(diag, options) {
+            on_diagnostic(diag);
+        }
+    };
+
+    let parsed = Parser::new(Scanner::new(source).tokens())
+        .with_error_sink(&mut |err| deliver(err.into_diagnostic(&ctx)))
+        .parse();
+    let Ok(ast) = parsed else {
+        return;
+    };
+
+    match Resolver::new(&ast).analyze() {
+        Ok(symbols) => {
+            for warning in symbols.warnings {
+                deliver(warning.into_diagnostic(&ctx));
+            }
+        }
+        Err((errors, known_variables)) => {
+            let ctx = DiagnosticContext::new(&known_variables, &matcher);
+            for err in errors {
+                deliver(err.into_diagnostic(&ctx));
+            }
+        }
+    }
+}
+
+/// Check a script and return all diagnostics.
+///
+/// Parse errors stop validation before resolution, matching `Runtime::new`.
+pub fn validate_with_options(source: &str, options: ValidateOptions) -> Vec<Diagnostic> {
+    let matcher = JaroWinklerMatcher::new(options.matcher_threshold);
+    run(source, options, &matcher, None).diagnostics
+}
+
+/// Check a script with default options, using `matcher` for "did you mean?"
+/// suggestions instead of the default Jaro-Winkler matcher.
+pub fn validate_with_matcher(source: &str, matcher: &dyn Matcher) -> Vec<Diagnostic> {
+    run(source, ValidateOptions::default(), matcher, None).diagnostics
+}
+
+/// Check a script and return its diagnostics along with the AST and symbols.
+pub fn validate_full(source: &str) -> ValidationResult {
+    let options = ValidateOptions::default();
+    let matcher = JaroWinklerMatcher::new(options.matcher_threshold);
+    run(source, options, &matcher, None)
+}
+
+/// Check a script with default options, stopping early once `cancel` is set.
+///
+/// The flag is checked before each top-level statement while parsing and
+/// resolving. A cancelled run returns the diagnostics found up to that point,
+/// so a language server can drop a stale validation as soon as the document
+/// changes again.
+pub fn validate_with_cancel(source: &str, cancel: &AtomicBool) -> Vec<Diagnostic> {
+    let options = ValidateOptions::default();
+    let matcher = JaroWinklerMatcher::new(options.matcher_threshold);
+    run(source, options, &matcher, Some(cancel)).diagnostics
+}
+
+/// Check a script with default options, timing each stage.
+///
+/// Scanning normally happens on demand as the parser asks for tokens; here
+/// the whole file is scanned first so the two can be timed apart.
+pub fn validate_with_metrics(source: &str) -> (Vec<Diagnostic>, Metrics) {
+    let options = ValidateOptions::default();
+    let matcher = JaroWinklerMatcher::new(options.matcher_threshold);
+    let mut metrics = Metrics::default();
+    let result = run_timed(source, options, &matcher, None, Some(&mut metrics));
+    (result.diagnostics, metrics)
+}
+
+/// Check a script with default options and render every diagnostic for a
+/// terminal, or return an empty string if there are none.
+///
+/// `source_id` names the script in the output, usually its path.
+pub fn render_diagnostics(source_id: &str, source: &str, colors: bool) -> String {
+    let renderer = AriadneRenderer { colors };
+    renderer.render_all(&validate(source), source_id, source)
+}
+
+/// Check a script with default options and return each diagnostic with
+/// LSP-style start and end positions.
+///
+/// Columns count UTF-16 code units when `use_utf16` is set, as LSP clients
+/// expect by default, and bytes otherwise.
+pub fn diagnostics_as_positions(source: &str, use_utf16: bool) -> Vec<PositionedDiagnostic> {
+    let index = LineIndex::new(source);
+    validate(source)
+        .into_iter()
+        .map(|diag| {
+            let span = diag.primary_span().unwrap_or(Span { start: 0, end: 0 });
+            PositionedDiagnostic {
+                start: index.to_lsp_position(span.start, use_utf16),
+                end: index.to_lsp_position(span.end, use_utf16),
+                severity: diag.severity.lsp_code(),
+                message: diag.message,
+            }
+        })
+        .collect()
+}
+
+/// Check several scripts that may include one another, and return each
+/// one's diagnostics under its id, in the order given.
+///
+/// Ids double as include paths: an `include` in one script loads another
+/// entry of `sources`, so the included script's declarations are visible to
+/// the one that includes it. Each script reports only the problems in its
+/// own text; an included script's problems are reported under its own id.
+pub fn validate_many(sources: &[(String, &str)]) -> Vec<(String, Vec<Diagnostic>)> {
+    let provider: HashMap<String, String> = sources
+        .iter()
+        .map(|(id, source)| (id.clone(), source.to_string()))
+        .collect();
+    let options = ValidateOptions::default();
+    let matcher = JaroWinklerMatcher::new(options.matcher_threshold);
+    sources
+        .iter()
+        .map(|(id, source)| {
+            let diagnostics = match expand_includes(id, source, &provider).0 {
+                Ok(ast) => resolve(ast, options, &matcher, None, None).diagnostics,
+                Err(errors) => {
+                    let ctx = DiagnosticContext::new(&[], &matcher);
+                    finish(errors.into_iter().map(|e| e.into_diagnostic(&ctx)), options)
+                }
+            };
+            let diagnostics = diagnostics
+                .into_iter()
+                .filter_map(|diag| within(diag, source.len()))
+                .collect();
+            (id.clone(), diagnostics)
+        })
+        .collect()
+}
+
+/// `diag` without the labels and suggestions past `len`, where an included
+/// file's text starts, or `None` if it is reported there.
+fn within(mut diag: Diagnostic, len: usize) -> Option<Diagnostic> {
+    if diag.primary_span().is_some_and(|span| span.start > len) {
+        return None;
+    }
+    diag.labels.retain(|label| label.span.start <= len);
+    diag.suggestions
+        .retain(|suggestion| suggestion.span.start <= len);
+    Some(diag)
+}
+
+fn run(
+    source: &str,
+    options: ValidateOptions,
+    matcher: &dyn Matcher,
+    cancel: Option<&AtomicBool>,
+) -> ValidationResult {
+    run_timed(source, options, matcher, cancel, None)
+}
+
+fn run_timed(
+    source: &str,
+    options: ValidateOptions,
+    matcher: &dyn Matcher,
+    cancel: Option<&AtomicBool>,
+    mut metrics: Option<&mut Metrics>,
+) -> ValidationResult {
+    let tokens = Scanner::new(source).tokens();
+    let (ast, errors) = match metrics.as_deref_mut() {
+        Some(metrics) => {
+            let started = Instant::now();
+            let tokens: Vec<_> = tokens.collect();
+            metrics.scan = started.elapsed();
+            metrics.tokens = tokens.len();
+
+            let started = Instant::now();
+            let parsed = parse(tokens.into_iter(), cancel);
+            metrics.parse = started.elapsed();
+            metrics.statements = parsed.0.walk().count();
+            parsed
+        }
+        None => parse(tokens, cancel),
+    };
+    if !errors.is_empty() {
+        let ctx = DiagnosticContext::new(&[], matcher);
+        return ValidationResult {
+            diagnostics: finish(errors.into_iter().map(|e| e.into_diagnostic(&ctx)), options),
+            ast: None,
+            partial_ast: Some(ast),
+            symbols: None,
+        };
+    }
+
+    resolve(ast, options, matcher, cancel, metrics)
+}
+
+/// Resolve a script that parsed cleanly, unless `options` turn resolution off.
+fn resolve(
+    ast: Script,
+    options: ValidateOptions,
+    matcher: &dyn Matcher,
+    cancel: Option<&AtomicBool>,
+    metrics: Option<&mut Metrics>,
+) -> ValidationResult {
+    if !options.resolve {
+        return ValidationResult {
+            diagnostics: Vec::new(),
+            ast: Some(ast),
+            partial_ast: None,
+            symbols: None,
+        };
+    }
+
+    let mut resolver = Resolver::new(&ast).with_max_line_chars(options.max_line_chars);
+    if let Some(cancel) = cancel {
+        resolver = resolver.with_cancel(cancel);
+    }
+    let started = metrics.is_some().then(Instant::now);
+    let analyzed = resolver.analyze();
+    if let (Some(metrics), Some(started)) = (metrics, started) {
+        metrics.resolve = Some(started.elapsed());
+    }
+    let (diagnostics, symbols) = match analyzed {
+        Ok(symbols) => {
+            let ctx = DiagnosticContext::new(&[], matcher);
+            let warnings = symbols
+                .warnings
+                .iter()
+                .map(|w| w.clone().into_diagnostic(&ctx));
+            (finish(warnings, options), Some(symbols))
+        }
+        Err((errors, known_variables)) => {
+            let ctx = DiagnosticContext::new(&known_variables, matcher);
+            let diagnostics = finish(errors.into_iter().map(|e| e.into_diagnostic(&ctx)), options);
+            (diagnostics, None)
+        }
+    };
+
+    ValidationResult {
+        diagnostics,
+        ast: Some(ast),
+        partial_ast: None,
+        symbols,
+    }
+}
+
+fn parse<'a>(
+    tokens: impl Iterator<Item = Result<Token<'a>, LexicalError>>,
+    cancel: Option<&'a AtomicBool>,
+) -> (Script, Vec<ParseError>) {
+    let mut parser = Parser::new(tokens);
+    if let Some(cancel) = cancel {
+        parser = parser.with_cancel(cancel);
+    }
+    parser.parse_recover()
+}
+
+fn finish(
+    diagnostics: impl Iterator<Item = Diagnostic>,
+    options: ValidateOptions,
+) -> Vec<Diagnostic> {
+    let mut diagnostics: Vec<Diagnostic> = diagnostics
+        .filter_map(|diag| admit(diag, options))
+        .collect();
+    if let Some(max) = options.max_diagnostics {
+        cap(&mut diagnostics, max);
+    }
+    diagnostics
+}
+
+/// `diag` as `options` report it, or `None` if they leave it out.
+fn admit(diag: Diagnostic, options: ValidateOptions) -> Option<Diagnostic> {
+    let diag = if options.deny_warnings {
+        diag.denied()
+    } else {
+        diag
+    };
+    (options.warnings || diag.severity != Severity::Warning).then_some(diag)
+}
+
+/// Sort `diagnostics` by position and replace everything past the first `max`
+/// with a single note. The sort is stable, so the result does not depend on
+/// anything but the source.
+fn cap(diagnostics: &mut Vec<Diagnostic>, max: usize) {
+    diagnostics.sort_by_key(|diag| diag.primary_span().map(|span| span.start));
+    if diagnostics.len() <= max {
+        return;
+    }
+    let suppressed = diagnostics.len() - max;
+    let first_span = diagnostics[max]
+        .primary_span()
+        .unwrap_or(Span { start: 0, end: 0 });
+    diagnostics.truncate(max);
+    let message = if suppressed == 1 {
+        "1 more diagnostic suppressed".to_string()
+    } else {
+        format!("{} more diagnostics suppressed", suppressed)
+    };
+    diagnostics.push(
+        Diagnostic::note(message, first_span, "first suppressed diagnostic")
+            .with_note("Fix the diagnostics above, or raise the limit, to see the rest"),
+    );
+}
@@ -1,18 +1,66 @@
-use crate::chunk::{Chunk, Instruction, Value};
+use crate::ast::NodeId;
+use crate::chunk::{Chunk, Instruction, LogicOp, NumberFormat, TextKey, Value, VarSource};
 use crate::diagnostic::{Diagnostic, DiagnosticContext, IntoDiagnostic, Severity};
+use crate::loc::{TemplatePart, parse_template};
+use crate::random::Rng;
+#[cfg(feature = "async")]
+use crate::storage::AsyncHostState;
 use crate::storage::{HostState, VariableStorage};
+use std::collections::{HashMap, HashSet};
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
+#[cfg(feature = "async")]
+use std::task::Poll;
 
 #[derive(Debug, Clone)]
 pub enum RuntimeError {
     /// select_and_continue called when VM is not at a ChoiceSet instruction
     NotAtChoice,
     /// Choice index out of bounds
-    InvalidChoiceIndex { index: usize, count: usize },
+    InvalidChoiceIndex {
+        index: usize,
+        count: usize,
+        /// The rendered texts of the choices on offer, when known
+        choices: Vec<String>,
+    },
+    /// `select_default` called on a choice set without a `#default` choice
+    NoDefaultChoice,
     /// Save variable not found in storage (storage may be corrupted or cleared)
     MissingSaveVariable { name: String },
     /// Extern variable not found in host state
     MissingExternVariable { name: String },
+    /// A number format was applied to a value that is not a number
+    NonNumericFormat { value: Value, format: NumberFormat },
+    /// An arithmetic operator was applied to a value that is not a number
+    TypeMismatch {
+        operator: &'static str,
+        left: Value,
+        right: Value,
+    },
+    /// Division with a zero divisor
+    DivisionByZero,
+    /// `random(min, max)` was given bounds with no whole number between them
+    InvalidRandomRange { min: f64, max: f64 },
+    /// A boolean operator was applied to a value that is not a bool
+    NotABoolean {
+        operator: &'static str,
+        value: Value,
+    },
+    /// Unary minus was applied to a value that is not a number
+    NotANumber {
+        operator: &'static str,
+        value: Value,
+    },
+    /// A step ran more instructions than the runtime's step budget allows
+    StepBudgetExceeded { budget: usize },
+    /// The bytecode is malformed, e.g. it reads past the stack or jumps out of
+    /// range. Comes from a damaged precompiled chunk or a compiler bug.
+    CorruptChunk { detail: String },
+    /// A string table entry cannot be used for the text it translates
+    BadTranslation { id: String, detail: String },
+    /// A host callback panicked while the runtime was catching host panics.
+    /// `name` is the extern variable being read, or `line filter`.
+    HostCallbackPanicked { name: String },
 }
 
 impl std::fmt::Display for RuntimeError {
@@ -24,19 +72,81 @@ impl std::fmt::Display for RuntimeError {
                     "select_and_continue called but VM is not waiting for a choice"
                 )
             }
-            RuntimeError::InvalidChoiceIndex { index, count } => {
+            RuntimeError::InvalidChoiceIndex { index, count, .. } => {
                 write!(
                     f,
                     "choice index {} out of bounds (only {} choices)",
                     index, count
                 )
             }
+            RuntimeError::NoDefaultChoice => {
+                write!(f, "the choices on offer have no default")
+            }
             RuntimeError::MissingSaveVariable { name } => {
                 write!(f, "save variable '{}' not found in storage", name)
             }
             RuntimeError::MissingExternVariable { name } => {
                 write!(f, "extern variable '{}' not found in host state", name)
             }
+            RuntimeError::NonNumericFormat { value, format } => {
+                write!(
+                    f,
+                    "cannot apply number format '{}' to non-number value {}",
+                    format,
+                    value.describe()
+                )
+            }
+            RuntimeError::TypeMismatch {
+                operator,
+                left,
+                right,
+            } => {
+                write!(
+                    f,
+                    "cannot apply '{}' to {} and {}",
+                    operator,
+                    left.describe(),
+                    right.describe()
+                )
+            }
+            RuntimeError::DivisionByZero => write!(f, "division by zero"),
+            RuntimeError::InvalidRandomRange { min, max } => {
+                write!(
+                    f,
+                    "random range {} to {} contains no whole number",
+                    min, max
+                )
+            }
+            RuntimeError::NotABoolean { operator, value } => {
+                write!(
+                    f,
+                    "'{}' expects a boolean, found {}",
+                    operator,
+                    value.describe()
+                )
+            }
+            RuntimeError::NotANumber { operator, value } => {
+                write!(
+                    f,
+                    "'{}' expects a number, found {}",
+                    operator,
+                    value.describe()
+                )
+            }
+            RuntimeError::StepBudgetExceeded { budget } => {
+                write!(
+                    f,
+                    "step ran more than {} instructions without pausing",
+                    budget
+                )
+            }
+            RuntimeError::CorruptChunk { detail } => write!(f, "corrupt bytecode: {}", detail),
+            RuntimeError::BadTranslation { id, detail } => {
+                write!(f, "translation '{}' {}", id, detail)
+            }
+            RuntimeError::HostCallbackPanicked { name } => {
+                write!(f, "host callback for '{}' panicked", name)
+            }
         }
     }
 }
@@ -55,14 +165,40 @@ impl IntoDiagnostic for RuntimeError {
                 notes: vec!["This is an API usage error - check your game logic".to_string()],
                 suggestions: vec![],
             },
-            RuntimeError::InvalidChoiceIndex { index, count } => Diagnostic {
+            RuntimeError::InvalidChoiceIndex {
+                index,
+                count,
+                choices,
+            } => {
+                let mut notes =
+                    vec!["Check that the choice index is within the valid range".to_string()];
+                if !choices.is_empty() {
+                    let listed: Vec<String> = choices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, text)| format!("{}: {}", i, text))
+                        .collect();
+                    notes.push(format!("available choices: {}", listed.join(", ")));
+                }
+                Diagnostic {
+                    severity: Severity::Error,
+                    message: format!(
+                        "choice index {} out of bounds (only {} choices available)",
+                        index, count
+                    ),
+                    labels: vec![],
+                    notes,
+                    suggestions: vec![],
+                }
+            }
+            RuntimeError::NoDefaultChoice => Diagnostic {
                 severity: Severity::Error,
-                message: format!(
-                    "choice index {} out of bounds (only {} choices available)",
-                    index, count
-                ),
+                message: "the choices on offer have no default".to_string(),
                 labels: vec![],
-                notes: vec!["Check that the choice index is within the valid range".to_string()],
+                notes: vec![
+                    "Mark the fallback choice with #default, or check `default_choice` before selecting it"
+                        .to_string(),
+                ],
                 suggestions: vec![],
             },
             RuntimeError::MissingSaveVariable { name } => Diagnostic {
@@ -85,13 +221,216 @@ impl IntoDiagnostic for RuntimeError {
                 ],
                 suggestions: vec![],
             },
+            RuntimeError::NonNumericFormat { value, format } => Diagnostic {
+                severity: Severity::Error,
+                message: format!(
+                    "cannot apply number format '{}' to non-number value {}",
+                    format,
+                    value.describe()
+                ),
+                labels: vec![],
+                notes: vec![
+                    "Number formats only apply to numbers".to_string(),
+                    "Remove the format, or make sure the variable holds a number".to_string(),
+                ],
+                suggestions: vec![],
+            },
+            RuntimeError::TypeMismatch {
+                operator,
+                left,
+                right,
+            } => Diagnostic {
+                severity: Severity::Error,
+                message: format!(
+                    "cannot apply '{}' to {} and {}",
+                    operator,
+                    left.describe(),
+                    right.describe()
+                ),
+                labels: vec![],
+                notes: vec!["Arithmetic only works on numbers".to_string()],
+                suggestions: vec![],
+            },
+            RuntimeError::DivisionByZero => Diagnostic {
+                severity: Severity::Error,
+                message: "division by zero".to_string(),
+                labels: vec![],
+                notes: vec!["Check that the divisor can never be zero".to_string()],
+                suggestions: vec![],
+            },
+            RuntimeError::InvalidRandomRange { min, max } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("random range {} to {} contains no whole number", min, max),
+                labels: vec![],
+                notes: vec!["The first argument to 'random' must not be greater than the second".to_string()],
+                suggestions: vec![],
+            },
+            RuntimeError::NotABoolean { operator, value } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("'{}' expects a boolean, found {}", operator, value.describe()),
+                labels: vec![],
+                notes: vec![
+                    "'and', 'or', and 'not' only work on true and false".to_string(),
+                ],
+                suggestions: vec![],
+            },
+            RuntimeError::NotANumber { operator, value } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("'{}' expects a number, found {}", operator, value.describe()),
+                labels: vec![],
+                notes: vec!["Arithmetic only works on numbers".to_string()],
+                suggestions: vec![],
+            },
+            RuntimeError::StepBudgetExceeded { budget } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("step ran more than {} instructions without pausing", budget),
+                labels: vec![],
+                notes: vec![
+                    "The script may be stuck in a loop; otherwise raise the step budget".to_string(),
+                ],
+                suggestions: vec![],
+            },
+            RuntimeError::CorruptChunk { detail } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("corrupt bytecode: {}", detail),
+                labels: vec![],
+                notes: vec![
+                    "Recompile the script; if the error persists, this is a compiler bug".to_string(),
+                ],
+                suggestions: vec![],
+            },
+            RuntimeError::BadTranslation { id, detail } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("translation '{}' {}", id, detail),
+                labels: vec![],
+                notes: vec![
+                    "Placeholders in a translation may only use variables the original text interpolates".to_string(),
+                ],
+                suggestions: vec![],
+            },
+            RuntimeError::HostCallbackPanicked { name } => Diagnostic {
+                severity: Severity::Error,
+                message: format!("host callback for '{}' panicked", name),
+                labels: vec![],
+                notes: vec![
+                    "The panic message was printed by the panic hook; host state may be left half-updated".to_string(),
+                ],
+                suggestions: vec![],
+            },
         }
     }
 }
 
+/// Run the host callback `call`, turning a panic into `HostCallbackPanicked`
+/// for `name` when `catch` is set.
+///
+/// The callback is asserted unwind safe: after a caught panic the dialogue
+/// only reports the error, so nothing reads the VM's own state half-updated.
+/// Any state the host shares with the callback is the host's to recover.
+pub(crate) fn guard_host_call<T>(
+    catch: bool,
+    name: &str,
+    call: impl FnOnce() -> T,
+) -> Result<T, RuntimeError> {
+    if !catch {
+        return Ok(call());
+    }
+    panic::catch_unwind(AssertUnwindSafe(call)).map_err(|_| RuntimeError::HostCallbackPanicked {
+        name: name.to_string(),
+    })
+}
+
 pub(crate) enum StepResult {
     Line(String),
     Choice(Vec<String>),
+    Wait(f64),
+    Done,
+}
+
+/// How much of a script a playthrough has reached, as reported by
+/// `Runtime::coverage`.
+///
+/// A line counts once it has been shown and a choice once it has been
+/// selected, so a choice that was offered but never picked is not covered.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Coverage {
+    /// Lines and choices reached at least once
+    pub visited_nodes: usize,
+    /// Lines and choices in the script
+    pub total_nodes: usize,
+}
+
+/// A VM stepping with an async host. Dropping it, even partway through a
+/// step, goes back to reading the host directly.
+#[cfg(feature = "async")]
+pub(crate) struct AsyncStep<'a> {
+    vm: &'a mut VM,
+}
+
+#[cfg(feature = "async")]
+impl AsyncStep<'_> {
+    /// Run until the dialogue pauses, awaiting `host` for each host variable
+    /// the step reads. `started` is the result of the step's first run.
+    pub(crate) async fn finish<H: AsyncHostState>(
+        &mut self,
+        started: Result<StepResult, RuntimeError>,
+        host: &H,
+    ) -> Result<StepResult, RuntimeError> {
+        let mut result = started;
+        while result.is_err()
+            && let Some(name) = self.vm.awaiting.take()
+        {
+            let lookup = host.lookup(&name);
+            let value = guard_host_future(self.vm.catch_host_panics, &name, lookup).await?;
+            self.vm.fetched.get_or_insert_default().insert(name, value);
+            result = self.vm.run();
+        }
+        result
+    }
+
+    pub(crate) fn step(&mut self) -> Result<StepResult, RuntimeError> {
+        self.vm.step()
+    }
+
+    pub(crate) fn select_and_continue(&mut self, index: usize) -> Result<StepResult, RuntimeError> {
+        self.vm.select_and_continue(index)
+    }
+}
+
+#[cfg(feature = "async")]
+impl Drop for AsyncStep<'_> {
+    fn drop(&mut self) {
+        self.vm.fetched = None;
+        self.vm.awaiting = None;
+    }
+}
+
+/// Await `future`, failing with `HostCallbackPanicked` instead of unwinding
+/// when polling it panics and `catch` is set, as `guard_host_call` does for
+/// a single call.
+#[cfg(feature = "async")]
+async fn guard_host_future<F: Future>(
+    catch: bool,
+    name: &str,
+    future: F,
+) -> Result<F::Output, RuntimeError> {
+    let mut future = std::pin::pin!(future);
+    std::future::poll_fn(
+        |cx| match guard_host_call(catch, name, || future.as_mut().poll(cx)) {
+            Ok(Poll::Ready(value)) => Poll::Ready(Ok(value)),
+            Ok(Poll::Pending) => Poll::Pending,
+            Err(err) => Poll::Ready(Err(err)),
+        },
+    )
+    .await
+}
+
+/// What the dialogue will stop on next, as reported by `Runtime::peek_next`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NextKind {
+    Line,
+    Choice,
+    Wait,
     Done,
 }
 
@@ -101,6 +440,26 @@ pub struct VM {
     stack: Vec<Value>,
     storage: Arc<dyn VariableStorage>,
     host: Arc<dyn HostState>,
+    /// How many times each line and choice has been reached.
+    visits: HashMap<NodeId, u32>,
+    /// Parsed string table entries for the texts in this chunk, by id
+    translations: Arc<HashMap<String, Vec<TemplatePart>>>,
+    /// Values of the expressions interpolated in recent texts, by capture slot
+    captures: HashMap<usize, Value>,
+    /// Source of `random(min, max)` values
+    rng: Rng,
+    /// Most instructions a single step may run, if capped
+    step_budget: Option<usize>,
+    /// Turn panics in host lookups into `HostCallbackPanicked`
+    catch_host_panics: bool,
+    /// Host values fetched so far in the async step in progress, which reads
+    /// the host through these instead. `None` outside an async step.
+    #[cfg(feature = "async")]
+    fetched: Option<HashMap<String, Option<Value>>>,
+    /// The host variable the async step in progress stopped for. The step
+    /// fails with `MissingExternVariable` until the host has answered.
+    #[cfg(feature = "async")]
+    awaiting: Option<String>,
 }
 
 impl std::fmt::Debug for VM {
@@ -109,6 +468,7 @@ impl std::fmt::Debug for VM {
             .field("chunk", &self.chunk)
             .field("ip", &self.ip)
             .field("stack", &self.stack)
+            .field("visits", &self.visits)
             .finish_non_exhaustive()
     }
 }
@@ -121,6 +481,289 @@ impl VM {
             stack: Vec::new(),
             storage,
             host,
+            visits: HashMap::new(),
+            translations: Arc::default(),
+            captures: HashMap::new(),
+            rng: Rng::from_entropy(),
+            step_budget: None,
+            catch_host_panics: false,
+            #[cfg(feature = "async")]
+            fetched: None,
+            #[cfg(feature = "async")]
+            awaiting: None,
+        }
+    }
+
+    /// Draw `random(min, max)` values from a generator seeded with `seed`, so
+    /// the same seed and path always produce the same values.
+    pub(crate) fn set_seed(&mut self, seed: u64) {
+        self.rng = Rng::seeded(seed);
+    }
+
+    /// Fail a step with `StepBudgetExceeded` once it has run `budget`
+    /// instructions without reaching a line, choice, wait, or the end.
+    pub(crate) fn set_step_budget(&mut self, budget: usize) {
+        self.step_budget = Some(budget);
+    }
+
+    /// Fail with `HostCallbackPanicked` instead of unwinding when a host
+    /// lookup panics.
+    pub(crate) fn set_catch_host_panics(&mut self, catch: bool) {
+        self.catch_host_panics = catch;
+    }
+
+    /// A copy of this VM at the same point, using `storage` for save variables.
+    pub(crate) fn fork(&self, storage: Arc<dyn VariableStorage>) -> Self {
+        Self {
+            chunk: self.chunk.clone(),
+            ip: self.ip,
+            stack: self.stack.clone(),
+            storage,
+            host: Arc::clone(&self.host),
+            visits: self.visits.clone(),
+            translations: Arc::clone(&self.translations),
+            captures: self.captures.clone(),
+            rng: self.rng.clone(),
+            step_budget: self.step_budget,
+            catch_host_panics: self.catch_host_panics,
+            #[cfg(feature = "async")]
+            fetched: None,
+            #[cfg(feature = "async")]
+            awaiting: None,
+        }
+    }
+
+    /// Render texts from `table`, keyed by the ids `extract_strings` reports.
+    ///
+    /// Entries for ids this chunk does not use are ignored. Each used entry is
+    /// checked up front, so a bad translation fails here rather than mid-dialogue.
+    pub(crate) fn set_string_table(
+        &mut self,
+        table: HashMap<String, String>,
+    ) -> Result<(), RuntimeError> {
+        let keys = self
+            .chunk
+            .line_keys
+            .values()
+            .chain(self.chunk.choice_keys.values().flatten());
+        let mut translations = HashMap::new();
+        for key in keys {
+            let Some(template) = table.get(&key.id) else {
+                continue;
+            };
+            let bad = |detail: String| RuntimeError::BadTranslation {
+                id: key.id.clone(),
+                detail,
+            };
+            let parts = parse_template(template).map_err(bad)?;
+            for part in &parts {
+                if let TemplatePart::Var { name, .. } = part
+                    && !key.vars.iter().any(|(known, _)| known == name)
+                {
+                    return Err(bad(format!(
+                        "uses '{}', which the original text does not interpolate",
+                        name
+                    )));
+                }
+            }
+            translations.insert(key.id.clone(), parts);
+        }
+        self.translations = Arc::new(translations);
+        Ok(())
+    }
+
+    /// The translation of the line just shown, if the string table has one.
+    pub(crate) fn translate_current_line(&self) -> Result<Option<String>, RuntimeError> {
+        let Some(offset) = self.ip.checked_sub(1) else {
+            return Ok(None);
+        };
+        match self.chunk.line_keys.get(&offset) {
+            Some(key) => self.translate(key),
+            None => Ok(None),
+        }
+    }
+
+    /// Translations of the choices the VM is waiting on, `None` for each
+    /// choice the string table does not cover.
+    pub(crate) fn translate_current_choices(&self) -> Result<Vec<Option<String>>, RuntimeError> {
+        let Some(keys) = self.chunk.choice_keys.get(&self.ip) else {
+            return Ok(Vec::new());
+        };
+        keys.iter().map(|key| self.translate(key)).collect()
+    }
+
+    fn translate(&self, key: &TextKey) -> Result<Option<String>, RuntimeError> {
+        let Some(parts) = self.translations.get(&key.id) else {
+            return Ok(None);
+        };
+        let mut text = String::new();
+        for part in parts {
+            match part {
+                TemplatePart::Text(literal) => text.push_str(literal),
+                TemplatePart::Var { name, format } => {
+                    // Checked against the key's variables in `set_string_table`
+                    let Some((_, source)) = key.vars.iter().find(|(known, _)| known == name) else {
+                        continue;
+                    };
+                    let value = self.read_source(source)?;
+                    match (format, value) {
+                        (Some(format), Value::Number(n)) => text.push_str(&format.apply(n)),
+                        (Some(format), value) => {
+                            return Err(RuntimeError::NonNumericFormat {
+                                value,
+                                format: *format,
+                            });
+                        }
+                        (None, value) => text.push_str(&value.to_string_value()),
+                    }
+                }
+            }
+        }
+        Ok(Some(text))
+    }
+
+    fn read_source(&self, source: &VarSource) -> Result<Value, RuntimeError> {
+        match source {
+            VarSource::Local { slot } => match self.stack.get(*slot) {
+                Some(value) => Ok(value.clone()),
+                None => Err(self.corrupt(format_args!("local slot {} out of range", slot))),
+            },
+            VarSource::Storage { name } => self
+                .storage
+                .get(name)
+                .ok_or_else(|| RuntimeError::MissingSaveVariable { name: name.clone() }),
+            VarSource::Host { name } => self.read_host(name),
+            VarSource::Const { value } => Ok(value.clone()),
+            VarSource::Capture { slot } => match self.captures.get(slot) {
+                Some(value) => Ok(value.clone()),
+                None => Err(self.corrupt(format_args!("capture slot {} never set", slot))),
+            },
+        }
+    }
+
+    /// A host variable, or the host's default for it if it has no value.
+    fn read_host(&self, name: &str) -> Result<Value, RuntimeError> {
+        guard_host_call(self.catch_host_panics, name, || {
+            self.host_value(name)
+                .or_else(|| self.host.default_for(name))
+        })?
+        .ok_or_else(|| RuntimeError::MissingExternVariable {
+            name: name.to_string(),
+        })
+    }
+
+    /// The host's value for `name`, taken from what the async step in
+    /// progress has fetched if there is one.
+    fn host_value(&self, name: &str) -> Option<Value> {
+        #[cfg(feature = "async")]
+        if let Some(fetched) = &self.fetched {
+            return fetched.get(name).cloned().flatten();
+        }
+        self.host.lookup(name)
+    }
+
+    /// Start a step that hands host lookups back to the caller, to be
+    /// answered asynchronously. The step ends when the guard is dropped.
+    #[cfg(feature = "async")]
+    pub(crate) fn async_step(&mut self) -> AsyncStep<'_> {
+        self.fetched = Some(HashMap::new());
+        AsyncStep { vm: self }
+    }
+
+    /// Offset of the next instruction to run.
+    pub(crate) fn ip(&self) -> usize {
+        self.ip
+    }
+
+    /// The compiled chunk this VM executes.
+    pub(crate) fn chunk(&self) -> &Chunk {
+        &self.chunk
+    }
+
+    /// How many times the line or choice with this id has been reached.
+    pub(crate) fn visit_count(&self, node: NodeId) -> u32 {
+        self.visits.get(&node).copied().unwrap_or(0)
+    }
+
+    /// The line the VM last paused on. Only meaningful after `step()` returns `Line`.
+    pub(crate) fn current_line_node(&self) -> Option<NodeId> {
+        let offset = self.ip.checked_sub(1)?;
+        self.chunk.line_nodes.get(&offset).copied()
+    }
+
+    /// The choices the VM is waiting on. Only meaningful after `step()` returns `Choice`.
+    pub(crate) fn current_choice_nodes(&self) -> &[NodeId] {
+        self.chunk
+            .choice_nodes
+            .get(&self.ip)
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Index of the `#default` choice in the set the VM is waiting on.
+    pub(crate) fn default_choice(&self) -> Option<usize> {
+        match self.chunk.code.get(self.ip) {
+            Some(Instruction::ChoiceSet { default, .. }) => *default,
+            _ => None,
+        }
+    }
+
+    /// Tags of the choice at `index` in the set the VM is waiting on.
+    pub(crate) fn choice_tags(&self, index: usize) -> &[String] {
+        self.chunk
+            .choice_tags
+            .get(&self.ip)
+            .and_then(|tags| tags.get(index))
+            .map(Vec::as_slice)
+            .unwrap_or(&[])
+    }
+
+    /// Lines and choices in the chunk that have not been reached yet.
+    pub(crate) fn unvisited_nodes(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.chunk
+            .node_spans
+            .keys()
+            .copied()
+            .filter(|node| !self.visits.contains_key(node))
+    }
+
+    /// Go back to the first instruction with no locals and no visits, as if
+    /// newly created. Storage, the host, and the string table are kept.
+    pub(crate) fn reset(&mut self) {
+        self.ip = 0;
+        self.stack.clear();
+        self.captures.clear();
+        self.visits.clear();
+    }
+
+    fn record_visit(&mut self, node: NodeId) {
+        *self.visits.entry(node).or_insert(0) += 1;
+    }
+
+    /// The next instruction that would pause the dialogue, found by reading
+    /// ahead without executing anything.
+    ///
+    /// Nothing the VM skips over can change where it stops: only `Jump` and
+    /// `ShortCircuit` move the instruction pointer, and a short circuit only
+    /// skips to the end of its own expression.
+    pub(crate) fn peek_next(&self) -> NextKind {
+        let mut ip = self.ip;
+        let mut jumped = HashSet::new();
+        loop {
+            match self.chunk.code.get(ip) {
+                Some(Instruction::Line) => return NextKind::Line,
+                Some(Instruction::ChoiceSet { .. }) => return NextKind::Choice,
+                Some(Instruction::Wait { .. }) => return NextKind::Wait,
+                Some(Instruction::Return) | None => return NextKind::Done,
+                Some(Instruction::Jump { target }) => {
+                    // A loop with no pause in it never stops anywhere
+                    if !jumped.insert(ip) {
+                        return NextKind::Done;
+                    }
+                    ip = *target;
+                }
+                Some(_) => ip += 1,
+            }
         }
     }
 
@@ -140,18 +783,104 @@ impl VM {
         }
     }
 
+    /// Pop two numbers, apply `op`, and push the result.
+    fn arithmetic(
+        &mut self,
+        operator: &'static str,
+        op: impl FnOnce(f64, f64) -> Result<f64, RuntimeError>,
+    ) -> Result<(), RuntimeError> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        match (&left, &right) {
+            (Value::Number(a), Value::Number(b)) => {
+                self.stack.push(Value::Number(op(*a, *b)?));
+                Ok(())
+            }
+            _ => Err(RuntimeError::TypeMismatch {
+                operator,
+                left,
+                right,
+            }),
+        }
+    }
+
+    /// Pop the top of the stack. The compiler never underflows it, so an empty
+    /// stack means the chunk is corrupt.
+    fn pop(&mut self) -> Result<Value, RuntimeError> {
+        self.stack
+            .pop()
+            .ok_or_else(|| self.corrupt(format_args!("stack underflow")))
+    }
+
+    /// The bool on top of the stack, left in place. Anything else is a type
+    /// error for `op`.
+    fn check_bool(&self, op: LogicOp) -> Result<bool, RuntimeError> {
+        match self.stack.last() {
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(value) => Err(RuntimeError::NotABoolean {
+                operator: op.as_str(),
+                value: value.clone(),
+            }),
+            None => Err(self.corrupt(format_args!("stack underflow"))),
+        }
+    }
+
+    /// A `CorruptChunk` error for the instruction that just ran.
+    fn corrupt(&self, detail: std::fmt::Arguments) -> RuntimeError {
+        RuntimeError::CorruptChunk {
+            detail: format!("{} at instruction {}", detail, self.ip.saturating_sub(1)),
+        }
+    }
+
+    /// Pop two numbers, compare them with `op`, and push the result.
+    fn compare(
+        &mut self,
+        operator: &'static str,
+        op: fn(&f64, &f64) -> bool,
+    ) -> Result<(), RuntimeError> {
+        let right = self.pop()?;
+        let left = self.pop()?;
+        match (&left, &right) {
+            (Value::Number(a), Value::Number(b)) => {
+                self.stack.push(Value::Bool(op(a, b)));
+                Ok(())
+            }
+            _ => Err(RuntimeError::TypeMismatch {
+                operator,
+                left,
+                right,
+            }),
+        }
+    }
+
     /// Continue execution after user selects a choice.
     /// Call this after `step()` returns `Choice`. The ip should be pointing at ChoiceSet.
     pub(crate) fn select_and_continue(&mut self, index: usize) -> Result<StepResult, RuntimeError> {
         // Read ChoiceSet to get targets
-        let instruction = self.chunk.code[self.ip].clone();
+        let instruction = self.chunk.code.get(self.ip).cloned();
 
-        if let Instruction::ChoiceSet { count, targets } = instruction {
+        if let Some(Instruction::ChoiceSet { count, targets, .. }) = instruction {
             if index >= count {
-                return Err(RuntimeError::InvalidChoiceIndex { index, count });
+                return Err(RuntimeError::InvalidChoiceIndex {
+                    index,
+                    count,
+                    choices: Vec::new(),
+                });
             }
-            self.ip += 1;
-            self.ip = targets[index];
+            let Some(&target) = targets.get(index) else {
+                return Err(RuntimeError::CorruptChunk {
+                    detail: format!("choice {} has no target at instruction {}", index, self.ip),
+                });
+            };
+            if let Some(&node) = self
+                .chunk
+                .choice_nodes
+                .get(&self.ip)
+                .and_then(|nodes| nodes.get(index))
+            {
+                self.record_visit(node);
+            }
+            self.ip = target;
         } else {
             return Err(RuntimeError::NotAtChoice);
         }
@@ -160,63 +889,195 @@ impl VM {
         self.run()
     }
 
-    /// Execute until we hit a pause point (Line, Choice) or Done.
+    /// Execute until we hit a pause point (Line, Choice, Wait) or Done.
     pub(crate) fn step(&mut self) -> Result<StepResult, RuntimeError> {
         self.run()
     }
 
     /// Core execution loop.
     fn run(&mut self) -> Result<StepResult, RuntimeError> {
+        let mut executed = 0;
         loop {
-            let instruction = self.chunk.code[self.ip].clone();
+            if let Some(budget) = self.step_budget {
+                if executed == budget {
+                    return Err(RuntimeError::StepBudgetExceeded { budget });
+                }
+                executed += 1;
+            }
+            let Some(instruction) = self.chunk.code.get(self.ip).cloned() else {
+                return Err(RuntimeError::CorruptChunk {
+                    detail: format!("instruction {} is past the end of the code", self.ip),
+                });
+            };
             self.ip += 1;
 
             match instruction {
                 Instruction::Constant { index } => {
-                    let value = self.chunk.constants[index].clone();
+                    let Some(value) = self.chunk.constants.get(index).cloned() else {
+                        return Err(self.corrupt(format_args!("constant {} out of range", index)));
+                    };
                     self.stack.push(value);
                 }
                 Instruction::GetLocal { slot } => {
-                    let value = self.stack[slot].clone();
+                    let Some(value) = self.stack.get(slot).cloned() else {
+                        return Err(self.corrupt(format_args!("local slot {} out of range", slot)));
+                    };
                     self.stack.push(value);
                 }
                 Instruction::SetLocal { slot } => {
-                    let value = self.stack.pop().expect("stack underflow: compiler bug");
+                    let value = self.pop()?;
+                    if slot >= self.stack.len() {
+                        return Err(self.corrupt(format_args!("local slot {} out of range", slot)));
+                    }
                     self.stack[slot] = value;
                 }
+                Instruction::DeclareLocal { slot } => {
+                    let value = self.pop()?;
+                    if slot > self.stack.len() {
+                        return Err(self.corrupt(format_args!("local slot {} out of range", slot)));
+                    }
+                    self.stack.truncate(slot);
+                    self.stack.push(value);
+                }
+                Instruction::Add => self.arithmetic("+", |a, b| Ok(a + b))?,
+                Instruction::Subtract => self.arithmetic("-", |a, b| Ok(a - b))?,
+                Instruction::Multiply => self.arithmetic("*", |a, b| Ok(a * b))?,
+                Instruction::Divide => self.arithmetic("/", |a, b| {
+                    if b == 0.0 {
+                        Err(RuntimeError::DivisionByZero)
+                    } else {
+                        Ok(a / b)
+                    }
+                })?,
+                Instruction::Equal => {
+                    let right = self.pop()?;
+                    let left = self.pop()?;
+                    self.stack.push(Value::Bool(left == right));
+                }
+                Instruction::NotEqual => {
+                    let right = self.pop()?;
+                    let left = self.pop()?;
+                    self.stack.push(Value::Bool(left != right));
+                }
+                Instruction::Less => self.compare("<", f64::lt)?,
+                Instruction::LessEqual => self.compare("<=", f64::le)?,
+                Instruction::Greater => self.compare(">", f64::gt)?,
+                Instruction::GreaterEqual => self.compare(">=", f64::ge)?,
+                Instruction::Random => {
+                    let max = self.pop()?;
+                    let min = self.pop()?;
+                    let (Value::Number(min), Value::Number(max)) = (&min, &max) else {
+                        return Err(RuntimeError::TypeMismatch {
+                            operator: "random",
+                            left: min,
+                            right: max,
+                        });
+                    };
+                    let (low, high) = (min.ceil(), max.floor());
+                    if low.is_nan() || high.is_nan() || low > high {
+                        return Err(RuntimeError::InvalidRandomRange {
+                            min: *min,
+                            max: *max,
+                        });
+                    }
+                    let roll = self.rng.range(low as i64, high as i64);
+                    self.stack.push(Value::Number(roll as f64));
+                }
+                Instruction::Not => match self.pop()? {
+                    Value::Bool(b) => self.stack.push(Value::Bool(!b)),
+                    value => {
+                        return Err(RuntimeError::NotABoolean {
+                            operator: "not",
+                            value,
+                        });
+                    }
+                },
+                Instruction::Negate => match self.pop()? {
+                    Value::Number(n) => self.stack.push(Value::Number(-n)),
+                    value => {
+                        return Err(RuntimeError::NotANumber {
+                            operator: "-",
+                            value,
+                        });
+                    }
+                },
+                Instruction::ShortCircuit { op, target } => {
+                    let settled = self.check_bool(op)?;
+                    if settled == op.settled_by() {
+                        self.ip = target;
+                    } else {
+                        self.stack.pop();
+                    }
+                }
+                Instruction::CheckBool { op } => {
+                    self.check_bool(op)?;
+                }
+                Instruction::Capture { slot } => {
+                    let Some(value) = self.stack.last() else {
+                        return Err(self.corrupt(format_args!("stack underflow")));
+                    };
+                    self.captures.insert(slot, value.clone());
+                }
+                Instruction::Format { format } => {
+                    let value = self.pop()?;
+                    match value {
+                        Value::Number(n) => self.stack.push(Value::String(format.apply(n))),
+                        value => return Err(RuntimeError::NonNumericFormat { value, format }),
+                    }
+                }
                 Instruction::Concat { count } => {
                     // Pop `count` values and concatenate as strings
-                    let start = self.stack.len() - count;
+                    let Some(start) = self.stack.len().checked_sub(count) else {
+                        return Err(self.corrupt(format_args!("stack underflow")));
+                    };
                     let mut result = String::new();
-                    for i in start..self.stack.len() {
-                        result.push_str(&self.stack[i].to_string_value());
+                    for value in &self.stack[start..] {
+                        match value {
+                            Value::String(s) => result.push_str(s),
+                            value => result.push_str(&value.to_string_value()),
+                        }
                     }
                     self.stack.truncate(start);
                     self.stack.push(Value::String(result));
                 }
                 Instruction::Line => {
-                    let value = self.stack.pop().expect("stack underflow: compiler bug");
-                    let text = value.to_string_value();
+                    if let Some(node) = self.current_line_node() {
+                        self.record_visit(node);
+                    }
+                    // A plain line's text is the constant pushed just before,
+                    // so it is moved out rather than copied again
+                    let value = self.pop()?;
+                    let text = match self.translate_current_line()? {
+                        Some(translated) => translated,
+                        None => value.into_string_value(),
+                    };
                     return Ok(StepResult::Line(text));
                 }
+                Instruction::Wait { seconds } => {
+                    return Ok(StepResult::Wait(seconds));
+                }
                 Instruction::ChoiceSet { count, .. } => {
                     // Pop choice texts from stack
                     let mut choices = Vec::with_capacity(count);
                     for _ in 0..count {
-                        let value = self.stack.pop().expect("stack underflow: compiler bug");
-                        let text = value.to_string_value();
-                        choices.push(text);
+                        choices.push(self.pop()?.into_string_value());
                     }
                     choices.reverse();
                     // Back up ip so select_and_continue can read ChoiceSet for targets
                     self.ip -= 1;
+                    let translated = self.translate_current_choices()?;
+                    for (choice, translation) in choices.iter_mut().zip(translated) {
+                        if let Some(translation) = translation {
+                            *choice = translation;
+                        }
+                    }
                     return Ok(StepResult::Choice(choices));
                 }
                 Instruction::Jump { target } => {
                     self.ip = target;
                 }
                 Instruction::InitStorage { name } => {
-                    let value = self.stack.pop().expect("stack underflow: compiler bug");
+                    let value = self.pop()?;
                     self.storage.initialize_if_absent(&name, value);
                 }
                 Instruction::GetStorage { name } => match self.storage.get(&name) {
@@ -224,13 +1085,22 @@ impl VM {
                     None => return Err(RuntimeError::MissingSaveVariable { name }),
                 },
                 Instruction::SetStorage { name } => {
-                    let value = self.stack.pop().expect("stack underflow: compiler bug");
+                    let value = self.pop()?;
                     self.storage.set(&name, value);
                 }
-                Instruction::GetHost { name } => match self.host.lookup(&name) {
-                    Some(value) => self.stack.push(value),
-                    None => return Err(RuntimeError::MissingExternVariable { name }),
-                },
+                Instruction::GetHost { name } => {
+                    #[cfg(feature = "async")]
+                    if let Some(fetched) = &self.fetched
+                        && !fetched.contains_key(&name)
+                    {
+                        // Rerun this instruction once the host has answered
+                        self.ip -= 1;
+                        self.awaiting = Some(name.clone());
+                        return Err(RuntimeError::MissingExternVariable { name });
+                    }
+                    let value = self.read_host(&name)?;
+                    self.stack.push(value);
+                }
                 Instruction::Return => {
                     // Note: stack may have locals remaining, that's OK
                     return Ok(StepResult::Done);
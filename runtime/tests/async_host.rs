@@ -0,0 +1,84 @@
+//! Async host tests - extern values fetched without blocking.
+
+#![cfg(feature = "async")]
+
+mod support;
+
+use bobbin_runtime::{
+    AsyncHostState, HostState, RuntimeBuilder, RuntimeError, Value, VariableStorage,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use support::{EmptyHostState, MemoryStorage, runtime};
+
+/// A host whose values arrive after a delay, like a network round trip.
+struct RemoteHost {
+    values: HashMap<&'static str, Value>,
+}
+
+impl AsyncHostState for RemoteHost {
+    async fn lookup(&self, name: &str) -> Option<Value> {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        self.values.get(name).cloned()
+    }
+}
+
+#[tokio::test]
+async fn dialogue_proceeds_with_async_host_values() {
+    let source = "extern mood\nHello.\nYou feel {mood}.\n- Ask again\n    Still {mood}.\n";
+    let host = RemoteHost {
+        values: HashMap::from([("mood", Value::String("calm".to_string()))]),
+    };
+    let mut runtime = runtime(source);
+    assert_eq!(runtime.current_line(), "Hello.");
+
+    runtime.advance_async(&host).await.unwrap();
+    assert_eq!(runtime.current_line(), "You feel calm.");
+
+    runtime.advance_async(&host).await.unwrap();
+    assert_eq!(runtime.current_choices(), ["Ask again"]);
+    runtime.select_choice_async(0, &host).await.unwrap();
+    assert_eq!(runtime.current_line(), "Still calm.");
+}
+
+#[tokio::test]
+async fn missing_async_value_is_reported() {
+    let host = RemoteHost {
+        values: HashMap::new(),
+    };
+    let mut runtime = runtime("extern mood\nHello.\nYou feel {mood}.\n");
+
+    let err = runtime.advance_async(&host).await.unwrap_err();
+    assert!(matches!(err, RuntimeError::MissingExternVariable { ref name } if name == "mood"));
+
+    // A failed step leaves the line on show
+    assert_eq!(runtime.current_line(), "Hello.");
+}
+
+/// A host whose lookups panic partway through, after the first await.
+struct PanickingRemoteHost;
+
+impl AsyncHostState for PanickingRemoteHost {
+    async fn lookup(&self, name: &str) -> Option<Value> {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        panic!("no such host variable: {}", name)
+    }
+}
+
+#[tokio::test]
+async fn async_host_panics_are_caught_when_asked() {
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = RuntimeBuilder::new(storage, host)
+        .catch_host_panics(true)
+        .build("extern mood\nHello.\nYou feel {mood}.\n")
+        .unwrap();
+
+    let err = runtime
+        .advance_async(&PanickingRemoteHost)
+        .await
+        .unwrap_err();
+    assert!(matches!(err, RuntimeError::HostCallbackPanicked { ref name } if name == "mood"));
+    assert_eq!(runtime.current_line(), "Hello.");
+}
@@ -2,7 +2,12 @@
 
 mod support;
 
-use bobbin_runtime::{HostState, Runtime, VariableStorage};
+use bobbin_runtime::{
+    BobbinError, HostState, NextKind, Runtime, RuntimeBuilder, RuntimeError, Value,
+    VariableStorage, extract_strings,
+};
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
 use std::sync::Arc;
 use support::{EmptyHostState, MemoryStorage};
 
@@ -16,6 +21,258 @@ fn empty_lines() {
     support::run_output_test(&support::cases_dir().join("basic/empty_lines.bobbin"));
 }
 
+#[test]
+fn continuation() {
+    support::run_output_test(&support::cases_dir().join("basic/continuation.bobbin"));
+}
+
+#[test]
+fn trailing_comments() {
+    support::run_output_test(&support::cases_dir().join("basic/comments.bobbin"));
+}
+
+#[test]
+fn wait_pauses_between_lines() {
+    support::run_trace_test(
+        &support::cases_dir().join("basic/wait.bobbin"),
+        "pause_then_continue",
+    );
+}
+
+#[test]
+fn text_block_keeps_line_breaks() {
+    let source = "temp name = \"Ada\"\n- Listen\n    \"\"\"\n    Dear {name},\n    the road is closed.\n    \"\"\"\n    She folds the letter.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+
+    runtime.select_choice(0).unwrap();
+    assert_eq!(runtime.current_line(), "Dear Ada,\nthe road is closed.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "She folds the letter.");
+    assert!(!runtime.has_more());
+}
+
+#[test]
+fn crlf_line_breaks_render_as_newlines() {
+    let source = "One \\\r\n  two.\r\n\"\"\"\r\nA\r\nB\r\n\"\"\"\r\nC \\\r  D.\r";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+
+    assert_eq!(runtime.current_line(), "One two.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "A\nB");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "C D.");
+}
+
+#[test]
+fn crlf_diagnostics_point_at_source_bytes() {
+    let source = "One \\\r\n  two.\r\n\"\"\"\r\nA\r\nB {missing}\r\n\"\"\"\r\n";
+    let diagnostics = bobbin_runtime::validate(source);
+
+    assert_eq!(diagnostics.len(), 1);
+    let span = diagnostics[0].primary_span().unwrap();
+    assert_eq!(&source[span.start..span.end], "missing");
+}
+
+#[test]
+fn lines_and_choices_are_trimmed_by_default() {
+    let source = "   Hello   \ntemp name = \" Ann \"\nHi, {name}  \n- \tGo   \n- Stay\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+
+    assert_eq!(runtime.current_line(), "Hello");
+    runtime.advance().unwrap();
+    // Only the ends of the whole line are trimmed
+    assert_eq!(runtime.current_line(), "Hi,  Ann");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_choices(), ["Go", "Stay"]);
+}
+
+#[test]
+fn trimming_can_be_turned_off() {
+    let source = "   Hello   \n- Go   \n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host)
+        .unwrap()
+        .with_trim_lines(false);
+
+    // The leading spaces are indentation, which is never part of the text
+    assert_eq!(runtime.current_line(), "Hello   ");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_choices(), ["Go   "]);
+}
+
+#[test]
+fn line_filter_rewrites_shown_text() {
+    let source = "temp name = \"ada\"\nHello, {name}.  \n- Wave\n- Leave\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut plain = Runtime::new(source, Arc::clone(&storage), Arc::clone(&host)).unwrap();
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+
+    // Applies to the line already on show, after interpolation
+    runtime.set_line_filter(Box::new(|text| text.to_uppercase()));
+    assert_eq!(runtime.current_line(), "HELLO, ADA.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_choices(), ["WAVE", "LEAVE"]);
+
+    assert_eq!(plain.current_line(), "Hello, ada.");
+    plain.advance().unwrap();
+    assert_eq!(plain.current_choices(), ["Wave", "Leave"]);
+}
+
+#[test]
+fn peek_next_looks_ahead_without_running_anything() {
+    let source =
+        "Welcome.\nsave gold = 5\nPick one.\n- Buy\n    Sold.\n    wait 1\n    Bye.\n- Leave\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, Arc::clone(&storage), host).unwrap();
+
+    // The save declaration between here and the next line is not run
+    assert_eq!(runtime.peek_next(), NextKind::Line);
+    assert!(storage.get("gold").is_none());
+
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "Pick one.");
+    assert_eq!(runtime.peek_next(), NextKind::Choice);
+
+    runtime.advance().unwrap();
+    assert_eq!(runtime.peek_next(), NextKind::Choice);
+    runtime.select_choice(0).unwrap();
+    assert_eq!(runtime.current_line(), "Sold.");
+    assert_eq!(runtime.peek_next(), NextKind::Wait);
+
+    runtime.advance().unwrap();
+    assert_eq!(runtime.peek_next(), NextKind::Line);
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "Bye.");
+    assert_eq!(runtime.peek_next(), NextKind::Done);
+}
+
+#[test]
+fn builder_applies_its_options() {
+    let source = "Hello   \nYou rolled {random(1, 100)}.\n";
+    let id = extract_strings(source).unwrap()[0].id.clone();
+    let build = || {
+        let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+        let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+        RuntimeBuilder::new(storage, host)
+            .seed(7)
+            .trim_lines(false)
+            .string_table(HashMap::from([(id.clone(), "Bonjour   ".to_string())]))
+            .build(source)
+            .unwrap()
+    };
+
+    let mut first = build();
+    let mut second = build();
+    assert_eq!(first.current_line(), "Bonjour   ");
+    first.advance().unwrap();
+    second.advance().unwrap();
+    assert_eq!(first.current_line(), second.current_line());
+}
+
+#[test]
+fn builder_step_budget_stops_long_steps() {
+    let source = "temp total = 1 + 2 + 3 + 4\nTotal: {total}\n";
+    let runtime = |budget: usize| {
+        let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+        let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+        RuntimeBuilder::new(storage, host)
+            .step_budget(budget)
+            .build(source)
+    };
+
+    assert!(matches!(
+        runtime(5),
+        Err(BobbinError::Runtime(RuntimeError::StepBudgetExceeded {
+            budget: 5
+        }))
+    ));
+    assert_eq!(runtime(100).unwrap().current_line(), "Total: 10");
+}
+
+#[test]
+fn builder_blank_lines_as_beats() {
+    // The blank lines at the start and end are not beats
+    let source = "\nThe door creaks.\n\n   \nA shadow moves.\n\n";
+    let lines = |beats: bool| {
+        let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+        let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+        let mut runtime = RuntimeBuilder::new(storage, host)
+            .blank_lines_as_beats(beats)
+            .build(source)
+            .unwrap();
+        let mut lines = vec![runtime.current_line().to_string()];
+        while runtime.has_more() {
+            runtime.advance().unwrap();
+            assert!(runtime.has_line(), "a beat is a line on show");
+            lines.push(runtime.current_line().to_string());
+        }
+        lines
+    };
+
+    assert_eq!(lines(true), ["The door creaks.", "", "A shadow moves."]);
+    assert_eq!(lines(false), ["The door creaks.", "A shadow moves."]);
+}
+
+/// A host whose every lookup panics.
+struct PanickingHost;
+
+impl HostState for PanickingHost {
+    fn lookup(&self, name: &str) -> Option<Value> {
+        panic!("no such host variable: {}", name)
+    }
+}
+
+#[test]
+fn builder_catch_host_panics() {
+    let source = "extern mood\nHello.\nYou feel {mood}.\n";
+    let runtime = |catch: bool| {
+        let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+        let host: Arc<dyn HostState> = Arc::new(PanickingHost);
+        RuntimeBuilder::new(storage, host)
+            .catch_host_panics(catch)
+            .build(source)
+            .unwrap()
+    };
+
+    let mut caught = runtime(true);
+    assert!(matches!(
+        caught.advance(),
+        Err(RuntimeError::HostCallbackPanicked { ref name }) if name == "mood"
+    ));
+
+    let mut uncaught = runtime(false);
+    assert!(panic::catch_unwind(AssertUnwindSafe(|| uncaught.advance())).is_err());
+}
+
+#[test]
+fn builder_catch_host_panics_covers_the_line_filter() {
+    let source = "Hello.\nGoodbye.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = RuntimeBuilder::new(storage, host)
+        .catch_host_panics(true)
+        .build(source)
+        .unwrap();
+    runtime.set_line_filter(Box::new(|text| {
+        assert_ne!(text, "Goodbye.", "filter gave up");
+        text.to_string()
+    }));
+
+    assert!(matches!(
+        runtime.advance(),
+        Err(RuntimeError::HostCallbackPanicked { ref name }) if name == "line filter"
+    ));
+}
+
 #[test]
 fn empty_source() {
     // Special case: empty source produces empty output
@@ -23,5 +280,6 @@ fn empty_source() {
     let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
     let runtime = Runtime::new("", storage, host).unwrap();
     assert_eq!(runtime.current_line(), "");
+    assert!(!runtime.has_line());
     assert!(!runtime.has_more());
 }
@@ -132,6 +132,20 @@ fn mixed_leave() {
     support::run_trace_test(&support::cases_dir().join("choices/mixed.bobbin"), "leave");
 }
 
+// =============================================================================
+// Ending Early
+// =============================================================================
+
+#[test]
+fn end_go() {
+    support::run_trace_test(&support::cases_dir().join("choices/end.bobbin"), "go");
+}
+
+#[test]
+fn end_stay() {
+    support::run_trace_test(&support::cases_dir().join("choices/end.bobbin"), "stay");
+}
+
 // =============================================================================
 // Nested Choices
 // =============================================================================
@@ -156,3 +170,196 @@ fn nested_talk_to_bob() {
 fn nested_leave() {
     support::run_trace_test(&support::cases_dir().join("choices/nested.bobbin"), "leave");
 }
+
+#[test]
+fn nested_with_tabs() {
+    support::run_trace_test(
+        &support::cases_dir().join("choices/nested_tabs.bobbin"),
+        "talk_to_alice",
+    );
+}
+
+#[test]
+fn nested_dedent_study() {
+    support::run_trace_test(
+        &support::cases_dir().join("choices/nested_dedent.bobbin"),
+        "study",
+    );
+}
+
+#[test]
+fn nested_dedent_cellar() {
+    support::run_trace_test(
+        &support::cases_dir().join("choices/nested_dedent.bobbin"),
+        "cellar",
+    );
+}
+
+// =============================================================================
+// Invalid Selection
+// =============================================================================
+
+#[test]
+fn invalid_index_keeps_waiting_for_choice() {
+    use bobbin_runtime::{HostState, Runtime, RuntimeError, VariableStorage};
+    use std::sync::Arc;
+    use support::{EmptyHostState, MemoryStorage};
+
+    let source = "Pick one\n- Left\n    You went left.\n- Right\n    You went right.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+    runtime.advance().unwrap();
+
+    match runtime.select_choice(5) {
+        Err(RuntimeError::InvalidChoiceIndex {
+            index,
+            count,
+            choices,
+        }) => {
+            assert_eq!(index, 5);
+            assert_eq!(count, 2);
+            assert_eq!(choices, ["Left", "Right"]);
+        }
+        other => panic!("Expected InvalidChoiceIndex, got: {:?}", other),
+    }
+    assert!(runtime.is_waiting_for_choice());
+    assert_eq!(runtime.current_choices(), ["Left", "Right"]);
+
+    runtime.select_choice(1).unwrap();
+    assert_eq!(runtime.current_line(), "You went right.");
+}
+
+// =============================================================================
+// Tags
+// =============================================================================
+
+#[test]
+fn tags_are_stripped_from_choice_text() {
+    use bobbin_runtime::{HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::{EmptyHostState, MemoryStorage};
+
+    let source = "The guard blocks the way.\n- Attack #aggressive #combat\n    You swing.\n- Talk to item #1 #peaceful\n- Wait\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+    runtime.advance().unwrap();
+
+    assert_eq!(
+        runtime.current_choices(),
+        ["Attack", "Talk to item #1", "Wait"]
+    );
+    assert_eq!(runtime.choice_tags(0), ["aggressive", "combat"]);
+    assert_eq!(runtime.choice_tags(1), ["peaceful"]);
+    assert!(runtime.choice_tags(2).is_empty());
+
+    // Tags stay with their branch
+    runtime.select_choice(0).unwrap();
+    assert_eq!(runtime.current_line(), "You swing.");
+    assert!(runtime.choice_tags(0).is_empty());
+}
+
+#[test]
+fn invalid_index_diagnostic_lists_available_choices() {
+    use bobbin_runtime::{BobbinError, HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::{EmptyHostState, MemoryStorage};
+
+    let source = "The guard blocks the way.\n- Attack\n- Flee\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+    runtime.advance().unwrap();
+
+    let err = runtime.select_choice(5).unwrap_err();
+    let diagnostics = BobbinError::Runtime(err).into_diagnostics();
+
+    assert!(
+        diagnostics[0]
+            .notes
+            .contains(&"available choices: 0: Attack, 1: Flee".to_string()),
+        "{:?}",
+        diagnostics[0].notes
+    );
+}
+
+#[test]
+fn hash_in_lines_is_text() {
+    support::run_output_test(&support::cases_dir().join("choices/hash_in_lines.bobbin"));
+}
+
+// =============================================================================
+// Default Choice
+// =============================================================================
+
+#[test]
+fn select_default_takes_the_marked_branch() {
+    use bobbin_runtime::{HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::{EmptyHostState, MemoryStorage};
+
+    let source = "The guard waits for an answer.\n- Argue\n    You argue.\n- Stay silent #default #quiet\n    You say nothing.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+    runtime.advance().unwrap();
+
+    assert_eq!(runtime.current_choices(), ["Argue", "Stay silent"]);
+    assert_eq!(runtime.default_choice(), Some(1));
+    // The marker is not a tag
+    assert_eq!(runtime.choice_tags(1), ["quiet"]);
+
+    runtime.select_default().unwrap();
+    assert_eq!(runtime.current_line(), "You say nothing.");
+    assert_eq!(runtime.default_choice(), None);
+}
+
+#[test]
+fn select_default_without_a_default_keeps_waiting_for_choice() {
+    use bobbin_runtime::{HostState, Runtime, RuntimeError, VariableStorage};
+    use std::sync::Arc;
+    use support::{EmptyHostState, MemoryStorage};
+
+    let source = "The guard waits for an answer.\n- Argue\n- Leave\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+    runtime.advance().unwrap();
+
+    assert_eq!(runtime.default_choice(), None);
+    assert!(matches!(
+        runtime.select_default(),
+        Err(RuntimeError::NoDefaultChoice)
+    ));
+
+    assert!(runtime.is_waiting_for_choice());
+    assert_eq!(runtime.current_choices(), ["Argue", "Leave"]);
+    runtime.select_choice(1).unwrap();
+    assert!(!runtime.has_more());
+}
+
+#[test]
+fn errors_two_default_choices() {
+    support::run_error_test(&support::cases_dir().join("choices/errors/two_defaults.bobbin"));
+}
+
+#[test]
+fn errors_stray_choice_marker() {
+    support::run_error_test(&support::cases_dir().join("choices/errors/stray_marker.bobbin"));
+}
+
+#[test]
+fn stray_choice_marker_points_at_the_marker() {
+    let source = "Hello.\n- Leave\n    Bye.\n        - Wave\n- Stay\n    - Sit\n";
+    let diagnostics = bobbin_runtime::validate(source);
+
+    // The choice nested under `- Stay` is in its branch and not flagged
+    assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(diagnostics[0].message, "choice marker out of place");
+    let marker = source.find("- Wave").unwrap();
+    assert_eq!(
+        diagnostics[0].primary_span().map(|span| span.start),
+        Some(marker)
+    );
+}
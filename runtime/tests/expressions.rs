@@ -0,0 +1,214 @@
+//! Expression tests - operators in declarations and assignments.
+
+mod support;
+
+use bobbin_runtime::{HostState, Runtime, Value, VariableStorage};
+use std::sync::Arc;
+use support::{EmptyHostState, MemoryStorage};
+
+// =============================================================================
+// Logical operators
+// =============================================================================
+
+#[test]
+fn logic_operators() {
+    support::run_output_test(&support::cases_dir().join("expressions/logic.bobbin"));
+}
+
+#[test]
+fn logic_short_circuits() {
+    // The host provides no `door_open`, so evaluating it would be an error
+    support::run_output_test(&support::cases_dir().join("expressions/short_circuit.bobbin"));
+}
+
+#[test]
+fn errors_and_number() {
+    support::run_error_test(&support::cases_dir().join("expressions/errors/and_number.bobbin"));
+}
+
+#[test]
+fn errors_not_string() {
+    support::run_error_test(&support::cases_dir().join("expressions/errors/not_string.bobbin"));
+}
+
+#[test]
+fn errors_undefined_operand() {
+    support::run_error_test(
+        &support::cases_dir().join("expressions/errors/undefined_operand.bobbin"),
+    );
+}
+
+#[test]
+fn errors_const_expression() {
+    support::run_error_test(
+        &support::cases_dir().join("expressions/errors/const_expression.bobbin"),
+    );
+}
+
+// =============================================================================
+// Arithmetic, comparison, and grouping
+// =============================================================================
+
+#[test]
+fn arithmetic_precedence() {
+    support::run_output_test(&support::cases_dir().join("expressions/arithmetic.bobbin"));
+}
+
+#[test]
+fn comparison_operators() {
+    support::run_output_test(&support::cases_dir().join("expressions/comparison.bobbin"));
+}
+
+#[test]
+fn errors_unclosed_paren() {
+    support::run_error_test(&support::cases_dir().join("expressions/errors/unclosed_paren.bobbin"));
+}
+
+#[test]
+fn errors_unmatched_paren() {
+    support::run_error_test(
+        &support::cases_dir().join("expressions/errors/unmatched_paren.bobbin"),
+    );
+}
+
+#[test]
+fn errors_compare_string() {
+    support::run_error_test(&support::cases_dir().join("expressions/errors/compare_string.bobbin"));
+}
+
+#[test]
+fn negation() {
+    support::run_output_test(&support::cases_dir().join("expressions/negation.bobbin"));
+}
+
+#[test]
+fn errors_negate_string() {
+    support::run_error_test(&support::cases_dir().join("expressions/errors/negate_string.bobbin"));
+}
+
+#[test]
+fn interpolated_expressions() {
+    support::run_output_test(&support::cases_dir().join("expressions/interpolation.bobbin"));
+}
+
+// =============================================================================
+// Built-in functions
+// =============================================================================
+
+/// Every line the script shows, running a runtime seeded with `seed`.
+fn seeded_lines(source: &str, seed: u64) -> Vec<String> {
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::with_seed(source, storage, host, seed).unwrap();
+    let mut lines = vec![runtime.current_line().to_string()];
+    while runtime.has_more() {
+        runtime.advance().unwrap();
+        lines.push(runtime.current_line().to_string());
+    }
+    lines
+}
+
+#[test]
+fn random_is_reproducible_with_the_same_seed() {
+    let source = "{random(1, 6)}\n".repeat(20);
+
+    let first = seeded_lines(&source, 42);
+    let second = seeded_lines(&source, 42);
+
+    assert_eq!(first, second);
+    for roll in &first {
+        let roll: u8 = roll.parse().unwrap();
+        assert!((1..=6).contains(&roll), "{}", roll);
+    }
+    assert_ne!(
+        first,
+        seeded_lines(&source, 7),
+        "a different seed should differ"
+    );
+}
+
+#[test]
+fn random_in_declarations() {
+    let source = "temp roll = random(2, 2) * 10\nRolled {roll}.\n";
+    assert_eq!(seeded_lines(source, 1), ["Rolled 20."]);
+}
+
+#[test]
+fn errors_unknown_function() {
+    support::run_error_test(
+        &support::cases_dir().join("expressions/errors/unknown_function.bobbin"),
+    );
+}
+
+#[test]
+fn errors_random_arity() {
+    support::run_error_test(&support::cases_dir().join("expressions/errors/random_arity.bobbin"));
+}
+
+#[test]
+fn errors_random_empty_range() {
+    support::run_error_test(
+        &support::cases_dir().join("expressions/errors/random_empty_range.bobbin"),
+    );
+}
+
+// =============================================================================
+// Values
+// =============================================================================
+
+#[test]
+fn values_equal_only_within_a_type() {
+    assert_eq!(Value::Number(2.0), Value::Number(2.0));
+    assert_eq!(Value::String("gold".into()), Value::String("gold".into()));
+    assert_eq!(Value::Bool(true), Value::Bool(true));
+    assert_ne!(Value::Number(1.0), Value::Number(2.0));
+    assert_ne!(Value::Number(1.0), Value::String("1".into()));
+    assert_ne!(Value::Bool(true), Value::Number(1.0));
+}
+
+#[test]
+fn values_order_within_a_type() {
+    assert!(Value::Number(-1.0) < Value::Number(2.5));
+    assert!(Value::String("apple".into()) < Value::String("banana".into()));
+    assert!(Value::Bool(false) < Value::Bool(true));
+    assert_eq!(
+        Value::Number(f64::NAN).partial_cmp(&Value::Number(1.0)),
+        None
+    );
+}
+
+#[test]
+fn values_order_bool_then_number_then_string() {
+    assert!(Value::Bool(true) < Value::Number(-100.0));
+    assert!(Value::Number(100.0) < Value::String(String::new()));
+    assert!(Value::Bool(true) < Value::String("a".into()));
+    assert!(Value::Number(f64::NAN) < Value::String("a".into()));
+
+    let mut values = vec![
+        Value::String("b".into()),
+        Value::Number(3.0),
+        Value::Bool(true),
+        Value::String("a".into()),
+        Value::Number(1.0),
+        Value::Bool(false),
+    ];
+    values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+    assert_eq!(
+        values,
+        [
+            Value::Bool(false),
+            Value::Bool(true),
+            Value::Number(1.0),
+            Value::Number(3.0),
+            Value::String("a".into()),
+            Value::String("b".into()),
+        ]
+    );
+}
+
+#[test]
+fn value_type_names() {
+    assert_eq!(Value::String("x".into()).type_name(), "string");
+    assert_eq!(Value::Number(0.0).type_name(), "number");
+    assert_eq!(Value::Bool(false).type_name(), "bool");
+}
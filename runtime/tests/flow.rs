@@ -0,0 +1,57 @@
+//! Label and goto tests.
+
+mod support;
+
+// =============================================================================
+// Jumps
+// =============================================================================
+
+#[test]
+fn forward_goto() {
+    support::run_output_test(&support::cases_dir().join("flow/forward.bobbin"));
+}
+
+#[test]
+fn backward_goto_loop() {
+    support::run_trace_test(
+        &support::cases_dir().join("flow/loop.bobbin"),
+        "browse_then_leave",
+    );
+}
+
+#[test]
+fn loop_redeclares_temp() {
+    support::run_trace_test(
+        &support::cases_dir().join("flow/loop_redeclare.bobbin"),
+        "rest_then_stop",
+    );
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[test]
+fn undefined_label() {
+    support::run_error_test(&support::cases_dir().join("flow/errors/undefined_label.bobbin"));
+}
+
+#[test]
+fn duplicate_label() {
+    support::run_error_test(&support::cases_dir().join("flow/errors/duplicate_label.bobbin"));
+}
+
+#[test]
+fn nested_label() {
+    support::run_error_test(&support::cases_dir().join("flow/errors/nested_label.bobbin"));
+}
+
+#[test]
+fn goto_skips_declaration() {
+    support::run_error_test(&support::cases_dir().join("flow/errors/skips_declaration.bobbin"));
+}
+
+#[test]
+fn empty_loop() {
+    support::run_error_test(&support::cases_dir().join("flow/errors/empty_loop.bobbin"));
+}
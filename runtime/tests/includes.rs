@@ -0,0 +1,163 @@
+//! Include tests - scripts assembled from several files.
+
+mod support;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bobbin_runtime::{
+    Compilation, HostState, Runtime, Value, VariableStorage, compile_with_includes,
+};
+use support::{EmptyHostState, MemoryStorage};
+
+fn files(entries: &[(&str, &str)]) -> HashMap<String, String> {
+    entries
+        .iter()
+        .map(|(path, source)| (path.to_string(), source.to_string()))
+        .collect()
+}
+
+/// Compile `path` from `files`, which must also hold the entry file.
+fn compile(path: &str, files: &HashMap<String, String>) -> Compilation {
+    compile_with_includes(path, &files[path], files)
+}
+
+// =============================================================================
+// Expansion
+// =============================================================================
+
+#[test]
+fn include_splices_declarations_and_lines() {
+    let files = files(&[
+        (
+            "main.bobbin",
+            "include \"lib/common.bobbin\"\ninclude \"lib/greeting.bobbin\"\nset visits += 1\nVisit {visits}.\n",
+        ),
+        (
+            "lib/common.bobbin",
+            "save visits = 0\ninclude \"greeting.bobbin\"\n",
+        ),
+        ("lib/greeting.bobbin", "Welcome back.\n"),
+    ]);
+
+    let compilation = compile("main.bobbin", &files);
+    let paths: Vec<_> = compilation
+        .sources
+        .files()
+        .iter()
+        .map(|file| file.path.as_str())
+        .collect();
+    assert_eq!(
+        paths,
+        ["main.bobbin", "lib/common.bobbin", "lib/greeting.bobbin"]
+    );
+
+    let storage = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::from_chunk(
+        compilation.chunk.expect("should compile"),
+        Arc::clone(&storage) as Arc<dyn VariableStorage>,
+        host,
+    )
+    .unwrap();
+
+    // The second include of greeting.bobbin is skipped
+    assert_eq!(runtime.current_line(), "Welcome back.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "Visit 1.");
+    assert!(!runtime.has_more());
+    assert_eq!(storage.get("visits"), Some(Value::Number(1.0)));
+}
+
+#[test]
+fn goto_reaches_label_in_included_file() {
+    let files = files(&[
+        (
+            "main.bobbin",
+            "goto start\ninclude \"scenes.bobbin\"\n== start ==\nStart.\ngoto ending\n",
+        ),
+        ("scenes.bobbin", "== ending ==\nThe end.\n"),
+    ]);
+
+    let chunk = compile("main.bobbin", &files)
+        .chunk
+        .expect("should compile");
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::from_chunk(chunk, storage, host).unwrap();
+
+    assert_eq!(runtime.current_line(), "Start.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "The end.");
+}
+
+// =============================================================================
+// Errors
+// =============================================================================
+
+#[test]
+fn missing_file_points_at_path() {
+    let files = files(&[("main.bobbin", "Hello.\ninclude \"shared.bobbin\"\n")]);
+
+    let compilation = compile("main.bobbin", &files);
+    let diagnostics = compilation.chunk.unwrap_err().to_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "included file 'shared.bobbin' not found"
+    );
+
+    let span = diagnostics[0].primary_span().unwrap();
+    let (file, local) = compilation.sources.localize(span).unwrap();
+    assert_eq!(file.path, "main.bobbin");
+    assert_eq!(&file.source[local.start..local.end], "\"shared.bobbin\"");
+}
+
+#[test]
+fn include_cycle_reports_chain() {
+    let files = files(&[
+        ("a.bobbin", "include \"b.bobbin\"\nA.\n"),
+        ("b.bobbin", "B.\ninclude \"a.bobbin\"\n"),
+    ]);
+
+    let compilation = compile("a.bobbin", &files);
+    let diagnostics = compilation.chunk.unwrap_err().to_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(
+        diagnostics[0].message,
+        "include cycle: a.bobbin -> b.bobbin -> a.bobbin"
+    );
+
+    let span = diagnostics[0].primary_span().unwrap();
+    let (file, local) = compilation.sources.localize(span).unwrap();
+    assert_eq!(file.path, "b.bobbin");
+    assert_eq!(&file.source[local.start..local.end], "\"a.bobbin\"");
+}
+
+#[test]
+fn errors_in_included_file_map_back_to_it() {
+    let files = files(&[
+        ("main.bobbin", "include \"vars.bobbin\"\nGold: {gold}.\n"),
+        ("vars.bobbin", "save gold = 10\nBroke: {gld}.\n"),
+    ]);
+
+    let compilation = compile("main.bobbin", &files);
+    let diagnostics = compilation.chunk.unwrap_err().to_diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "undefined variable 'gld'");
+
+    let span = diagnostics[0].primary_span().unwrap();
+    let (file, local) = compilation.sources.localize(span).unwrap();
+    assert_eq!(file.path, "vars.bobbin");
+    assert_eq!(&file.source[local.start..local.end], "gld");
+}
+
+#[test]
+fn include_without_provider() {
+    support::run_error_test(&support::cases_dir().join("includes/errors/without_provider.bobbin"));
+}
+
+#[test]
+fn nested_include() {
+    support::run_error_test(&support::cases_dir().join("includes/errors/nested_include.bobbin"));
+}
@@ -3,6 +3,9 @@
 //! This module provides infrastructure for running data-driven tests using
 //! sidecar files that specify expected outputs.
 
+// Each test binary compiles this module separately and uses a different subset of it.
+#![allow(dead_code)]
+
 mod host_state;
 mod storage;
 
@@ -44,6 +47,8 @@ pub enum Assertion {
     HasMore,
     /// Assert is_waiting_for_choice() is true
     WaitingForChoice,
+    /// Assert current_wait() equals the given number of seconds
+    Wait(f64),
     /// Assert a variable exists in storage with the given value
     StorageVar { name: String, value: Value },
 }
@@ -337,6 +342,15 @@ fn parse_step(line: &str, line_num: usize) -> Option<Step> {
         };
     }
 
+    // Wait assertion: ~ seconds
+    if let Some(seconds) = line.strip_prefix("~ ") {
+        let seconds = seconds
+            .trim()
+            .parse()
+            .unwrap_or_else(|_| panic!("Line {}: Invalid wait duration: {}", line_num, seconds));
+        return Some(Step::Assert(Assertion::Wait(seconds)));
+    }
+
     // Storage variable assertion: $ name = value
     if let Some(rest) = line.strip_prefix("$ ") {
         let (name, value) = parse_storage_assertion(rest, line_num);
@@ -486,6 +500,19 @@ fn execute_runtime_assertion(
                 path_name
             );
         }
+        Assertion::Wait(expected) => {
+            let actual = runtime.current_wait();
+            assert_eq!(
+                actual,
+                Some(*expected),
+                "Wait mismatch at step {} in {} (path: {})\nExpected: {:?}\nActual: {:?}",
+                step_idx,
+                case_path.display(),
+                path_name,
+                Some(expected),
+                actual
+            );
+        }
         Assertion::StorageVar { .. } => {
             // StorageVar assertions are handled inline in run_trace_test
             panic!(
@@ -561,6 +588,19 @@ fn execute_assertion(
                 path_name
             );
         }
+        Assertion::Wait(expected) => {
+            let actual = runtime.current_wait();
+            assert_eq!(
+                actual,
+                Some(*expected),
+                "Wait mismatch at step {} in {} (path: {})\nExpected: {:?}\nActual: {:?}",
+                step_idx,
+                case_path.display(),
+                path_name,
+                Some(expected),
+                actual
+            );
+        }
         Assertion::StorageVar { name, value } => {
             let actual = storage.get(name);
             assert_eq!(
@@ -617,6 +657,17 @@ fn execute_action(
     }
 }
 
+// =============================================================================
+// Runtime Construction
+// =============================================================================
+
+/// A runtime for `source` with empty storage and no host variables.
+pub fn runtime(source: &str) -> Runtime {
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    Runtime::new(source, storage, host).unwrap()
+}
+
 // =============================================================================
 // Path Resolution Helper
 // =============================================================================
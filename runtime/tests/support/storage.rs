@@ -46,4 +46,12 @@ impl VariableStorage for MemoryStorage {
     fn contains(&self, name: &str) -> bool {
         self.values.read().unwrap().contains_key(name)
     }
+
+    fn iter(&self) -> Vec<(String, Value)> {
+        let values = self.values.read().unwrap();
+        values
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect()
+    }
 }
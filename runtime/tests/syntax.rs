@@ -2,9 +2,22 @@
 
 mod support;
 
+use bobbin_runtime::scanner::{LexicalError, Scanner};
+use bobbin_runtime::token::Span;
+use bobbin_runtime::validate;
+
+#[test]
+fn errors_mixed_indentation_across_lines() {
+    support::run_error_test(
+        &support::cases_dir().join("syntax/errors/mixed_indentation_across_lines.bobbin"),
+    );
+}
+
 #[test]
-fn errors_tabs() {
-    support::run_error_test(&support::cases_dir().join("syntax/errors/tabs.bobbin"));
+fn errors_mixed_indentation_in_line() {
+    support::run_error_test(
+        &support::cases_dir().join("syntax/errors/mixed_indentation_in_line.bobbin"),
+    );
 }
 
 #[test]
@@ -24,6 +37,23 @@ fn errors_lone_closing_brace() {
     support::run_error_test(&support::cases_dir().join("syntax/errors/lone_closing_brace.bobbin"));
 }
 
+#[test]
+fn errors_unterminated_block() {
+    support::run_error_test(&support::cases_dir().join("syntax/errors/unterminated_block.bobbin"));
+}
+
+#[test]
+fn errors_invalid_format() {
+    support::run_error_test(&support::cases_dir().join("syntax/errors/invalid_format.bobbin"));
+}
+
+#[test]
+fn errors_wait_without_duration() {
+    support::run_error_test(
+        &support::cases_dir().join("syntax/errors/wait_without_duration.bobbin"),
+    );
+}
+
 // =============================================================================
 // Assignment Syntax Errors
 // =============================================================================
@@ -45,9 +75,69 @@ fn errors_set_missing_value() {
     support::run_error_test(&support::cases_dir().join("syntax/errors/set_missing_value.bobbin"));
 }
 
+#[test]
+fn errors_temp_missing_equals() {
+    support::run_error_test(&support::cases_dir().join("syntax/errors/temp_missing_equals.bobbin"));
+}
+
 #[test]
 fn errors_invalid_declaration_character() {
     support::run_error_test(
         &support::cases_dir().join("syntax/errors/invalid_declaration_character.bobbin"),
     );
 }
+
+// =============================================================================
+// Control Characters
+// =============================================================================
+
+fn scan(source: &str) -> (Vec<&str>, Vec<LexicalError>) {
+    let mut lexemes = Vec::new();
+    let mut errors = Vec::new();
+    for token in Scanner::new(source).iter() {
+        match token {
+            Ok(token) => lexemes.push(token.lexeme),
+            Err(err) => errors.push(err),
+        }
+    }
+    (lexemes, errors)
+}
+
+#[test]
+fn control_character_in_text_is_reported_and_skipped() {
+    let source = "Hello\0 there.\n";
+    let (lexemes, errors) = scan(source);
+
+    assert_eq!(lexemes, ["Hello", " there.", "\n"]);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(matches!(
+        errors[0],
+        LexicalError::ControlCharacter {
+            character: '\0',
+            span: Span { start: 5, end: 6 }
+        }
+    ));
+
+    let diagnostics = validate(source);
+    assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(
+        diagnostics[0].message,
+        "lexical error: unexpected control character U+0000"
+    );
+}
+
+#[test]
+fn control_character_in_string_keeps_the_string() {
+    let source = "temp name = \"a\u{1b}b\"\n";
+    let (lexemes, errors) = scan(source);
+
+    assert_eq!(lexemes, ["temp", "name", "=", "\"a\u{1b}b\"", "\n"]);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].span(), Span { start: 14, end: 15 });
+}
+
+#[test]
+fn tabs_and_line_breaks_are_not_control_errors() {
+    let (_, errors) = scan("Hello\tthere.\r\nBye.\n");
+    assert!(errors.is_empty(), "{:?}", errors);
+}
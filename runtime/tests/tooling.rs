@@ -0,0 +1,695 @@
+//! Tooling API tests - disassembly and other developer-facing inspection.
+
+mod support;
+
+use bobbin_runtime::scanner::Scanner;
+use bobbin_runtime::token::Span;
+use bobbin_runtime::token::TokenKind;
+use bobbin_runtime::{
+    FlowNodeKind, HighlightKind, HighlightSpan, HostState, Instruction, LocEntry, LocKind, Runtime,
+    RuntimeError, VariableStorage, extract_strings, highlight,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use support::{EmptyHostState, MemoryStorage, runtime};
+
+// =============================================================================
+// Disassembly
+// =============================================================================
+
+#[test]
+fn disassemble_choice_script() {
+    let source = "Pick one\n- Left\n    You went left.\n- Right\nDone.\n";
+    let listing = runtime(source).disassemble();
+
+    let opcodes: Vec<&str> = listing
+        .lines()
+        .map(|line| line.split_whitespace().nth(1).unwrap())
+        .collect();
+    assert_eq!(
+        opcodes,
+        [
+            "Constant",
+            "Line",
+            "Constant",
+            "Constant",
+            "ChoiceSet",
+            "Constant",
+            "Line",
+            "Jump",
+            "Jump",
+            "Constant",
+            "Line",
+            "Return",
+        ],
+        "unexpected disassembly:\n{}",
+        listing
+    );
+
+    // Constants are resolved and jump targets are absolute indices
+    assert!(
+        listing.contains("0000 Constant       0 (\"Pick one\")"),
+        "{}",
+        listing
+    );
+    assert!(
+        listing.contains("0004 ChoiceSet      2 -> [5, 8]"),
+        "{}",
+        listing
+    );
+    assert!(listing.contains("0007 Jump           -> 9"), "{}", listing);
+}
+
+#[test]
+fn compile_to_chunk_needs_no_runtime() {
+    // Reading an extern would fail in a runtime with no host; compiling
+    // alone never runs anything
+    let source = "extern gold\nPick one\n- Buy\n    You pay {gold}.\n- Leave\nDone.\n";
+    let chunk = bobbin_runtime::compile_to_chunk(source).unwrap();
+
+    let Some(Instruction::ChoiceSet { count, targets, .. }) = chunk
+        .code
+        .iter()
+        .find(|instruction| matches!(instruction, Instruction::ChoiceSet { .. }))
+    else {
+        panic!("no choice set:\n{}", chunk);
+    };
+    assert_eq!(*count, 2);
+    // The extern is read only in the first branch
+    let reads_gold = |instruction: &Instruction| matches!(instruction, Instruction::GetHost { name } if name == "gold");
+    assert!(chunk.code[targets[0]..targets[1]].iter().any(reads_gold));
+    assert!(!chunk.code[targets[1]..].iter().any(reads_gold));
+    assert_eq!(chunk.extern_names(), ["gold"]);
+    assert!(matches!(chunk.code.last(), Some(Instruction::Return)));
+}
+
+#[test]
+fn chunk_display_lists_code_and_constants() {
+    let chunk = bobbin_runtime::compile_to_chunk("temp gold = 5\nYou have {gold}.\n").unwrap();
+    let printed = chunk.to_string();
+
+    assert!(printed.starts_with(&chunk.disassemble()), "{}", printed);
+    assert!(printed.contains("Constant       0 (5)"), "{}", printed);
+    assert!(printed.contains("GetLocal       slot 0"), "{}", printed);
+    assert!(
+        printed.contains("constants:\n0000 5\n0001 \"You have \""),
+        "{}",
+        printed
+    );
+
+    let concat = chunk
+        .code
+        .iter()
+        .find(|instruction| matches!(instruction, Instruction::Concat { .. }))
+        .unwrap();
+    assert_eq!(concat.to_string(), "Concat         3");
+}
+
+#[test]
+fn plain_text_compiles_without_concat() {
+    let source = "Once upon a time.\n- Listen\n    The end.\n";
+    let chunk = bobbin_runtime::compile_to_chunk(source).unwrap();
+
+    assert!(
+        !chunk
+            .code
+            .iter()
+            .any(|instruction| matches!(instruction, Instruction::Concat { .. })),
+        "{}",
+        chunk
+    );
+
+    let mut runtime = runtime(source);
+    assert_eq!(runtime.current_line(), "Once upon a time.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_choices(), ["Listen"]);
+    runtime.select_choice(0).unwrap();
+    assert_eq!(runtime.current_line(), "The end.");
+}
+
+// =============================================================================
+// Token stream
+// =============================================================================
+
+#[test]
+fn scanner_iter_yields_interpolation_tokens() {
+    let source = "Hello {name}!";
+    let tokens: Vec<_> = Scanner::new(source)
+        .iter()
+        .map(|token| token.unwrap())
+        .collect();
+
+    let kinds: Vec<TokenKind> = tokens.iter().map(|token| token.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            TokenKind::TextSegment,
+            TokenKind::OpenBrace,
+            TokenKind::Identifier,
+            TokenKind::CloseBrace,
+            TokenKind::TextSegment,
+        ]
+    );
+
+    let name = tokens[2];
+    assert_eq!(name.lexeme, "name");
+    assert_eq!(&source[name.span.start..name.span.end], "name");
+}
+
+#[test]
+fn scanner_iter_is_lazy_and_fused() {
+    let mut tokens = Scanner::new("First line\nSecond line\n").iter();
+
+    let first = tokens.next().unwrap().unwrap();
+    assert_eq!(first.kind, TokenKind::TextSegment);
+    assert_eq!(first.lexeme, "First line");
+
+    assert_eq!(tokens.by_ref().count(), 3);
+    assert!(tokens.next().is_none());
+}
+
+// =============================================================================
+// Source spans
+// =============================================================================
+
+fn byte_span(start: usize, end: usize) -> Span {
+    Span { start, end }
+}
+
+#[test]
+fn span_merge_overlapping_spans() {
+    assert_eq!(byte_span(2, 6).merge(byte_span(4, 9)), byte_span(2, 9));
+    assert_eq!(byte_span(4, 9).merge(byte_span(2, 6)), byte_span(2, 9));
+}
+
+#[test]
+fn span_merge_disjoint_spans_covers_the_gap() {
+    assert_eq!(byte_span(0, 3).merge(byte_span(7, 10)), byte_span(0, 10));
+    assert_eq!(byte_span(7, 10).merge(byte_span(0, 3)), byte_span(0, 10));
+}
+
+#[test]
+fn span_merge_nested_spans_keeps_the_outer() {
+    assert_eq!(byte_span(1, 10).merge(byte_span(3, 5)), byte_span(1, 10));
+    assert_eq!(byte_span(3, 5).merge(byte_span(1, 10)), byte_span(1, 10));
+    assert_eq!(byte_span(1, 10).merge(byte_span(4, 4)), byte_span(1, 10));
+}
+
+#[test]
+fn span_contains_is_end_exclusive() {
+    assert!(byte_span(2, 5).contains(2));
+    assert!(byte_span(2, 5).contains(4));
+    assert!(!byte_span(2, 5).contains(5));
+    assert!(!byte_span(2, 5).contains(1));
+    assert!(!byte_span(3, 3).contains(3));
+}
+
+#[test]
+fn span_is_empty() {
+    assert!(byte_span(3, 3).is_empty());
+    assert!(!byte_span(3, 4).is_empty());
+}
+
+// =============================================================================
+// Highlighting
+// =============================================================================
+
+fn span(kind: HighlightKind, start: usize, end: usize) -> HighlightSpan {
+    HighlightSpan {
+        span: Span { start, end },
+        kind,
+    }
+}
+
+#[test]
+fn highlight_declaration_with_comment() {
+    assert_eq!(
+        highlight("save gold = 100 // note"),
+        [
+            span(HighlightKind::Keyword, 0, 4),
+            span(HighlightKind::Variable, 5, 9),
+            span(HighlightKind::Punctuation, 10, 11),
+            span(HighlightKind::Number, 12, 15),
+            span(HighlightKind::Comment, 16, 23),
+        ]
+    );
+}
+
+#[test]
+fn highlight_choice_text() {
+    let kinds: Vec<HighlightKind> = highlight("- Roll {random(1, 6):0} #dice\n")
+        .iter()
+        .map(|highlight| highlight.kind)
+        .collect();
+    assert_eq!(
+        kinds,
+        [
+            HighlightKind::Punctuation,
+            HighlightKind::StringText,
+            HighlightKind::Punctuation,
+            HighlightKind::Keyword,
+            HighlightKind::Punctuation,
+            HighlightKind::Number,
+            HighlightKind::Punctuation,
+            HighlightKind::Number,
+            HighlightKind::Punctuation,
+            HighlightKind::Number,
+            HighlightKind::Punctuation,
+            HighlightKind::Tag,
+        ]
+    );
+}
+
+#[test]
+fn highlight_never_fails() {
+    // The stray '}' is a lexical error; the text after it is still classified
+    let spans = highlight("Broken } text {name}\n\tsave x = 1\n");
+    assert!(spans.contains(&span(HighlightKind::StringText, 7, 8)));
+    assert!(spans.contains(&span(HighlightKind::Variable, 15, 19)));
+}
+
+// =============================================================================
+// Formatting
+// =============================================================================
+
+#[test]
+fn format_cleans_up_layout() {
+    let messy = concat!(
+        "\n\nsave   gold=10   // starting coins  \n",
+        "temp  mood   =  \"calm\"\n",
+        "temp roll = random (1 ,6)\n",
+        "Hello,   {name}!  \n\n\n\n",
+        "== shop   // the store\n",
+        "Buy something?\n",
+        "- Sword #weapon\n",
+        "  set gold   -=  ( 2+3 )*price\n",
+        "  - Really?\n",
+        "       Yes.\n",
+        "-    Leave\n",
+        "        wait   1.5\n\n",
+        "        goto   shop\n",
+        "extern   name",
+    );
+    let clean = concat!(
+        "save gold = 10 // starting coins\n",
+        "temp mood = \"calm\"\n",
+        "temp roll = random(1, 6)\n",
+        "Hello,   {name}!\n\n",
+        "== shop == // the store\n",
+        "Buy something?\n",
+        "- Sword #weapon\n",
+        "    set gold -= (2 + 3) * price\n",
+        "    - Really?\n",
+        "        Yes.\n",
+        "- Leave\n",
+        "    wait 1.5\n\n",
+        "    goto shop\n",
+        "extern name\n",
+    );
+
+    assert_eq!(bobbin_runtime::format_source(messy), clean);
+}
+
+#[test]
+fn format_keeps_unary_minus_next_to_its_operand() {
+    let cases = [
+        ("temp x =  -y\n", "temp x = -y\n"),
+        ("set x = 2- -y\n", "set x = 2 - -y\n"),
+        ("temp z = - ( a+1 )\n", "temp z = -(a + 1)\n"),
+        ("set x -= -( y )\n", "set x -= -(y)\n"),
+        (
+            "temp w = max(-a ,b-1) > -2 and not -c\n",
+            "temp w = max(-a, b - 1) > -2 and not -c\n",
+        ),
+    ];
+    for (source, expected) in cases {
+        assert_eq!(
+            bobbin_runtime::format_source(source),
+            expected,
+            "{:?}",
+            source
+        );
+    }
+}
+
+#[test]
+fn format_is_idempotent() {
+    let sources = [
+        "save   gold=10\n- A\n  Nested {gold:0.2}.\n\n\n- B\n    - C\n",
+        "Long line that \\\n      continues here.\n  \n\"\"\"\n  Block\n    text\n\"\"\"   \n",
+        "- \n",
+        "",
+    ];
+    for source in sources {
+        let once = bobbin_runtime::format_source(source);
+        assert_eq!(bobbin_runtime::format_source(&once), once, "{:?}", source);
+    }
+}
+
+#[test]
+fn format_keeps_text_blocks_compilable() {
+    let source = "Before.\n\n\n\"\"\"\n  Block\n    text\n\"\"\"   \n- Pick\n   \"\"\"\n   Choice block\n   \"\"\"\n";
+    let formatted = bobbin_runtime::format_source(source);
+
+    assert_eq!(
+        formatted,
+        "Before.\n\n\"\"\"\n  Block\n    text\n\"\"\"\n- Pick\n    \"\"\"\n   Choice block\n   \"\"\"\n"
+    );
+    assert!(bobbin_runtime::compile_to_chunk(&formatted).is_ok());
+}
+
+#[test]
+fn format_keeps_continuations_that_end_on_a_blank_line() {
+    let texts = |source: &str| -> Vec<String> {
+        extract_strings(source)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.text)
+            .collect()
+    };
+    let cases = [
+        ("Hello there \\\n\nWorld\n", "Hello there \\\n\nWorld\n"),
+        ("- pick \\\n\n  Then\n", "- pick \\\n\n    Then\n"),
+        ("1.5\\\n\n\n  x", "1.5\\\n\n\n    x\n"),
+        // A tab keeps the backslash literal, and so does the end of the file
+        ("Path C:\\\t\nNext\n", "Path C:\\\t\nNext\n"),
+        ("Path C:\\", "Path C:\\"),
+        ("- \t#not_a_tag\n", "- \t#not_a_tag\n"),
+    ];
+    for (source, expected) in cases {
+        let formatted = bobbin_runtime::format_source(source);
+        assert_eq!(formatted, expected, "{:?}", source);
+        assert_eq!(bobbin_runtime::format_source(&formatted), formatted);
+        assert_eq!(texts(&formatted), texts(source), "{:?}", source);
+    }
+}
+
+#[test]
+fn format_leaves_source_with_lexical_errors_alone() {
+    let source = "- Spaces\n  Indented with spaces.\n- Tab\n\tIndented with a tab.\n";
+    assert_eq!(bobbin_runtime::format_source(source), source);
+}
+
+// =============================================================================
+// Dry runs
+// =============================================================================
+
+const SECRET_SCRIPT: &str = "\
+Start.
+- Learn the word
+    save secret = \"xyzzy\"
+    Learned.
+- Skip it
+    Skipped.
+The word is {secret}.
+";
+
+#[test]
+fn dry_run_finds_error_on_untaken_branch() {
+    use bobbin_runtime::{ChoiceStrategy, RuntimeError};
+
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let runtime = Runtime::new(SECRET_SCRIPT, Arc::clone(&storage), host).unwrap();
+
+    let errors = runtime.dry_run(ChoiceStrategy::All);
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert!(
+        matches!(&errors[0], RuntimeError::MissingSaveVariable { name } if name == "secret"),
+        "{:?}",
+        errors
+    );
+
+    // The happy path alone is clean, and nothing was written to real storage
+    assert!(runtime.dry_run(ChoiceStrategy::First).is_empty());
+    assert!(!storage.contains("secret"));
+    assert_eq!(runtime.current_line(), "Start.");
+}
+
+#[test]
+fn dry_run_terminates_on_loops() {
+    use bobbin_runtime::ChoiceStrategy;
+
+    let source = "== top ==\nAgain?\n- Yes\n    goto top\n- No\nBye.\n";
+    assert!(runtime(source).dry_run(ChoiceStrategy::All).is_empty());
+}
+
+// =============================================================================
+// Flow graph
+// =============================================================================
+
+#[test]
+fn flow_graph_of_branching_script() {
+    let source = concat!(
+        "temp gold = 5\n",
+        "== camp ==\n",
+        "You rest with {gold} gold.\n",
+        "- Rest again\n",
+        "    goto camp\n",
+        "- Leave\n",
+        "    You walk away.\n",
+        "Goodbye.\n",
+    );
+    let graph = runtime(source).flow_graph();
+
+    let labels: Vec<(FlowNodeKind, &str)> = graph
+        .nodes
+        .iter()
+        .map(|node| (node.kind, node.label.as_str()))
+        .collect();
+    assert_eq!(
+        labels,
+        [
+            (FlowNodeKind::Line, "You rest with {gold} gold."),
+            (FlowNodeKind::ChoiceSet, "Rest again / Leave"),
+            (FlowNodeKind::Line, "You walk away."),
+            (FlowNodeKind::Line, "Goodbye."),
+            (FlowNodeKind::End, ""),
+        ]
+    );
+    assert_eq!(graph.start, Some(graph.nodes[0].id));
+    assert_eq!(graph.edges.len(), 5);
+
+    // The loop back to camp is an edge, not a second copy of the line
+    let choice_set = graph.nodes[1].id;
+    let choice_edges: Vec<(usize, Option<&str>)> = graph
+        .edges
+        .iter()
+        .filter(|edge| edge.from == choice_set)
+        .map(|edge| (edge.to, edge.label.as_deref()))
+        .collect();
+    assert_eq!(
+        choice_edges,
+        [
+            (graph.nodes[0].id, Some("Rest again")),
+            (graph.nodes[2].id, Some("Leave")),
+        ]
+    );
+}
+
+// =============================================================================
+// Localization
+// =============================================================================
+
+#[test]
+fn extract_strings_ignores_choice_tags_for_ids() {
+    let source = "Pick one\n- Shout #angry\n- Glare #angry\n- Flee\n";
+    let entries = extract_strings(source).unwrap();
+
+    let kinds: Vec<LocKind> = entries.iter().map(|entry| entry.kind).collect();
+    assert_eq!(
+        kinds,
+        [
+            LocKind::Line,
+            LocKind::Choice,
+            LocKind::Choice,
+            LocKind::Choice
+        ]
+    );
+    assert_eq!(entries[1].text, "Shout");
+    assert!(entries.iter().all(|entry| entry.id.len() == 16));
+    assert_ne!(
+        entries[1].id, entries[2].id,
+        "shared tags keep ids distinct"
+    );
+}
+
+#[test]
+fn extract_strings_keeps_interpolations_verbatim() {
+    let source = "save gold = 10\nYou have {gold:0.2} gold {{coins}}.\n- Spend {gold}\n";
+    let entries = extract_strings(source).unwrap();
+
+    let texts: Vec<&str> = entries.iter().map(|entry| entry.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        ["You have {gold:0.2} gold {{coins}}.", "Spend {gold}"]
+    );
+}
+
+#[test]
+fn extract_strings_numbers_expressions() {
+    let source = "save gold = 10\nNext: {gold + 1}, then {gold * 2:0.2} ({gold}).\n";
+    let entries = extract_strings(source).unwrap();
+
+    assert_eq!(entries[0].text, "Next: {0}, then {1:0.2} ({gold}).");
+}
+
+#[test]
+fn string_table_round_trips_extracted_text() {
+    let source = concat!(
+        "save gold = 10\n",
+        "You have { gold } gold.\n",
+        "Next: {gold + 1}, then {gold * 2:0.2} {{braces}}.\n",
+        "- Spend {gold - 3}\n",
+        "- Keep {gold}\n",
+    );
+    let table: HashMap<String, String> = extract_strings(source)
+        .unwrap()
+        .into_iter()
+        .map(|entry| (entry.id, entry.text))
+        .collect();
+
+    let mut translated = runtime(source).with_string_table(table).unwrap();
+    let mut original = runtime(source);
+    while original.current_choices().is_empty() {
+        assert_eq!(translated.current_line(), original.current_line());
+        translated.advance().unwrap();
+        original.advance().unwrap();
+    }
+    assert_eq!(translated.current_choices(), original.current_choices());
+    assert_eq!(original.current_choices(), ["Spend 7", "Keep 10"]);
+}
+
+#[test]
+fn extract_strings_ids_survive_text_edits() {
+    let before = extract_strings("Hello.\n- Go\n    Gone.\n").unwrap();
+    let after = extract_strings("Hello there.\n- Leave\n    Left.\n").unwrap();
+
+    let ids = |entries: &[LocEntry]| -> Vec<String> {
+        entries.iter().map(|entry| entry.id.clone()).collect()
+    };
+    assert_eq!(ids(&before), ids(&after));
+    let mut unique = ids(&before);
+    unique.sort();
+    unique.dedup();
+    assert_eq!(unique.len(), 3);
+}
+
+#[test]
+fn string_table_translates_lines_and_choices() {
+    let source = "temp name = \"Ada\"\nHello, {name}!\n- Wave #greeting\n- Leave\n";
+    let entries = extract_strings(source).unwrap();
+    let table = HashMap::from([
+        (entries[0].id.clone(), "Bonjour, {name} !".to_string()),
+        (entries[1].id.clone(), "Saluer".to_string()),
+    ]);
+
+    let mut french = runtime(source).with_string_table(table).unwrap();
+    assert_eq!(french.current_line(), "Bonjour, Ada !");
+    french.advance().unwrap();
+    assert_eq!(french.current_choices(), ["Saluer", "Leave"]);
+
+    let mut english = runtime(source);
+    assert_eq!(english.current_line(), "Hello, Ada!");
+    english.advance().unwrap();
+    assert_eq!(english.current_choices(), ["Wave", "Leave"]);
+}
+
+#[test]
+fn string_table_rejects_unknown_placeholders() {
+    let source = "temp name = \"Ada\"\nHello, {name}!\n";
+    let line_id = extract_strings(source).unwrap()[0].id.clone();
+    let table = HashMap::from([(line_id, "Bonjour, {nom} !".to_string())]);
+
+    let Err(err) = runtime(source).with_string_table(table) else {
+        panic!("expected a bad translation error");
+    };
+    assert!(
+        matches!(err, RuntimeError::BadTranslation { .. }),
+        "{}",
+        err
+    );
+    assert!(err.to_string().contains("'nom'"), "{}", err);
+}
+
+// =============================================================================
+// Precompiled bytecode
+// =============================================================================
+
+#[test]
+fn from_chunk_runs_precompiled_script() {
+    let chunk = bobbin_runtime::compile_to_chunk("Hello.\nGoodbye.\n").unwrap();
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::from_chunk(chunk, storage, host).unwrap();
+
+    assert_eq!(runtime.current_line(), "Hello.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "Goodbye.");
+    assert!(!runtime.has_more());
+}
+
+#[test]
+fn corrupt_chunk_is_an_error_not_a_panic() {
+    use bobbin_runtime::{BobbinError, Chunk, Instruction, RuntimeError};
+
+    let chunks = [
+        // Reads a local that was never declared
+        vec![Instruction::GetLocal { slot: 3 }, Instruction::Line],
+        // Shows a line with nothing on the stack
+        vec![Instruction::Line],
+        // Jumps past the end of the code
+        vec![Instruction::Jump { target: 10 }],
+    ];
+    for code in chunks {
+        let mut chunk = Chunk::new();
+        for instruction in code {
+            chunk.emit(instruction, 1);
+        }
+        let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+        let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+        let Err(err) = Runtime::from_chunk(chunk, storage, host) else {
+            panic!("corrupt chunk was accepted");
+        };
+        assert!(
+            matches!(err, BobbinError::Runtime(RuntimeError::CorruptChunk { .. })),
+            "{:?}",
+            err
+        );
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialized_chunk_round_trips() {
+    let source =
+        "save gold = 10\nYou have {gold} gold.\n- Buy\n    set gold = 5\n- Leave\nBye, {gold}.\n";
+    let chunk = bobbin_runtime::compile_to_chunk(source).unwrap();
+    let json = serde_json::to_string(&chunk).unwrap();
+    let loaded: bobbin_runtime::Chunk = serde_json::from_str(&json).unwrap();
+    assert_eq!(loaded.disassemble(), chunk.disassemble());
+
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let mut runtime = Runtime::from_chunk(loaded, storage, host).unwrap();
+    assert_eq!(runtime.current_line(), "You have 10 gold.");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_choices(), ["Buy", "Leave"]);
+    runtime.select_choice(0).unwrap();
+    assert_eq!(runtime.current_line(), "Bye, 5.");
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serialized_chunk_rejects_other_version() {
+    let chunk = bobbin_runtime::compile_to_chunk("Hello.\n").unwrap();
+    let mut json: serde_json::Value = serde_json::to_value(&chunk).unwrap();
+    json["version"] = (bobbin_runtime::BYTECODE_VERSION + 1).into();
+
+    let err = serde_json::from_value::<bobbin_runtime::Chunk>(json).unwrap_err();
+    assert!(
+        err.to_string().contains("unsupported bytecode version"),
+        "{}",
+        err
+    );
+}
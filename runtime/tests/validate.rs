@@ -0,0 +1,598 @@
+//! Validation tests - diagnostics without creating a runtime.
+
+use bobbin_runtime::ast::{Stmt, TextPart};
+use bobbin_runtime::diagnostic::{JaroWinklerMatcher, LineIndex, Severity, SourcePosition};
+use bobbin_runtime::{
+    DeclKind, NodeId, ValidateOptions, compile_to_chunk, diagnostics_as_positions,
+    render_diagnostics, validate, validate_full, validate_many, validate_streaming,
+    validate_with_cancel, validate_with_matcher, validate_with_metrics, validate_with_options,
+};
+use std::sync::atomic::AtomicBool;
+
+// =============================================================================
+// Default validation
+// =============================================================================
+
+#[test]
+fn valid_script_has_no_diagnostics() {
+    let source =
+        "save gold = 10\nYou have {gold} gold.\n- Buy\n    set gold = 5\n- Leave\n    Bye.\n";
+    assert!(validate(source).is_empty());
+}
+
+#[test]
+fn reports_parse_errors() {
+    let diagnostics = validate("temp = 5\n");
+    assert!(!diagnostics.is_empty());
+    assert!(diagnostics.iter().all(|d| d.severity == Severity::Error));
+}
+
+#[test]
+fn reports_semantic_errors_with_suggestion() {
+    let diagnostics = validate("temp gold = 5\nYou have {gld} gold.\n");
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].message, "undefined variable 'gld'");
+    assert_eq!(diagnostics[0].suggestions[0].replacement, "gold");
+    // A close match means a typo, not a missing extern
+    assert!(diagnostics[0].notes.is_empty());
+}
+
+#[test]
+fn suggestions_favor_a_recently_used_name() {
+    // "cats" and "cate" are equally close to "cat"; "cate" was just used
+    let source = "temp cats = 1\ntemp cate = 2\nYou see {cate}.\nAnd {cat}.\n";
+    let diagnostics = validate(source);
+    assert_eq!(diagnostics.len(), 1);
+
+    let suggestions: Vec<_> = diagnostics[0]
+        .suggestions
+        .iter()
+        .map(|s| s.replacement.as_str())
+        .collect();
+    assert_eq!(suggestions, ["cate", "cats"]);
+}
+
+#[test]
+fn interpolation_errors_point_at_the_variable_name() {
+    let source = "temp gold = 5\ntemp typo = 1\nGot {gold} and {tyop:0.2} {typo}\n";
+    let diagnostics = validate(source);
+    assert_eq!(diagnostics.len(), 1);
+
+    let span = diagnostics[0].primary_span().unwrap();
+    assert_eq!(span.start, source.find("tyop").unwrap());
+    assert_eq!(&source[span.start..span.end], "tyop");
+
+    // Applying the suggestion keeps the braces and the format
+    let suggestion = &diagnostics[0].suggestions[0];
+    let mut fixed = source.to_string();
+    fixed.replace_range(
+        suggestion.span.start..suggestion.span.end,
+        &suggestion.replacement,
+    );
+    assert_eq!(fixed, source.replace("{tyop:0.2}", "{typo:0.2}"));
+}
+
+// =============================================================================
+// Options
+// =============================================================================
+
+#[test]
+fn resolve_option_controls_semantic_checks() {
+    let source = "Hello, {nobody}!\n";
+
+    let parse_only = ValidateOptions {
+        resolve: false,
+        ..ValidateOptions::default()
+    };
+    assert!(validate_with_options(source, parse_only).is_empty());
+
+    let full = ValidateOptions {
+        resolve: true,
+        ..ValidateOptions::default()
+    };
+    assert_eq!(validate_with_options(source, full).len(), 1);
+}
+
+#[test]
+fn matcher_threshold_controls_suggestions() {
+    let source = "temp gold = 5\nYou have {gd} gold.\n";
+
+    let loose = ValidateOptions {
+        matcher_threshold: 0.5,
+        ..ValidateOptions::default()
+    };
+    assert_eq!(validate_with_options(source, loose)[0].suggestions.len(), 1);
+
+    let strict = ValidateOptions {
+        matcher_threshold: 0.99,
+        ..ValidateOptions::default()
+    };
+    assert!(
+        validate_with_options(source, strict)[0]
+            .suggestions
+            .is_empty()
+    );
+}
+
+#[test]
+fn max_diagnostics_caps_in_source_order() {
+    let source: String = (0..50)
+        .map(|i| format!("Hello, {{typo{}}}!\n", i))
+        .collect();
+    let options = ValidateOptions {
+        max_diagnostics: Some(10),
+        ..ValidateOptions::default()
+    };
+
+    let diagnostics = validate_with_options(&source, options);
+
+    assert_eq!(diagnostics.len(), 11);
+    assert_eq!(diagnostics[0].message, "undefined variable 'typo0'");
+    assert_eq!(diagnostics[9].message, "undefined variable 'typo9'");
+    assert_eq!(diagnostics[10].severity, Severity::Note);
+    assert_eq!(diagnostics[10].message, "40 more diagnostics suppressed");
+    let starts: Vec<usize> = diagnostics
+        .iter()
+        .map(|d| d.primary_span().unwrap().start)
+        .collect();
+    assert!(starts.is_sorted());
+}
+
+#[test]
+fn max_diagnostics_under_the_cap_adds_no_note() {
+    let options = ValidateOptions {
+        max_diagnostics: Some(10),
+        ..ValidateOptions::default()
+    };
+    let diagnostics = validate_with_options("Hello, {nobody}!\n", options);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+}
+
+#[test]
+fn custom_matcher_drops_borderline_suggestion() {
+    // 'mood' and 'mode' score about 0.87
+    let source = "temp mood = \"calm\"\nYou feel {mode}.\n";
+
+    let default = validate(source);
+    assert_eq!(default[0].suggestions.len(), 1);
+    assert_eq!(default[0].suggestions[0].message, "did you mean 'mood'?");
+
+    let strict = JaroWinklerMatcher::new(0.9);
+    assert!(
+        validate_with_matcher(source, &strict)[0]
+            .suggestions
+            .is_empty()
+    );
+
+    let err = compile_to_chunk(source).unwrap_err();
+    assert_eq!(err.to_diagnostics()[0].suggestions.len(), 1);
+    assert!(
+        err.to_diagnostics_with_matcher(&strict)[0]
+            .suggestions
+            .is_empty()
+    );
+}
+
+// =============================================================================
+// Cancellation
+// =============================================================================
+
+#[test]
+fn validate_with_cancel_runs_to_completion_when_not_cancelled() {
+    let source = "Hello {missing}\n".repeat(50);
+    let cancel = AtomicBool::new(false);
+
+    assert_eq!(validate_with_cancel(&source, &cancel).len(), 50);
+}
+
+#[test]
+fn validate_with_cancel_stops_when_cancelled() {
+    let source = "Hello {missing}\n".repeat(50);
+    let cancel = AtomicBool::new(true);
+
+    assert!(validate_with_cancel(&source, &cancel).is_empty());
+}
+
+// =============================================================================
+// Full results
+// =============================================================================
+
+#[test]
+fn validate_full_returns_ast_and_symbols() {
+    let source =
+        "save gold = 10\nYou have {gold} gold.\n- Buy\n    set gold = 5\n- Leave\n    Bye.\n";
+    let result = validate_full(source);
+
+    assert!(result.diagnostics.is_empty());
+    let ast = result.ast.expect("valid script should parse");
+    assert_eq!(ast.statements.len(), 3);
+    assert!(matches!(ast.statements[2], Stmt::ChoiceSet { .. }));
+
+    let symbols = result.symbols.expect("valid script should resolve");
+    assert!(symbols.save_bindings.values().all(|name| name == "gold"));
+    // Declaration, interpolation, and assignment all bind to the save variable
+    assert_eq!(symbols.save_bindings.len(), 3);
+}
+
+/// Ids of statements, choices, and interpolated variables, in source order.
+fn node_ids(statements: &[Stmt], ids: &mut Vec<NodeId>) {
+    for stmt in statements {
+        ids.extend(stmt.node_id());
+        let parts = match stmt {
+            Stmt::Line { parts, .. } => parts.as_slice(),
+            Stmt::ChoiceSet { choices, .. } => {
+                for choice in choices {
+                    ids.push(choice.id);
+                    ids.extend(choice.parts.iter().filter_map(TextPart::node_id));
+                    node_ids(&choice.nested, ids);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        ids.extend(parts.iter().filter_map(TextPart::node_id));
+    }
+}
+
+#[test]
+fn node_ids_are_stable_across_parses() {
+    let source = concat!(
+        "save gold = 10\n",
+        "extern name\n",
+        "Hello, {name}. You have {gold} gold.\n",
+        "- Buy {gold}\n",
+        "    set gold -= 5\n",
+        "    temp left = gold\n",
+        "- Leave\n",
+        "    Bye.\n",
+    );
+    let parse = || {
+        let mut ids = Vec::new();
+        node_ids(&validate_full(source).ast.unwrap().statements, &mut ids);
+        ids
+    };
+
+    let first = parse();
+    assert_eq!(first, parse());
+    assert_eq!(first.len(), 11);
+    assert!(first.is_sorted_by(|a, b| a.0 < b.0), "{:?}", first);
+}
+
+#[test]
+fn script_walk_visits_nested_statements_in_source_order() {
+    let source =
+        "Intro.\n- A\n    In A.\n    - A1\n        Deep.\n    - A2\n- B\n    In B.\nOutro.\n";
+    let ast = validate_full(source).ast.unwrap();
+
+    let visited: Vec<(&str, usize)> = ast
+        .walk()
+        .map(|(stmt, depth)| {
+            let span = stmt.span();
+            let text = source[span.start..span.end].lines().next().unwrap();
+            (text, depth)
+        })
+        .collect();
+    assert_eq!(
+        visited,
+        [
+            ("Intro.", 0),
+            ("- A", 0),
+            ("In A.", 1),
+            ("- A1", 1),
+            ("Deep.", 2),
+            ("In B.", 1),
+            ("Outro.", 0),
+        ]
+    );
+
+    let choice_sets = ast
+        .walk()
+        .filter(|(stmt, _)| matches!(stmt, Stmt::ChoiceSet { .. }))
+        .count();
+    assert_eq!(choice_sets, 2);
+}
+
+#[test]
+fn choice_branch_spans_cover_nested_statements() {
+    let source = "Pick.\n- Stay\n    Good.\n    - Really\n        Yes.\n- Go\n    Bye.\nDone.\n";
+    let ast = validate_full(source).ast.unwrap();
+    let chunk = compile_to_chunk(source).unwrap();
+
+    let mut branches = Vec::new();
+    for (stmt, _) in ast.walk() {
+        let Stmt::ChoiceSet { choices, .. } = stmt else {
+            continue;
+        };
+        for choice in choices {
+            let span = choice.branch_span;
+            branches.push(&source[span.start..span.end]);
+
+            // The branch's instructions show its nested lines
+            let range = &chunk.branches[&choice.id];
+            let shown: Vec<NodeId> = chunk
+                .line_nodes
+                .iter()
+                .filter(|(offset, _)| range.contains(offset))
+                .map(|(_, id)| *id)
+                .collect();
+            for stmt in &choice.nested {
+                if let Stmt::Line { id, .. } = stmt {
+                    assert!(shown.contains(id), "{:?} not in {:?}", id, range);
+                }
+            }
+        }
+    }
+    assert_eq!(
+        branches,
+        ["Good.\n    - Really\n        Yes.", "Bye.", "Yes."]
+    );
+}
+
+#[test]
+fn choice_without_nested_statements_has_an_empty_branch_span() {
+    let source = "- Leave\n";
+    let ast = validate_full(source).ast.unwrap();
+    let Stmt::ChoiceSet { choices, .. } = &ast.statements[0] else {
+        panic!("expected a choice set");
+    };
+    assert_eq!(choices[0].branch_span.start, source.find('\n').unwrap());
+    assert!(choices[0].branch_span.is_empty());
+}
+
+#[test]
+fn validate_full_records_declarations() {
+    let source = "save gold = 1\nextern player\n- Shop\n    temp price = 5\n- Leave\n";
+    let symbols = validate_full(source)
+        .symbols
+        .expect("valid script should resolve");
+
+    let (kind, span) = symbols.declarations["gold"];
+    assert_eq!(kind, DeclKind::Save);
+    assert_eq!(&source[span.start..span.end], "save gold = 1");
+    assert_eq!(symbols.declarations["player"].0, DeclKind::Extern);
+    assert_eq!(symbols.declarations["price"].0, DeclKind::Temp);
+}
+
+#[test]
+fn validate_full_records_doc_comments() {
+    let source = concat!(
+        "save gold = 0 // starting coins\n",
+        "extern player   //  the player's name  \n",
+        "temp mood = \"calm\" //\n",
+        "set gold = 5 // not a declaration\n",
+        "Hello, {player}.\n",
+    );
+    let symbols = validate_full(source)
+        .symbols
+        .expect("valid script should resolve");
+
+    assert_eq!(symbols.doc("gold"), Some("starting coins"));
+    assert_eq!(symbols.doc("player"), Some("the player's name"));
+    assert_eq!(symbols.doc("mood"), None);
+    assert_eq!(symbols.docs.len(), 2);
+}
+
+#[test]
+fn validate_full_lists_all_variables_in_order() {
+    let source =
+        "extern player\nconst max = 3\nsave gold = 1\n- Shop\n    temp price = 5\n- Leave\n";
+    let symbols = validate_full(source)
+        .symbols
+        .expect("valid script should resolve");
+
+    assert_eq!(symbols.all_variables(), ["player", "max", "gold", "price"]);
+}
+
+#[test]
+fn validate_full_lists_required_externs() {
+    let source = "extern player\nsave gold = 1\nextern day\nDay {day}, {player}.\n";
+    let symbols = validate_full(source)
+        .symbols
+        .expect("valid script should resolve");
+
+    assert_eq!(symbols.required_externs(), ["player", "day"]);
+}
+
+#[test]
+fn validate_full_keeps_ast_when_resolution_fails() {
+    let result = validate_full("Hello, {nobody}!\n");
+
+    assert_eq!(result.diagnostics.len(), 1);
+    assert!(result.ast.is_some());
+    assert!(result.symbols.is_none());
+}
+
+#[test]
+fn validate_full_has_no_ast_when_parsing_fails() {
+    let result = validate_full("temp = 5\n");
+
+    assert!(!result.diagnostics.is_empty());
+    assert!(result.ast.is_none());
+    assert!(result.symbols.is_none());
+}
+
+#[test]
+fn validate_full_keeps_statements_around_a_broken_line() {
+    let result = validate_full("Hello.\ntemp = 5\nGoodbye.\n");
+
+    assert_eq!(result.diagnostics.len(), 1);
+    let partial = result
+        .partial_ast
+        .expect("parse errors should keep a partial AST");
+    assert_eq!(partial.statements.len(), 2);
+    assert!(
+        partial
+            .statements
+            .iter()
+            .all(|stmt| matches!(stmt, Stmt::Line { .. }))
+    );
+}
+
+// =============================================================================
+// Streaming
+// =============================================================================
+
+#[test]
+fn validate_streaming_delivers_each_syntax_error() {
+    let source = "temp = 5\nset = 3\nFine.\nsave gold 10\n";
+    let mut streamed = Vec::new();
+    validate_streaming(source, &mut |diag| streamed.push(diag.message));
+
+    assert_eq!(streamed.len(), 3, "{:#?}", streamed);
+    let collected: Vec<_> = validate(source).into_iter().map(|d| d.message).collect();
+    assert_eq!(streamed, collected);
+}
+
+#[test]
+fn validate_streaming_delivers_semantic_errors_and_warnings() {
+    let source = "temp gold = 5\nYou have {gld} and {silvr}.\ngoto end\nNever.\n== end ==\n";
+    let mut calls = 0;
+    let mut severities = Vec::new();
+    validate_streaming(source, &mut |diag| {
+        calls += 1;
+        severities.push(diag.severity);
+    });
+
+    assert_eq!(calls, 3);
+    assert_eq!(
+        severities,
+        [Severity::Error, Severity::Error, Severity::Warning]
+    );
+}
+
+// =============================================================================
+// Multiple sources
+// =============================================================================
+
+#[test]
+fn validate_many_groups_diagnostics_by_source() {
+    let sources = [
+        ("intro.bobbin".to_string(), "Hello.\n"),
+        ("shop.bobbin".to_string(), "You have {gold} gold.\n"),
+    ];
+    let results = validate_many(&sources);
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].0, "intro.bobbin");
+    assert!(results[0].1.is_empty(), "{:#?}", results[0].1);
+    assert_eq!(results[1].0, "shop.bobbin");
+    assert_eq!(results[1].1.len(), 1, "{:#?}", results[1].1);
+    assert!(results[1].1[0].message.contains("gold"));
+}
+
+#[test]
+fn validate_many_shares_declarations_through_includes() {
+    // Each problem is reported once, under the file it is in
+    let sources = [
+        (
+            "main.bobbin".to_string(),
+            "include \"vars.bobbin\"\nYou have {gold} gold.\n",
+        ),
+        (
+            "vars.bobbin".to_string(),
+            "save gold = 10\n- Same\n- Same\n",
+        ),
+    ];
+    let results = validate_many(&sources);
+
+    assert!(results[0].1.is_empty(), "{:#?}", results[0].1);
+    assert_eq!(results[1].1.len(), 1, "{:#?}", results[1].1);
+    assert_eq!(results[1].1[0].severity, Severity::Warning);
+}
+
+// =============================================================================
+// Rendering
+// =============================================================================
+
+#[test]
+fn render_diagnostics_renders_each_problem() {
+    let source = "save gold = 10\nYou have {golden} gold.\n";
+    let plain = render_diagnostics("shop.bobbin", source, false);
+    assert!(plain.contains("golden"), "{}", plain);
+    assert!(plain.contains("shop.bobbin"), "{}", plain);
+    assert!(!plain.contains('\x1b'), "{}", plain);
+
+    let colored = render_diagnostics("shop.bobbin", source, true);
+    assert!(colored.contains('\x1b'), "{}", colored);
+}
+
+#[test]
+fn render_diagnostics_is_empty_for_a_clean_script() {
+    assert_eq!(render_diagnostics("shop.bobbin", "Hello.\n", false), "");
+}
+
+#[test]
+fn diagnostics_as_positions_match_the_line_index() {
+    let source = "save gold = 10\nÉtienne says: {golden} 🪙\nBye.\n";
+    let index = LineIndex::new(source);
+    let diagnostics = validate(source);
+    let span = diagnostics[0].primary_span().unwrap();
+
+    for use_utf16 in [true, false] {
+        let positioned = diagnostics_as_positions(source, use_utf16);
+        assert_eq!(positioned.len(), diagnostics.len(), "{:#?}", positioned);
+        assert_eq!(positioned[0].message, "undefined variable 'golden'");
+        assert_eq!(positioned[0].severity, 1);
+        assert_eq!(
+            positioned[0].start,
+            index.to_lsp_position(span.start, use_utf16)
+        );
+        assert_eq!(
+            positioned[0].end,
+            index.to_lsp_position(span.end, use_utf16)
+        );
+    }
+    assert_eq!(
+        diagnostics_as_positions(source, true)[0].start,
+        SourcePosition {
+            line: 1,
+            column: 15
+        }
+    );
+    assert_eq!(
+        diagnostics_as_positions(source, false)[0].start,
+        SourcePosition {
+            line: 1,
+            column: 16
+        }
+    );
+}
+
+#[test]
+fn positioned_diagnostics_locate_a_parse_failure() {
+    let source = "Hello.\nset = 5\n";
+    let err = compile_to_chunk(source).unwrap_err();
+    let positioned = err.to_positioned_diagnostics(source);
+
+    assert_eq!(positioned.len(), 1, "{:#?}", positioned);
+    let (diagnostic, start, end) = &positioned[0];
+    assert_eq!(diagnostic.message, err.to_diagnostics()[0].message);
+    assert_eq!(*start, SourcePosition { line: 1, column: 4 });
+    assert_eq!(*end, SourcePosition { line: 1, column: 5 });
+}
+
+// =============================================================================
+// Metrics
+// =============================================================================
+
+#[test]
+fn validate_with_metrics_times_each_stage() {
+    let source = "save gold = 10\nYou have {gold} gold.\n- Buy\n    set gold = 5\n    Sold.\n- Leave\n    Bye.\n";
+    let (diagnostics, metrics) = validate_with_metrics(source);
+
+    assert!(diagnostics.is_empty());
+    assert!(metrics.resolve.is_some());
+    // Three top-level statements plus the three in the branches
+    assert_eq!(metrics.statements, 6);
+    assert!(metrics.tokens > metrics.statements);
+}
+
+#[test]
+fn validate_with_metrics_skips_resolve_after_syntax_errors() {
+    let (diagnostics, metrics) = validate_with_metrics("Hello.\ntemp = 5\nGoodbye.\n");
+
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(metrics.resolve, None);
+    assert_eq!(metrics.statements, 2);
+}
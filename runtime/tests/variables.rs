@@ -2,6 +2,8 @@
 
 mod support;
 
+use bobbin_runtime::{Instruction, Value, compile_to_chunk};
+
 // =============================================================================
 // Basic Interpolation
 // =============================================================================
@@ -45,6 +47,28 @@ fn assignment_types() {
     support::run_output_test(&support::cases_dir().join("variables/assignment_types.bobbin"));
 }
 
+#[test]
+fn compound_assignment() {
+    support::run_output_test(&support::cases_dir().join("variables/compound/numbers.bobbin"));
+}
+
+#[test]
+fn compound_assignment_save() {
+    support::run_output_test(&support::cases_dir().join("variables/compound/save.bobbin"));
+}
+
+#[test]
+fn errors_division_by_zero() {
+    support::run_error_test(&support::cases_dir().join("variables/errors/division_by_zero.bobbin"));
+}
+
+#[test]
+fn errors_arithmetic_type_mismatch() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/arithmetic_type_mismatch.bobbin"),
+    );
+}
+
 // =============================================================================
 // Save Variables
 // =============================================================================
@@ -105,6 +129,55 @@ fn save_mixed_with_temp() {
     );
 }
 
+#[test]
+fn storage_iter_lists_every_save() {
+    use bobbin_runtime::{HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::{EmptyHostState, MemoryStorage};
+
+    let source =
+        "save gold = 10\nsave name = \"Ada\"\nsave met = false\ntemp mood = 1\nset gold = 5\nHi.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let _runtime = Runtime::new(source, Arc::clone(&storage), host).unwrap();
+
+    // Order is unspecified, so sort before comparing
+    let mut variables = storage.iter();
+    variables.sort_by(|a, b| a.0.cmp(&b.0));
+    assert_eq!(
+        variables,
+        [
+            ("gold".to_string(), Value::Number(5.0)),
+            ("met".to_string(), Value::Bool(false)),
+            ("name".to_string(), Value::String("Ada".to_string())),
+        ]
+    );
+}
+
+// =============================================================================
+// Const Variables
+// =============================================================================
+
+#[test]
+fn const_basic() {
+    support::run_output_test(&support::cases_dir().join("variables/const/basic.bobbin"));
+}
+
+#[test]
+fn const_compiles_to_constant_load() {
+    let chunk = compile_to_chunk("const MAX_GOLD = 9999\nCarry {MAX_GOLD}.\n").unwrap();
+
+    assert!(
+        !chunk.code.iter().any(|op| matches!(
+            op,
+            Instruction::GetLocal { .. } | Instruction::DeclareLocal { .. }
+        )),
+        "{}",
+        chunk.disassemble()
+    );
+    assert!(chunk.constants.contains(&Value::Number(9999.0)));
+}
+
 // =============================================================================
 // Extern Variables (Host State)
 // =============================================================================
@@ -157,6 +230,46 @@ fn extern_mixed_with_temp_and_save() {
     );
 }
 
+#[test]
+fn check_externs_flags_missing_host_variables() {
+    use bobbin_runtime::{HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::{MemoryStorage, MockHostState};
+
+    let source = "extern gold\nextern reputation\nHello.\n- Shop\n    {gold} gold, {reputation} rep.\n- Leave\n";
+    let mut host = MockHostState::new();
+    host.set("gold", Value::Number(10.0));
+
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(host);
+    let runtime = Runtime::new(source, storage, host).unwrap();
+
+    assert_eq!(runtime.check_externs(), ["reputation"]);
+}
+
+#[test]
+fn var_kind_reports_each_declaration() {
+    use bobbin_runtime::{DeclKind, HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::{MemoryStorage, MockHostState};
+
+    let source = "extern gold\nsave visits = 0\nconst max = 3\nHello.\n- Shop\n    temp price = 5\n    That is {price} of your {gold} gold.\n";
+    let mut host = MockHostState::new();
+    host.set("gold", Value::Number(10.0));
+
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(host);
+    let mut runtime = Runtime::new(source, storage, host).unwrap();
+    runtime.advance().unwrap();
+    runtime.select_choice(0).unwrap();
+
+    assert_eq!(runtime.var_kind("gold"), Some(DeclKind::Extern));
+    assert_eq!(runtime.var_kind("visits"), Some(DeclKind::Save));
+    assert_eq!(runtime.var_kind("max"), Some(DeclKind::Const));
+    assert_eq!(runtime.var_kind("price"), Some(DeclKind::Temp));
+    assert_eq!(runtime.var_kind("silver"), None);
+}
+
 #[test]
 fn extern_missing_at_runtime() {
     // Test that using a declared extern variable that the host doesn't provide
@@ -183,6 +296,37 @@ fn extern_missing_at_runtime() {
     }
 }
 
+#[test]
+fn extern_missing_uses_host_default() {
+    use bobbin_runtime::{HostState, Runtime, VariableStorage};
+    use std::sync::Arc;
+    use support::MemoryStorage;
+
+    /// Provides only `gold`, with a placeholder for anything else.
+    struct LenientHost;
+
+    impl HostState for LenientHost {
+        fn lookup(&self, name: &str) -> Option<Value> {
+            (name == "gold").then_some(Value::Number(10.0))
+        }
+
+        fn default_for(&self, name: &str) -> Option<Value> {
+            Some(Value::String(format!("<{}>", name)))
+        }
+    }
+
+    let source = "extern gold\nextern player_name\nHello, {player_name}. You have {gold} gold.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(LenientHost);
+    let runtime = Runtime::new(source, storage, host).unwrap();
+
+    assert_eq!(
+        runtime.current_line(),
+        "Hello, <player_name>. You have 10 gold."
+    );
+    assert!(runtime.check_externs().is_empty());
+}
+
 // =============================================================================
 // Type-specific Interpolation
 // =============================================================================
@@ -212,6 +356,18 @@ fn types_empty_string() {
     support::run_output_test(&support::cases_dir().join("variables/types/empty_string.bobbin"));
 }
 
+#[test]
+fn format_numbers() {
+    support::run_output_test(&support::cases_dir().join("variables/format/numbers.bobbin"));
+}
+
+#[test]
+fn errors_format_non_number() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/format_non_number.bobbin"),
+    );
+}
+
 // =============================================================================
 // Variables in Choices
 // =============================================================================
@@ -280,6 +436,30 @@ fn in_choices_sibling_reuse_path_b() {
     );
 }
 
+#[test]
+fn in_choices_sibling_nested_copper() {
+    support::run_trace_test(
+        &support::cases_dir().join("variables/in_choices/sibling_nested.bobbin"),
+        "copper",
+    );
+}
+
+#[test]
+fn in_choices_sibling_nested_nothing() {
+    support::run_trace_test(
+        &support::cases_dir().join("variables/in_choices/sibling_nested.bobbin"),
+        "nothing",
+    );
+}
+
+#[test]
+fn in_choices_branch_then_outer() {
+    support::run_trace_test(
+        &support::cases_dir().join("variables/in_choices/branch_then_outer.bobbin"),
+        "path_a",
+    );
+}
+
 #[test]
 fn in_choices_outer_scope_assignment_cheer_up() {
     support::run_trace_test(
@@ -305,11 +485,32 @@ fn errors_undefined() {
     support::run_error_test(&support::cases_dir().join("variables/errors/undefined.bobbin"));
 }
 
+#[test]
+fn errors_undefined_similar() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/undefined_similar.bobbin"),
+    );
+}
+
+#[test]
+fn errors_undefined_host_hint() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/undefined_host_hint.bobbin"),
+    );
+}
+
 #[test]
 fn errors_shadowing() {
     support::run_error_test(&support::cases_dir().join("variables/errors/shadowing.bobbin"));
 }
 
+#[test]
+fn errors_shadowing_in_branch() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/shadowing_in_branch.bobbin"),
+    );
+}
+
 #[test]
 fn errors_redeclaration() {
     support::run_error_test(&support::cases_dir().join("variables/errors/redeclaration.bobbin"));
@@ -355,6 +556,18 @@ fn errors_save_set_undefined() {
     );
 }
 
+#[test]
+fn errors_const_assignment() {
+    support::run_error_test(&support::cases_dir().join("variables/errors/const_assignment.bobbin"));
+}
+
+#[test]
+fn errors_temp_shadows_const() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/temp_shadows_const.bobbin"),
+    );
+}
+
 // =============================================================================
 // Extern Semantic Errors
 // =============================================================================
@@ -366,6 +579,13 @@ fn errors_extern_assignment() {
     );
 }
 
+#[test]
+fn errors_extern_compound_assignment() {
+    support::run_error_test(
+        &support::cases_dir().join("variables/errors/extern_compound_assignment.bobbin"),
+    );
+}
+
 #[test]
 fn errors_extern_redeclaration() {
     support::run_error_test(
@@ -0,0 +1,117 @@
+//! Visit tracking tests - counting how often lines and choices are reached.
+
+mod support;
+
+use bobbin_runtime::{Coverage, Value};
+use support::runtime;
+
+// =============================================================================
+// Lines
+// =============================================================================
+
+#[test]
+fn line_is_visited_when_shown() {
+    let mut runtime = runtime("First.\nSecond.\n");
+
+    let first = runtime.current_line_node().unwrap();
+    assert_eq!(runtime.visit_count(first), 1);
+
+    runtime.advance().unwrap();
+    let second = runtime.current_line_node().unwrap();
+    assert_ne!(first, second);
+    assert_eq!(runtime.visit_count(first), 1);
+    assert_eq!(runtime.visit_count(second), 1);
+    assert!(runtime.has_visited(second));
+}
+
+// =============================================================================
+// Choices
+// =============================================================================
+
+#[test]
+fn only_selected_choice_is_visited() {
+    let mut runtime = runtime("- Left\n    You went left.\n- Right\n    You went right.\nDone.\n");
+
+    let choices = runtime.current_choice_nodes().to_vec();
+    assert_eq!(choices.len(), 2);
+    assert!(runtime.current_line_node().is_none());
+    assert!(!runtime.has_visited(choices[0]));
+
+    runtime.select_choice(1).unwrap();
+    assert_eq!(runtime.current_line(), "You went right.");
+    assert!(!runtime.has_visited(choices[0]));
+    assert_eq!(runtime.visit_count(choices[1]), 1);
+
+    // Counts persist as dialogue continues
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "Done.");
+    assert_eq!(runtime.visit_count(choices[1]), 1);
+    assert!(runtime.current_choice_nodes().is_empty());
+}
+
+#[test]
+fn invalid_choice_index_does_not_count_as_visit() {
+    let mut runtime = runtime("- Left\n- Right\n");
+    let choices = runtime.current_choice_nodes().to_vec();
+
+    assert!(runtime.select_choice(5).is_err());
+    assert!(choices.iter().all(|&node| !runtime.has_visited(node)));
+}
+
+#[test]
+fn goto_loop_counts_each_pass() {
+    let mut runtime = runtime("== top ==\nAgain?\n- Yes\n    goto top\n- No\n");
+    let line = runtime.current_line_node().unwrap();
+    assert_eq!(runtime.visit_count(line), 1);
+
+    runtime.advance().unwrap();
+    let choices = runtime.current_choice_nodes().to_vec();
+    runtime.select_choice(0).unwrap();
+    assert_eq!(runtime.current_line(), "Again?");
+    assert_eq!(runtime.visit_count(line), 2);
+    assert_eq!(runtime.visit_count(choices[0]), 1);
+}
+
+// =============================================================================
+// Coverage
+// =============================================================================
+
+#[test]
+fn coverage_reports_the_branch_not_taken() {
+    let source = "Pick.\n- Left\n    You went left.\n- Right\n    You went right.\nDone.\n";
+    let mut runtime = runtime(source);
+    assert_eq!(
+        runtime.coverage(),
+        Coverage {
+            visited_nodes: 1,
+            total_nodes: 6,
+        }
+    );
+
+    runtime.advance().unwrap();
+    runtime.select_choice(1).unwrap();
+    runtime.advance().unwrap();
+    assert_eq!(runtime.current_line(), "Done.");
+
+    assert_eq!(runtime.coverage().visited_nodes, 4);
+    let unvisited: Vec<&str> = runtime
+        .unvisited_spans()
+        .into_iter()
+        .map(|span| &source[span.start..span.end])
+        .collect();
+    assert_eq!(unvisited, ["- Left", "You went left."]);
+}
+
+#[test]
+fn reset_starts_coverage_over() {
+    let mut runtime = runtime("save met = false\nHello.\nset met = true\nAgain.\n");
+    runtime.advance().unwrap();
+    assert_eq!(runtime.coverage().visited_nodes, 2);
+
+    runtime.reset().unwrap();
+    assert_eq!(runtime.current_line(), "Hello.");
+    assert!(runtime.has_more());
+    assert_eq!(runtime.coverage().visited_nodes, 1);
+    // Saved state outlives the reset
+    assert_eq!(runtime.storage().get("met"), Some(Value::Bool(true)));
+}
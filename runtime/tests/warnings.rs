@@ -0,0 +1,354 @@
+//! Warning tests - diagnostics that flag likely mistakes without failing compilation.
+
+mod support;
+
+use bobbin_runtime::diagnostic::{Diagnostic, LabelStyle, Severity};
+use bobbin_runtime::token::Span;
+use bobbin_runtime::{
+    BobbinError, HostState, Runtime, RuntimeBuilder, ValidateOptions, VariableStorage, validate,
+    validate_with_options,
+};
+use std::sync::Arc;
+use support::{EmptyHostState, MemoryStorage};
+
+fn warnings(source: &str) -> Vec<Diagnostic> {
+    validate(source)
+        .into_iter()
+        .filter(|d| d.severity == Severity::Warning)
+        .collect()
+}
+
+fn span_of(source: &str, text: &str, occurrence: usize) -> Span {
+    let start = source
+        .match_indices(text)
+        .nth(occurrence)
+        .unwrap_or_else(|| panic!("{:?} occurrence {} not in source", text, occurrence))
+        .0;
+    Span {
+        start,
+        end: start + text.len(),
+    }
+}
+
+fn secondary_spans(diagnostic: &Diagnostic) -> Vec<Span> {
+    diagnostic
+        .labels
+        .iter()
+        .filter(|l| l.style == LabelStyle::Secondary)
+        .map(|l| l.span)
+        .collect()
+}
+
+// =============================================================================
+// Duplicate choices
+// =============================================================================
+
+#[test]
+fn duplicate_choice_text() {
+    let source = "Where to?\n- Go north\n- Go south\n- Go north\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "duplicate choice 'Go north'");
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "- Go north", 1))
+    );
+    assert_eq!(
+        secondary_spans(&warnings[0]),
+        [span_of(source, "- Go north", 0)]
+    );
+}
+
+#[test]
+fn duplicate_choice_only_within_one_set() {
+    let source = "- Ask again\n- Leave\nThey shrug.\n- Ask again\n- Stay\n";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn duplicate_choice_ignores_interpolated_text() {
+    let source = "temp a = \"x\"\ntemp b = \"y\"\n- Take {a}\n- Take {b}\n";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn duplicate_choice_does_not_block_runtime() {
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    let runtime = Runtime::new("- Same\n- Same\n", storage, host).unwrap();
+    assert_eq!(runtime.current_choices(), ["Same", "Same"]);
+}
+
+// =============================================================================
+// Empty choices
+// =============================================================================
+
+#[test]
+fn empty_choice_text() {
+    let source = "Pick one\n- \n- Leave\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "choice has no text");
+    // The span covers the choice marker
+    assert_eq!(warnings[0].primary_span(), Some(span_of(source, "-", 0)));
+}
+
+#[test]
+fn empty_choice_text_not_reported_for_interpolation() {
+    let source = "temp label = \"\"\n- {label}\n- Leave\n";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn repeated_empty_choices_warn_once_each() {
+    let source = "- \n- \n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 2, "{:#?}", warnings);
+    assert!(warnings.iter().all(|w| w.message == "choice has no text"));
+}
+
+// =============================================================================
+// Empty choice branches
+// =============================================================================
+
+#[test]
+fn empty_branch_beside_a_full_one() {
+    let source = "The guard blocks the way.\n- Bribe him\n    He pockets the coin.\n- Walk away\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "choice branch is empty");
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "Walk away", 0))
+    );
+}
+
+#[test]
+fn empty_branches_in_a_plain_menu_are_not_reported() {
+    let source = "What's your name?\n- Alice\n- Bob\nNice to meet you!\n";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn full_branches_are_not_reported() {
+    let source = "- Bribe him\n    He pockets the coin.\n- Walk away\n    You leave.\n";
+    assert!(warnings(source).is_empty());
+}
+
+// =============================================================================
+// Write-only save variables
+// =============================================================================
+
+#[test]
+fn save_assigned_but_never_read() {
+    let source = "save seen = false\nHello.\n- Look\n    set seen = true\n- Leave\n    Bye.\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "save variable 'seen' is never read");
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "save seen = false", 0))
+    );
+    assert_eq!(
+        secondary_spans(&warnings[0]),
+        [span_of(source, "set seen = true", 0)]
+    );
+}
+
+#[test]
+fn save_read_anywhere_is_not_write_only() {
+    let source =
+        "save seen = false\nHello.\n- Look\n    set seen = true\n- Leave\n    Seen: {seen}\n";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn compound_assignment_is_not_a_read() {
+    let source = "save visits = 0\nset visits += 1\nHello.\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "save variable 'visits' is never read");
+}
+
+#[test]
+fn unassigned_save_and_extern_are_not_write_only() {
+    let source = "save gold = 0\nextern name\nHello.\n";
+    assert!(warnings(source).is_empty());
+}
+
+// =============================================================================
+// Self-assignment
+// =============================================================================
+
+#[test]
+fn assignment_to_itself_has_no_effect() {
+    let source = "save gold = 10\nset gold = gold\nYou have {gold} gold.\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "assignment has no effect");
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "set gold = gold", 0))
+    );
+}
+
+#[test]
+fn assignment_from_an_expression_is_not_flagged() {
+    let source = "temp gold = 10\nset gold = gold + 0\nYou have {gold} gold.\n";
+    assert!(warnings(source).is_empty());
+}
+
+#[test]
+fn undefined_self_assignment_is_only_an_error() {
+    let diagnostics = validate("set gold = gold\n");
+    assert!(
+        diagnostics.iter().all(|d| d.severity == Severity::Error),
+        "{:#?}",
+        diagnostics
+    );
+}
+
+// =============================================================================
+// Options
+// =============================================================================
+
+#[test]
+fn warnings_can_be_disabled() {
+    let source = "- Same\n- Same\n";
+    let options = ValidateOptions {
+        warnings: false,
+        ..ValidateOptions::default()
+    };
+    assert!(validate_with_options(source, options).is_empty());
+}
+
+#[test]
+fn denied_warnings_become_errors() {
+    let source = "save seen = false\nHello.\n- Look\n    set seen = true\n- Leave\n    Bye.\n";
+    let options = ValidateOptions {
+        deny_warnings: true,
+        ..ValidateOptions::default()
+    };
+    let diagnostics = validate_with_options(source, options);
+
+    assert_eq!(diagnostics.len(), 1, "{:#?}", diagnostics);
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].message, "save variable 'seen' is never read");
+    assert!(
+        diagnostics[0]
+            .notes
+            .iter()
+            .any(|n| n.contains("warnings are denied"))
+    );
+}
+
+#[test]
+fn denied_warnings_stop_the_runtime_from_building() {
+    let source = "save seen = false\nHello.\n- Look\n    set seen = true\n- Leave\n    Bye.\n";
+    let storage: Arc<dyn VariableStorage> = Arc::new(MemoryStorage::new());
+    let host: Arc<dyn HostState> = Arc::new(EmptyHostState);
+    assert!(Runtime::new(source, Arc::clone(&storage), Arc::clone(&host)).is_ok());
+
+    let err = match RuntimeBuilder::new(storage, host)
+        .deny_warnings(true)
+        .build(source)
+    {
+        Ok(_) => panic!("a denied warning should fail the build"),
+        Err(err) => err,
+    };
+    assert!(matches!(err, BobbinError::DeniedWarnings(ref warnings) if warnings.len() == 1));
+    let diagnostics = err.into_diagnostics();
+    assert_eq!(diagnostics[0].severity, Severity::Error);
+    assert_eq!(diagnostics[0].message, "save variable 'seen' is never read");
+}
+
+// =============================================================================
+// Unreachable statements
+// =============================================================================
+
+#[test]
+fn statement_after_goto_is_unreachable() {
+    let source = "goto end\nNever shown.\nAlso never shown.\n== end ==\nShown.\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "unreachable statement");
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "Never shown.", 0))
+    );
+}
+
+#[test]
+fn statement_after_end_is_unreachable() {
+    let source = "Shown.\nend\nNever shown.\n";
+    let warnings = warnings(source);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].message, "unreachable statement");
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "Never shown.", 0))
+    );
+}
+
+#[test]
+fn end_in_text_is_a_line() {
+    let source = "end of the road\nThe end\n";
+    assert!(warnings(source).is_empty());
+}
+
+// =============================================================================
+// Long lines
+// =============================================================================
+
+fn long_line_warnings(source: &str, max_line_chars: usize) -> Vec<Diagnostic> {
+    let options = ValidateOptions {
+        max_line_chars: Some(max_line_chars),
+        ..ValidateOptions::default()
+    };
+    validate_with_options(source, options)
+}
+
+#[test]
+fn line_over_the_limit_is_flagged() {
+    let source = "Hello there\nHi there.\n";
+    let warnings = long_line_warnings(source, 10);
+
+    assert_eq!(warnings.len(), 1, "{:#?}", warnings);
+    assert_eq!(warnings[0].severity, Severity::Warning);
+    assert_eq!(
+        warnings[0].message,
+        "line exceeds 10 characters and may overflow the dialogue box"
+    );
+    assert_eq!(
+        warnings[0].primary_span(),
+        Some(span_of(source, "Hello there", 0))
+    );
+}
+
+#[test]
+fn long_line_limit_ignores_interpolations() {
+    let source = "temp name = \"Bartholomew\"\nHi, {name}!\n";
+    assert!(long_line_warnings(source, 10).is_empty());
+}
+
+#[test]
+fn long_line_limit_counts_characters_not_bytes() {
+    let source = "Ça va, été?\n";
+    assert!(long_line_warnings(source, 11).is_empty());
+    assert_eq!(long_line_warnings(source, 10).len(), 1);
+}
+
+#[test]
+fn long_lines_are_not_checked_by_default() {
+    let source = "This line is much longer than any dialogue box would ever want.\n";
+    assert!(warnings(source).is_empty());
+}
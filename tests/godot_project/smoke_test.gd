@@ -33,5 +33,88 @@ func _init():
 		quit(1)
 	print("PASS: get_variable() returned correct value")
 
+	# Test 4b: Variant <-> Value conversions round-trip through storage
+	var samples = {"an_int": 7, "a_float": 2.5, "a_bool": true, "a_string": "gold"}
+	for key in samples:
+		runtime2.set_variable(key, samples[key])
+		var got = runtime2.get_variable(key)
+		if typeof(got) != typeof(samples[key]) or got != samples[key]:
+			printerr("FAIL: " + key + " round-tripped as " + type_string(typeof(got)) + " " + str(got))
+			quit(1)
+	if runtime2.get_variable("never_declared") != null:
+		printerr("FAIL: get_variable() should return null for unknown variables")
+		quit(1)
+	# Unsupported types are rejected (logged) and leave storage unchanged
+	runtime2.set_variable("counter", Vector2(1, 2))
+	if runtime2.get_variable("counter") != 42:
+		printerr("FAIL: unsupported set_variable() overwrote storage")
+		quit(1)
+	print("PASS: save variable conversions round-trip")
+
+	# Test 5: Choices
+	var runtime3 = BobbinRuntime.from_string("Pick one\n- Left\n    You went left.\n- Right\n    You went right.\n")
+	if runtime3 == null:
+		printerr("FAIL: from_string() returned null for choice script")
+		quit(1)
+	runtime3.advance()
+	if not runtime3.is_waiting_for_choice():
+		printerr("FAIL: is_waiting_for_choice() should be true")
+		quit(1)
+	var choices = runtime3.current_choices()
+	if choices.size() != 2 or choices[0] != "Left" or choices[1] != "Right":
+		printerr("FAIL: current_choices() returned: " + str(choices))
+		quit(1)
+	print("PASS: current_choices() returned correct choices")
+
+	# Out-of-range selection logs an error and leaves the runtime waiting
+	runtime3.select_choice(5)
+	if not runtime3.is_waiting_for_choice():
+		printerr("FAIL: invalid select_choice() should keep waiting for a choice")
+		quit(1)
+	print("PASS: select_choice() rejected out-of-range index")
+
+	runtime3.select_choice(1)
+	if runtime3.current_line() != "You went right.":
+		printerr("FAIL: select_choice() led to: " + runtime3.current_line())
+		quit(1)
+	print("PASS: select_choice() followed the selected branch")
+
+	# Test 6: Flow signals
+	var runtime4 = BobbinRuntime.from_string("First\nSecond\n")
+	var seen_lines: Array = []
+	var finished: Array = []
+	runtime4.line_changed.connect(func(text): seen_lines.append(text))
+	runtime4.dialogue_finished.connect(func(): finished.append(runtime4.has_more()))
+	runtime4.advance()
+	if seen_lines != ["Second"]:
+		printerr("FAIL: line_changed delivered: " + str(seen_lines))
+		quit(1)
+	print("PASS: line_changed fired with the new line")
+	# Handlers run after the runtime updates, so has_more() is already false
+	if finished != [false]:
+		printerr("FAIL: dialogue_finished delivered: " + str(finished))
+		quit(1)
+	print("PASS: dialogue_finished fired once")
+
+	# Test 7: Signals for empty lines and dialogue that is over from the start
+	var runtime5 = BobbinRuntime.from_string_with_host("extern name\nHi\n{name}\n", {"name": ""})
+	var empty_lines: Array = []
+	runtime5.line_changed.connect(func(text): empty_lines.append(text))
+	runtime5.advance()
+	if empty_lines != [""]:
+		printerr("FAIL: line_changed for an empty line delivered: " + str(empty_lines))
+		quit(1)
+	print("PASS: line_changed fired for an empty line")
+
+	var runtime6 = BobbinRuntime.from_string("")
+	var finished_early: Array = []
+	runtime6.dialogue_finished.connect(func(): finished_early.append(true))
+	runtime6.advance()
+	runtime6.advance()
+	if finished_early != [true]:
+		printerr("FAIL: dialogue_finished for an empty script delivered: " + str(finished_early))
+		quit(1)
+	print("PASS: dialogue_finished fired once for an empty script")
+
 	print("=== All smoke tests passed ===")
 	quit(0)